@@ -0,0 +1,175 @@
+//! Arguments of the `api_mod!` macro, and of the `#[api]` attribute on API functions.
+
+use syn::{
+    Attribute, LitInt, LitStr, Path, Token,
+    meta::ParseNestedMeta,
+    parse::{Parse, ParseStream},
+};
+
+/// Version of an API module, in the form of `major.minor`.
+pub struct ApiModVersion {
+    /// Major version.
+    pub major: u16,
+    /// Minor version.
+    pub minor: u16,
+}
+
+/// Arguments of the `api_mod!` macro, e.g. `#[api_mod(version = "1.2", trace)]`.
+#[derive(Default)]
+pub struct ApiModArgs {
+    /// Version of the API module.
+    pub version: Option<ApiModVersion>,
+    /// Whether calls to the API functions are traced.
+    pub trace: bool,
+    /// Whether the API module can be implemented more than once, with calls fanned out to all implementations.
+    pub multi: bool,
+}
+
+impl ApiModArgs {
+    /// Parse a single argument, used with [`syn::meta::parser`].
+    pub fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("version") {
+            let lit: LitStr = meta.value()?.parse()?;
+            let version = lit.value();
+            let parsed = version
+                .split_once('.')
+                .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)));
+
+            match parsed {
+                Some((major, minor)) => {
+                    self.version = Some(ApiModVersion { major, minor });
+                    Ok(())
+                }
+                None => Err(syn::Error::new(
+                    lit.span(),
+                    "expected a version in the form of `major.minor`",
+                )),
+            }
+        } else if meta.path.is_ident("trace") {
+            self.trace = true;
+            Ok(())
+        } else if meta.path.is_ident("multi") {
+            self.multi = true;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported `api_mod` argument"))
+        }
+    }
+}
+
+/// Arguments of the `api_mod_impl!` macro, e.g. `#[api_mod_impl(path::to::api_mod, multi, order = 10)]` or
+/// `#[api_mod_impl(path::to::api_mod, partial)]`.
+pub struct ApiModImplArgs {
+    /// Path to the implemented API module.
+    pub implementee: Path,
+    /// Whether the implemented API module is a `multi` API module.
+    pub multi: bool,
+    /// Order of the implementation among the ones of a `multi` API module, lower ones are called first.
+    pub order: Option<i32>,
+    /// Whether the implementation covers only a part of the API functions.
+    pub partial: bool,
+}
+
+impl Parse for ApiModImplArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Self {
+            implementee: input.parse()?,
+            multi: false,
+            order: None,
+            partial: false,
+        };
+
+        while input.parse::<Option<Token![,]>>()?.is_some() {
+            if input.is_empty() {
+                break;
+            }
+            let path: Path = input.parse()?;
+            if path.is_ident("multi") {
+                args.multi = true;
+            } else if path.is_ident("partial") {
+                args.partial = true;
+            } else if path.is_ident("order") {
+                input.parse::<Token![=]>()?;
+                let negative = input.parse::<Option<Token![-]>>()?.is_some();
+                let order: i32 = input.parse::<LitInt>()?.base10_parse()?;
+                args.order = Some(if negative { -order } else { order });
+            } else {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "unsupported `api_mod_impl` argument",
+                ));
+            }
+        }
+
+        if !input.is_empty() {
+            return Err(input.error("expected `,`"));
+        }
+        if args.order.is_some() && !args.multi {
+            return Err(input.error("`order` is only allowed with `multi`"));
+        }
+        if args.partial && args.multi {
+            return Err(input.error("`partial` is not allowed with `multi`"));
+        }
+
+        Ok(args)
+    }
+}
+
+/// Arguments of the `#[api]` attribute on an API function, e.g. `#[api(max_duration = "1ms")]`.
+#[derive(Default)]
+pub struct ApiFnArgs {
+    /// Maximum expected duration of a call to the API function, in nanoseconds.
+    pub max_duration_nanos: Option<u64>,
+}
+
+impl ApiFnArgs {
+    /// Extract the arguments from the `#[api]` attributes of an API function, returning the remaining attributes.
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<(Self, Vec<Attribute>)> {
+        let mut args = Self::default();
+        let mut remaining = vec![];
+
+        for attr in attrs {
+            if attr.path().is_ident("api") {
+                attr.parse_nested_meta(|meta| args.parse(meta))?;
+            } else {
+                remaining.push(attr.clone());
+            }
+        }
+
+        Ok((args, remaining))
+    }
+
+    /// Parse a single argument, used with [`Attribute::parse_nested_meta`].
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("max_duration") {
+            let lit: LitStr = meta.value()?.parse()?;
+            match parse_duration_nanos(&lit.value()) {
+                Some(nanos) => {
+                    self.max_duration_nanos = Some(nanos);
+                    Ok(())
+                }
+                None => Err(syn::Error::new(
+                    lit.span(),
+                    "expected a duration like `500ns`, `100us`, `1ms` or `1s`",
+                )),
+            }
+        } else {
+            Err(meta.error("unsupported `api` argument"))
+        }
+    }
+}
+
+/// Parse a duration like `100us` into nanoseconds.
+fn parse_duration_nanos(duration: &str) -> Option<u64> {
+    let unit_start = duration.find(|c: char| !c.is_ascii_digit())?;
+    let (value, unit) = duration.split_at(unit_start);
+    let value: u64 = value.parse().ok()?;
+    let scale = match unit {
+        "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        _ => return None,
+    };
+    value.checked_mul(scale)
+}
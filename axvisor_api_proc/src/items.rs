@@ -3,29 +3,102 @@
 //! `api_mod!` and `api_mod_impl!` have very similar structures.
 
 use syn::{
-    Attribute, Block, Ident, Item, Signature, Token, Visibility, braced,
+    Attribute, Block, Expr, Ident, Item, Meta, Signature, Token, Type, Visibility, braced,
     parse::{Parse, ParseStream},
+    parse_quote,
     token::Brace,
 };
 
+/// The body of an [API function](`ItemApiFn`), which is a semicolon for the definition, and a block for the
+/// implementation.
+pub trait ApiFnBody: Parse {
+    /// Check whether the upcoming `const` item with the given attributes is an API constant.
+    fn is_api_const(attrs: &[Attribute], input: ParseStream) -> bool;
+
+    /// Parse the rest of an API constant after its type into the body of its accessor returning `ty`.
+    fn parse_const_body(input: ParseStream, ty: &Type) -> syn::Result<Self>;
+}
+
+impl ApiFnBody for Token![;] {
+    /// In the definition, API constants are the `const` items without a value.
+    fn is_api_const(_attrs: &[Attribute], input: ParseStream) -> bool {
+        let parse_header = |input: ParseStream| {
+            input.parse::<Token![const]>()?;
+            input.parse::<Ident>()?;
+            input.parse::<Token![:]>()?;
+            input.parse::<Type>()
+        };
+        let forked = input.fork();
+        parse_header(&forked).is_ok() && forked.peek(Token![;])
+    }
+
+    fn parse_const_body(input: ParseStream, _ty: &Type) -> syn::Result<Self> {
+        input.parse()
+    }
+}
+
+impl ApiFnBody for Block {
+    /// In the implementation, API constants are the `const` items marked with `#[api]`.
+    fn is_api_const(attrs: &[Attribute], _input: ParseStream) -> bool {
+        attrs.iter().any(is_bare_api_attr)
+    }
+
+    fn parse_const_body(input: ParseStream, ty: &Type) -> syn::Result<Self> {
+        input.parse::<Token![=]>()?;
+        let value: Expr = input.parse()?;
+        input.parse::<Token![;]>()?;
+        // Evaluated at compile time, and returned by value without requiring `Copy`.
+        Ok(parse_quote! {{
+            const VALUE: #ty = #value;
+            VALUE
+        }})
+    }
+}
+
+/// Check whether an attribute is a bare `#[api]`, which marks API constants.
+fn is_bare_api_attr(attr: &Attribute) -> bool {
+    matches!(&attr.meta, Meta::Path(path) if path.is_ident("api"))
+}
+
 /// An API function, defined with the `extern fn` syntax. It represents both the definition and the implementation. For
 /// the definition, `T` is `Token![;]`, and for the implementation, `T` is `syn::Block`.
-pub struct ItemApiFn<T: Parse> {
+///
+/// API constants, defined with `const FOO: T;` and implemented with `#[api] const FOO: T = expr;`, are also parsed into
+/// API functions, i.e. their accessors `fn foo() -> T`.
+pub struct ItemApiFn<T: ApiFnBody> {
     /// Attributes of the function.
     pub attrs: Vec<Attribute>,
     #[expect(dead_code)]
-    /// The `extern` keyword.
-    pub extern_token: Token![extern],
+    /// The `extern` keyword, absent for API constants.
+    pub extern_token: Option<Token![extern]>,
     /// The function signature.
     pub sig: Signature,
     /// The body of the function, or a semicolon.
     pub body: T,
 }
 
-impl<T: Parse> Parse for ItemApiFn<T> {
+impl<T: ApiFnBody> Parse for ItemApiFn<T> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let attrs = input.call(Attribute::parse_outer)?;
-        let extern_token = input.parse()?;
+        let mut attrs = input.call(Attribute::parse_outer)?;
+
+        if input.peek(Token![const]) {
+            input.parse::<Token![const]>()?;
+            let name: Ident = input.parse()?;
+            input.parse::<Token![:]>()?;
+            let ty: Type = input.parse()?;
+            let body = T::parse_const_body(input, &ty)?;
+
+            attrs.retain(|attr| !is_bare_api_attr(attr));
+            let accessor = Ident::new(&name.to_string().to_lowercase(), name.span());
+            return Ok(Self {
+                attrs,
+                extern_token: None,
+                sig: parse_quote! { fn #accessor() -> #ty },
+                body,
+            });
+        }
+
+        let extern_token = Some(input.parse()?);
         let sig = input.parse()?;
         let body = input.parse()?;
 
@@ -40,17 +113,20 @@ impl<T: Parse> Parse for ItemApiFn<T> {
 
 /// An item in a [`ItemApiMod`], which can be a regular [`Item`] or an [API function](`ItemApiFn`). As `ItemApiFn`, this
 /// enum represents both the definition and the implementation.
-pub enum ApiModItem<T: Parse> {
+pub enum ApiModItem<T: ApiFnBody> {
     Regular(Item),
     ApiFn(ItemApiFn<T>),
 }
 
-impl<T: Parse> Parse for ApiModItem<T> {
+impl<T: ApiFnBody> Parse for ApiModItem<T> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         // Attributes will be parsed twice, but it's not a big deal.
         let forked = input.fork();
-        let _ = forked.call(Attribute::parse_outer)?;
-        let is_api_fn = forked.peek(Token![extern]) && forked.peek2(Token![fn]);
+        let attrs = forked.call(Attribute::parse_outer)?;
+        let is_api_fn = (forked.peek(Token![extern]) && forked.peek2(Token![fn]))
+            || (forked.peek(Token![const])
+                && forked.peek2(Ident)
+                && T::is_api_const(&attrs, &forked));
         drop(forked);
 
         if is_api_fn {
@@ -63,7 +139,7 @@ impl<T: Parse> Parse for ApiModItem<T> {
 
 /// A module that contains the definition or implementation of API functions, and marked by `#[api_mod]` or
 /// `#[api_mod_impl]`.
-pub struct ItemApiMod<T: Parse> {
+pub struct ItemApiMod<T: ApiFnBody> {
     /// Attributes of the module.
     pub attrs: Vec<Attribute>,
     /// Visibility of the module.
@@ -79,7 +155,7 @@ pub struct ItemApiMod<T: Parse> {
     pub items: Vec<ApiModItem<T>>,
 }
 
-impl<T: Parse> Parse for ItemApiMod<T> {
+impl<T: ApiFnBody> Parse for ItemApiMod<T> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let attrs = input.call(Attribute::parse_outer)?;
         let vis = input.parse()?;
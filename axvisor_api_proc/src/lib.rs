@@ -2,10 +2,12 @@ use proc_macro::TokenStream as TokenStream1;
 use proc_macro_crate::{FoundCrate, crate_name};
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, quote_spanned};
-use syn::{FnArg, Ident, Path, Token, spanned::Spanned};
+use syn::{Attribute, FnArg, Ident, Path, ReturnType, Token, Type, spanned::Spanned};
 
+mod args;
 mod items;
 
+use args::{ApiFnArgs, ApiModArgs, ApiModImplArgs, ApiModVersion};
 use items::{ApiModItem, ItemApiFn, ItemApiModDef, ItemApiModImpl};
 
 /// Find the path to the `axvisor_api` crate.
@@ -31,6 +33,11 @@ fn capitalize_first_letter(s: &str) -> String {
     }
 }
 
+/// Convert a snake case string to camel case.
+fn to_camel_case(s: &str) -> String {
+    s.split('_').map(capitalize_first_letter).collect()
+}
+
 /// Get the name of the API trait for an API module.
 fn get_api_trait_name(module_name: impl AsRef<str>, span: Span) -> Ident {
     let module_name = module_name.as_ref();
@@ -38,11 +45,56 @@ fn get_api_trait_name(module_name: impl AsRef<str>, span: Span) -> Ident {
     Ident::new(&trait_name, span)
 }
 
+/// Get the name of the symbol exported by every implementation of an API module, holding the version of the API module
+/// which the implementation is built against.
+fn get_api_version_symbol_name(trait_ident: &Ident) -> String {
+    // `.` never appears in the symbol names of API functions, so there are no conflicts.
+    format!("__{trait_ident}.api_version")
+}
+
+/// Get the `cfg` attributes of an API function.
+fn get_cfg_attrs(attrs: &[Attribute]) -> Vec<&Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .collect()
+}
+
+/// Combine the `cfg` attributes of an API function into a single predicate, or `None` if there are no `cfg`
+/// attributes.
+fn get_cfg_predicate(attrs: &[Attribute]) -> syn::Result<Option<TokenStream>> {
+    let predicates = get_cfg_attrs(attrs)
+        .into_iter()
+        .map(|attr| attr.meta.require_list().map(|list| list.tokens.clone()))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(match predicates.as_slice() {
+        [] => None,
+        [predicate] => Some(predicate.clone()),
+        _ => Some(quote! { all(#(#predicates),*) }),
+    })
+}
+
 /// Get the extra doc comments for an API module definition.
+///
+/// API functions gated by `cfg` attributes are listed only if they are available in the current build configuration,
+/// and are mentioned as unavailable otherwise.
 fn get_api_mod_def_extra_doc_comments(
     mod_ident: &Ident,
+    version: Option<&ApiModVersion>,
     api_fn_items: &Vec<&ItemApiFn<Token![;]>>,
 ) -> TokenStream {
+    let version_hint = version.map(|v| {
+        let hint = format!(
+            "This module is of API version {}.{}, see [`check_version`]({}::check_version).",
+            v.major, v.minor, mod_ident
+        );
+        quote! {
+            #[doc = ""]
+            #[doc = #hint]
+        }
+    });
+
     if api_fn_items.is_empty() {
         return quote! {
             #[doc = ""]
@@ -57,28 +109,98 @@ fn get_api_mod_def_extra_doc_comments(
         api_fn_count,
         if api_fn_count == 1 { "" } else { "s" }
     );
-    let api_fn_list = api_fn_items
-        .iter()
-        .map(|f| format!("- [`{0}`]({1}::{0})", f.sig.ident.to_string(), mod_name));
+    let mut api_fn_list = quote! {};
+    for f in api_fn_items {
+        let fn_name = f.sig.ident.to_string();
+        let entry = format!("- [`{fn_name}`]({mod_name}::{fn_name})");
+        match get_cfg_predicate(&f.attrs) {
+            Ok(None) => api_fn_list.extend(quote! { #[doc = #entry] }),
+            Ok(Some(predicate)) => {
+                let unavailable_entry =
+                    format!("- `{fn_name}` (not available in this build configuration)");
+                api_fn_list.extend(quote! {
+                    #[cfg_attr(#predicate, doc = #entry)]
+                    #[cfg_attr(not(#predicate), doc = #unavailable_entry)]
+                });
+            }
+            Err(err) => return err.to_compile_error(),
+        }
+    }
 
     quote! {
+        #version_hint
         #[doc = ""]
         #[doc = #api_fn_count_hint]
-        #(
-            #[doc = #api_fn_list]
-        )*
+        #api_fn_list
     }
 }
 
-fn get_api_fn_def_extra_doc_comments() -> TokenStream {
+/// Get the version-related items for an API module definition.
+///
+/// The version is always recorded in a hidden constant, which is exported by the implementations. For versioned API
+/// modules, the public `MODULE_API_VERSION` constant, and the `implemented_version` and `check_version` helpers are
+/// also generated.
+fn get_api_mod_def_version_items(
+    version: Option<&ApiModVersion>,
+    trait_ident: &Ident,
+    axvisor_api_path: &TokenStream,
+) -> TokenStream {
+    let version_type = quote! { #axvisor_api_path::__priv::ApiVersion };
+    let Some(ApiModVersion { major, minor }) = version else {
+        return quote! {
+            #[doc(hidden)]
+            pub const __AXVISOR_API_MODULE_VERSION: #version_type = #version_type::UNVERSIONED;
+        };
+    };
+
+    let version_symbol_name = get_api_version_symbol_name(trait_ident);
+
+    quote! {
+        /// Version of this API module which the current component is built against.
+        pub const MODULE_API_VERSION: #version_type = #version_type::new(#major, #minor);
+        #[doc(hidden)]
+        pub const __AXVISOR_API_MODULE_VERSION: #version_type = MODULE_API_VERSION;
+
+        /// Get the version of this API module which the implementation is built against.
+        pub fn implemented_version() -> #version_type {
+            unsafe extern "Rust" {
+                #[link_name = #version_symbol_name]
+                static IMPLEMENTED_VERSION: #version_type;
+            }
+            unsafe { IMPLEMENTED_VERSION }
+        }
+
+        /// Check whether the implementation of this API module is compatible with [`MODULE_API_VERSION`].
+        ///
+        /// Components built separately against a different version of this API module should call this function at
+        /// initialization, before calling any other API function in this module.
+        pub fn check_version() -> bool {
+            MODULE_API_VERSION.is_satisfied_by(implemented_version())
+        }
+    }
+}
+
+fn get_api_fn_def_extra_doc_comments(args: &ApiFnArgs) -> TokenStream {
+    let max_duration_hint = args.max_duration_nanos.map(|nanos| {
+        let hint = format!(
+            "Calls to this function are expected to finish in {nanos} ns, slower calls are reported through \
+             the `diag` API if the `watchdog` feature of `axvisor_api` is enabled."
+        );
+        quote! {
+            #[doc = ""]
+            #[doc = #hint]
+        }
+    });
+
     quote! {
         #[doc = ""]
         #[doc = "This function is an API function and **should be implemented somewhere**."]
+        #max_duration_hint
     }
 }
 
 /// Process an API module definition.
-fn process_api_mod_def(module: ItemApiModDef) -> TokenStream {
+fn process_api_mod_def(args: ApiModArgs, module: ItemApiModDef) -> TokenStream {
     let attrs = &module.attrs;
     let vis = &module.vis;
     let mod_token = &module.mod_token;
@@ -95,7 +217,25 @@ fn process_api_mod_def(module: ItemApiModDef) -> TokenStream {
         }
     }
 
-    let extra_doc_comments = get_api_mod_def_extra_doc_comments(&mod_ident, &api_fn_items);
+    let version = args.version.as_ref();
+    let trace = args.trace;
+    let multi = args.multi;
+    if multi {
+        if version.is_some() {
+            return quote! { compile_error!("`multi` API modules cannot be versioned"); };
+        }
+        if let Some(item) = api_fn_items
+            .iter()
+            .find(|item| !matches!(item.sig.output, ReturnType::Default))
+        {
+            return syn::Error::new_spanned(
+                &item.sig.output,
+                "API functions in `multi` API modules cannot return values",
+            )
+            .to_compile_error();
+        }
+    }
+    let extra_doc_comments = get_api_mod_def_extra_doc_comments(mod_ident, version, &api_fn_items);
 
     if api_fn_items.is_empty() {
         return quote! {
@@ -113,7 +253,12 @@ fn process_api_mod_def(module: ItemApiModDef) -> TokenStream {
     let trait_ident = get_api_trait_name(mod_ident.to_string(), mod_token.span());
     let api_fn_attrs = api_fn_items
         .iter()
-        .map(|item| &item.attrs)
+        .map(|item| {
+            item.attrs
+                .iter()
+                .filter(|attr| !attr.path().is_ident("api"))
+                .collect::<Vec<_>>()
+        })
         .collect::<Vec<_>>();
     let api_fn_signatures = api_fn_items
         .iter()
@@ -129,10 +274,20 @@ fn process_api_mod_def(module: ItemApiModDef) -> TokenStream {
         }
     };
 
+    let version_items = get_api_mod_def_version_items(version, &trait_ident, &axvisor_api_path);
+    let defined_fn_markers = get_api_mod_def_fn_markers(&api_fn_items, &axvisor_api_path);
+    let coverage_fn = get_api_mod_def_coverage_fn(&api_fn_items, &trait_ident);
+    let client_items = get_api_mod_def_client_items(mod_ident, &api_fn_items);
+    let entries_of_fn = (cfg!(feature = "dynamic") && !multi)
+        .then(|| get_api_mod_def_entries_of_fn(&api_fn_items, &trait_ident, &axvisor_api_path));
+
     // Generate the API function implementations
     let mut api_fn_impls = quote! {};
     for api_fn_item in api_fn_items {
-        let attrs = &api_fn_item.attrs;
+        let (fn_args, attrs) = match ApiFnArgs::from_attrs(&api_fn_item.attrs) {
+            Ok(result) => result,
+            Err(err) => return err.to_compile_error(),
+        };
         let sig = &api_fn_item.sig;
         let fn_name = &sig.ident;
         let args = &sig
@@ -140,21 +295,74 @@ fn process_api_mod_def(module: ItemApiModDef) -> TokenStream {
             .iter()
             .map(|arg| match arg {
                 FnArg::Receiver(_) => panic!("API functions cannot have self arguments"),
-                FnArg::Typed(pat) => &pat.pat,
+                FnArg::Typed(pat) => &*pat.pat,
             })
             .collect::<Vec<_>>();
 
-        let extra_doc_comments = get_api_fn_def_extra_doc_comments();
+        let extra_doc_comments = get_api_fn_def_extra_doc_comments(&fn_args);
+        let call = if multi {
+            let checked_fn_name = Ident::new(&format!("{fn_name}_checked"), fn_name.span());
+            let unsafety = &sig.unsafety;
+            quote! {
+                let _ = #unsafety { #checked_fn_name(#(#args),*) };
+            }
+        } else if cfg!(feature = "dynamic") {
+            get_api_fn_dynamic_call(sig, args)
+        } else {
+            quote! {
+                #axvisor_api_path::__priv::crate_interface::call_interface!(
+                    #trait_ident::#fn_name, #(#args),*
+                )
+            }
+        };
+
+        let body = match fn_args.max_duration_nanos {
+            Some(max_duration_nanos) => quote! {
+                let __axvisor_api_watchdog = #axvisor_api_path::__priv::ApiCallWatchdog::start(
+                    module_path!(),
+                    stringify!(#fn_name),
+                    #max_duration_nanos,
+                );
+                let __axvisor_api_ret = #call;
+                __axvisor_api_watchdog.finish();
+                __axvisor_api_ret
+            },
+            None => call,
+        };
+        let body = if trace {
+            quote! {
+                let __axvisor_api_tracer = #axvisor_api_path::__priv::ApiCallTracer::enter(
+                    module_path!(),
+                    stringify!(#fn_name),
+                    &#axvisor_api_path::__priv::ApiCallArgs(&[
+                        #((stringify!(#args), &#args as &dyn ::core::fmt::Debug)),*
+                    ]),
+                );
+                let __axvisor_api_ret = { #body };
+                __axvisor_api_tracer.exit();
+                __axvisor_api_ret
+            }
+        } else {
+            body
+        };
+        let track_caller =
+            (trace || fn_args.max_duration_nanos.is_some()).then(|| quote! { #[track_caller] });
 
         api_fn_impls.extend(quote! {
             #(#attrs)*
             #extra_doc_comments
+            #track_caller
             pub #sig {
-                #axvisor_api_path::__priv::crate_interface::call_interface!(
-                    #trait_ident::#fn_name, #(#args),*
-                )
+                #body
             }
         });
+        api_fn_impls.extend(get_api_fn_checked_variant(
+            api_fn_item,
+            args,
+            &trait_ident,
+            multi,
+            &axvisor_api_path,
+        ));
     }
 
     quote! {
@@ -163,15 +371,304 @@ fn process_api_mod_def(module: ItemApiModDef) -> TokenStream {
         #vis #mod_token #mod_ident {
             #(#regular_items)*
 
+            #version_items
+
             #api_fn_impls
 
             #trait_def
+
+            #[doc(hidden)]
+            pub const __AXVISOR_API_MODULE_MULTI: bool = #multi;
+
+            #client_items
+
+            #defined_fn_markers
+
+            #coverage_fn
+
+            #entries_of_fn
+        }
+    }
+}
+
+/// Get the call to an API function through the runtime registry, used with the `dynamic` feature.
+///
+/// The call goes through the `_checked` variant, and panics if the API function is not implemented.
+fn get_api_fn_dynamic_call(sig: &syn::Signature, args: &[&syn::Pat]) -> TokenStream {
+    let fn_name = &sig.ident;
+    let checked_fn_name = Ident::new(&format!("{fn_name}_checked"), fn_name.span());
+    let unsafety = &sig.unsafety;
+    let ok_arm = match &sig.output {
+        ReturnType::Type(_, ty) if matches!(**ty, Type::Never(_)) => {
+            quote! { Ok(ret) => match ret {} }
+        }
+        _ => quote! { Ok(ret) => ret },
+    };
+
+    quote! {
+        match #unsafety { #checked_fn_name(#(#args),*) } {
+            #ok_arm,
+            Err(_) => panic!("API function `{}::{}` is not implemented", module_path!(), stringify!(#fn_name)),
+        }
+    }
+}
+
+/// Get the `_checked` variant of an API function, which returns `Err(ApiNotImplemented)` instead of failing the link if
+/// the API function is not implemented in the final binary.
+///
+/// The implementation is looked up in the registry of API functions, and called through a function pointer.
+///
+/// For `multi` API modules, all implementations are called in order, and `Err(ApiNotImplemented)` is returned if there
+/// are none.
+fn get_api_fn_checked_variant(
+    api_fn_item: &ItemApiFn<Token![;]>,
+    args: &[&syn::Pat],
+    trait_ident: &Ident,
+    multi: bool,
+    axvisor_api_path: &TokenStream,
+) -> TokenStream {
+    let cfg_attrs = get_cfg_attrs(&api_fn_item.attrs);
+    let sig = &api_fn_item.sig;
+    let fn_name = &sig.ident;
+    let api_name = trait_ident.to_string();
+    let fn_name_str = fn_name.to_string();
+
+    let lifetimes = sig.generics.lifetimes();
+    let unsafety = &sig.unsafety;
+    let arg_types = sig.inputs.iter().filter_map(|arg| match arg {
+        FnArg::Receiver(_) => None,
+        FnArg::Typed(pat) => Some(&pat.ty),
+    });
+    let output = &sig.output;
+    let ret_type = match output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) if matches!(**ty, Type::Never(_)) => {
+            quote! { ::core::convert::Infallible }
+        }
+        ReturnType::Type(_, ty) => quote! { #ty },
+    };
+
+    let mut checked_sig = sig.clone();
+    checked_sig.ident = Ident::new(&format!("{fn_name}_checked"), fn_name.span());
+    checked_sig.output = syn::parse_quote! {
+        -> ::core::result::Result<#ret_type, #axvisor_api_path::__priv::ApiNotImplemented>
+    };
+    let fn_ptr = quote! {
+        // SAFETY: the registry only holds pointers to the implementations of API functions, which are of this type.
+        let __axvisor_api_fn = unsafe {
+            ::core::mem::transmute::<*const (), for<#(#lifetimes),*> #unsafety fn(#(#arg_types),*) #output>(
+                __axvisor_api_ptr,
+            )
+        };
+    };
+
+    let (doc, body) = if multi {
+        let doc = format!(
+            "Call all implementations of [`{fn_name}`], or return `Err(ApiNotImplemented)` if there are none in the \
+             final binary."
+        );
+        let body = quote! {
+            let __axvisor_api_count = #axvisor_api_path::__priv::for_each_api_fn(
+                #api_name,
+                #fn_name_str,
+                |__axvisor_api_ptr| {
+                    #fn_ptr
+                    #unsafety { __axvisor_api_fn(#(::core::clone::Clone::clone(&#args)),*) }
+                },
+            );
+            if __axvisor_api_count > 0 {
+                ::core::result::Result::Ok(())
+            } else {
+                ::core::result::Result::Err(#axvisor_api_path::__priv::ApiNotImplemented)
+            }
+        };
+        (doc, body)
+    } else {
+        let doc = format!(
+            "Call [`{fn_name}`], or return `Err(ApiNotImplemented)` if it is not implemented in the final binary."
+        );
+        let body = quote! {
+            static __AXVISOR_API_CACHE: #axvisor_api_path::__priv::ApiFnCache =
+                #axvisor_api_path::__priv::ApiFnCache::new();
+            match __AXVISOR_API_CACHE.lookup(#api_name, #fn_name_str) {
+                Some(__axvisor_api_ptr) => {
+                    #fn_ptr
+                    ::core::result::Result::Ok(#unsafety { __axvisor_api_fn(#(#args),*) })
+                }
+                None => ::core::result::Result::Err(#axvisor_api_path::__priv::ApiNotImplemented),
+            }
+        };
+        (doc, body)
+    };
+
+    quote! {
+        #(#cfg_attrs)*
+        #[doc = #doc]
+        #[allow(unreachable_code)]
+        pub #checked_sig {
+            #body
+        }
+    }
+}
+
+/// Get the client trait of an API module, e.g. `MemoryApi`, and the zero-sized `Api` implementing it by calling the API
+/// functions, so that components can be generic over the client trait, and be tested with other implementations.
+fn get_api_mod_def_client_items(
+    mod_ident: &Ident,
+    api_fn_items: &[&ItemApiFn<Token![;]>],
+) -> TokenStream {
+    let client_trait_ident = Ident::new(
+        &format!("{}Api", to_camel_case(&mod_ident.to_string())),
+        mod_ident.span(),
+    );
+    let trait_doc = "The APIs in this module as a trait, with which components can be generic over the \
+                     implementation of the APIs. [`Api`] implements it by calling the API functions in this module.";
+    let impl_doc = format!(
+        "Implementation of [`{client_trait_ident}`] calling the API functions in this module."
+    );
+
+    let mut trait_fns = quote! {};
+    let mut impl_fns = quote! {};
+    for item in api_fn_items {
+        let attrs = item
+            .attrs
+            .iter()
+            .filter(|attr| !attr.path().is_ident("api"));
+        let cfg_attrs = get_cfg_attrs(&item.attrs);
+        let sig = &item.sig;
+        let fn_name = &sig.ident;
+        let unsafety = &sig.unsafety;
+        let args = sig.inputs.iter().filter_map(|arg| match arg {
+            FnArg::Receiver(_) => None,
+            FnArg::Typed(pat) => Some(&pat.pat),
+        });
+
+        trait_fns.extend(quote! {
+            #(#attrs)*
+            #sig;
+        });
+        impl_fns.extend(quote! {
+            #(#cfg_attrs)*
+            #[inline]
+            #sig {
+                #unsafety { #fn_name(#(#args),*) }
+            }
+        });
+    }
+
+    quote! {
+        #[doc = #trait_doc]
+        pub trait #client_trait_ident {
+            #trait_fns
+        }
+
+        #[doc = #impl_doc]
+        #[derive(Debug, Clone, Copy, Default)]
+        pub struct Api;
+
+        impl #client_trait_ident for Api {
+            #impl_fns
+        }
+    }
+}
+
+/// Get the function referencing the link-time symbols of all API functions in an API module definition.
+///
+/// Partial implementations reference this function, so that the final binary fails to link with an undefined symbol
+/// `__Axvisor<Module>ApiTrait_<function>` if the partial implementations do not cover all API functions together.
+fn get_api_mod_def_coverage_fn(
+    api_fn_items: &[&ItemApiFn<Token![;]>],
+    trait_ident: &Ident,
+) -> TokenStream {
+    let extern_mod_ident = Ident::new(&format!("__{trait_ident}_mod"), trait_ident.span());
+    let references = api_fn_items.iter().map(|item| {
+        let cfg_attrs = get_cfg_attrs(&item.attrs);
+        let extern_fn_ident = Ident::new(
+            &format!("__{trait_ident}_{}", item.sig.ident),
+            item.sig.ident.span(),
+        );
+        quote! {
+            #(#cfg_attrs)*
+            ::core::hint::black_box(#extern_mod_ident::#extern_fn_ident as *const ());
+        }
+    });
+
+    quote! {
+        #[doc(hidden)]
+        pub fn __axvisor_api_require_all_fns() {
+            #(#references)*
+        }
+    }
+}
+
+/// Get the function enumerating the registry entries of an arbitrary implementation of the API trait, used with the
+/// `dynamic` feature to override implementations at runtime, see `axvisor_api::testing`.
+fn get_api_mod_def_entries_of_fn(
+    api_fn_items: &[&ItemApiFn<Token![;]>],
+    trait_ident: &Ident,
+    axvisor_api_path: &TokenStream,
+) -> TokenStream {
+    let api_name = trait_ident.to_string();
+    let entries = api_fn_items.iter().map(|item| {
+        let cfg_attrs = get_cfg_attrs(&item.attrs);
+        let fn_name = &item.sig.ident;
+        quote! {
+            #(#cfg_attrs)*
+            f(#axvisor_api_path::__priv::ApiFnEntry {
+                api: #api_name,
+                function: stringify!(#fn_name),
+                ptr: <T as #trait_ident>::#fn_name as *const (),
+                module: ::core::any::type_name::<T>(),
+                order: 0,
+            });
+        }
+    });
+
+    quote! {
+        #[doc(hidden)]
+        pub fn __axvisor_api_for_each_entry_of<T: #trait_ident>(
+            f: &mut dyn FnMut(#axvisor_api_path::__priv::ApiFnEntry),
+        ) {
+            #(#entries)*
+        }
+    }
+}
+
+/// Get the markers of the API functions in an API module definition.
+///
+/// Every API function gets a marker type named after it, which implements `ApiFnDefined` only if the function is
+/// available in the current build configuration, i.e. under the same `cfg` attributes as the function. Implementations
+/// check the markers of the functions they provide, so that providing a function which is `cfg`'d out in the definition
+/// is reported with a clear error, instead of an obscure one about the hidden API trait.
+fn get_api_mod_def_fn_markers(
+    api_fn_items: &[&ItemApiFn<Token![;]>],
+    axvisor_api_path: &TokenStream,
+) -> TokenStream {
+    let markers = api_fn_items.iter().map(|item| {
+        let fn_name = &item.sig.ident;
+        let cfg_attrs = get_cfg_attrs(&item.attrs);
+        quote! {
+            pub struct #fn_name;
+            #(#cfg_attrs)*
+            #[diagnostic::do_not_recommend]
+            impl #axvisor_api_path::__priv::ApiFnDefined for #fn_name {}
+        }
+    });
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        pub mod __axvisor_api_fns {
+            #(#markers)*
         }
     }
 }
 
 /// Reuses the path to the module to be implemented, to make sure the `impl` block can find the correct trait.
-fn get_implementee_reuse_ident(implementee: &Path) -> Ident {
+///
+/// The name of the implementing module is included, as a module can be implemented more than once in the same parent
+/// module.
+fn get_implementee_reuse_ident(implementee: &Path, mod_ident: &Ident) -> Ident {
     let mut ident = String::from(if implementee.leading_colon.is_some() {
         "__axvisor_api_implementee_abs"
     } else {
@@ -182,12 +679,33 @@ fn get_implementee_reuse_ident(implementee: &Path) -> Ident {
         ident.push('_');
         ident.push_str(seg.ident.to_string().as_str());
     }
+    ident.push_str("_for_");
+    ident.push_str(mod_ident.to_string().as_str());
 
     Ident::new(&ident, implementee.span())
 }
 
+/// Get the name of the symbol exported by every implementation of an API module.
+///
+/// The symbol is unique per API module, so if more than one `api_mod_impl` of the same API module are linked together,
+/// the linker reports a duplicate symbol with this self-explanatory name, along with the locations of all the
+/// implementing modules.
+fn get_impl_marker_symbol_name(implementee_name: &str, trait_ident: &Ident) -> String {
+    format!(
+        "axvisor_api: duplicate `#[api_mod_impl]` of API module `{implementee_name}` ({trait_ident}), only one \
+         implementation is allowed"
+    )
+}
+
 /// Process an API module implementation.
-fn process_api_mod_impl(implementee: Path, input: ItemApiModImpl) -> TokenStream {
+fn process_api_mod_impl(args: ApiModImplArgs, input: ItemApiModImpl) -> TokenStream {
+    let ApiModImplArgs {
+        implementee,
+        multi,
+        order,
+        partial,
+    } = args;
+    let order = order.unwrap_or(0);
     let attrs = &input.attrs;
     let vis = &input.vis;
     let mod_token = &input.mod_token;
@@ -200,7 +718,10 @@ fn process_api_mod_impl(implementee: Path, input: ItemApiModImpl) -> TokenStream
     let implementee_trait_ident = get_api_trait_name(&implementee_name, implementee.span());
     // we should reuse the implementee mod path besides the implementing mod, to make sure the `impl` block can find
     // the corrent trait.
-    let implementee_reuse_ident = get_implementee_reuse_ident(&implementee);
+    let implementee_reuse_ident = get_implementee_reuse_ident(&implementee, mod_ident);
+    let impl_marker_symbol_name =
+        get_impl_marker_symbol_name(&implementee_name, &implementee_trait_ident);
+    let version_symbol_name = get_api_version_symbol_name(&implementee_trait_ident);
 
     let axvisor_api_path = find_axvisor_api_crate();
 
@@ -214,17 +735,165 @@ fn process_api_mod_impl(implementee: Path, input: ItemApiModImpl) -> TokenStream
     }
 
     let mut api_fn_impls = TokenStream::new();
+    let mut api_fn_checks = TokenStream::new();
+    let mut api_fn_entries = TokenStream::new();
+    let mut api_fn_registrations = TokenStream::new();
+    let mut api_fn_exports = TokenStream::new();
+    let api_name = implementee_trait_ident.to_string();
     for api_fn_item in api_fn_items {
         let attrs = &api_fn_item.attrs;
+        let cfg_attrs = get_cfg_attrs(attrs);
         let sig = &api_fn_item.sig;
+        let fn_name = &sig.ident;
         let body = &api_fn_item.body;
 
         api_fn_impls.extend(quote! {
             #(#attrs)*
             #sig #body
         });
+        if partial {
+            let unsafety = &sig.unsafety;
+            let args = sig.inputs.iter().filter_map(|arg| match arg {
+                FnArg::Receiver(_) => None,
+                FnArg::Typed(pat) => Some(&pat.pat),
+            });
+            let mut export_sig = sig.clone();
+            export_sig.ident = Ident::new("__axvisor_api_export", fn_name.span());
+            export_sig.abi = Some(syn::parse_quote! { extern "Rust" });
+            let export_name = format!("__{implementee_trait_ident}_{fn_name}");
+            api_fn_exports.extend(quote! {
+                #(#cfg_attrs)*
+                const _: () = {
+                    #[unsafe(export_name = #export_name)]
+                    #export_sig {
+                        #unsafety { #fn_name(#(#args),*) }
+                    }
+                };
+            });
+        }
+        api_fn_checks.extend(quote_spanned! { fn_name.span() =>
+            #(#cfg_attrs)*
+            const _: () = #axvisor_api_path::__priv::assert_api_fn_defined::<
+                super::#implementee_reuse_ident::__axvisor_api_fns::#fn_name,
+            >();
+        });
+        let fn_path = if partial {
+            quote! { #fn_name }
+        } else {
+            quote! { <__Impl as super::#implementee_reuse_ident::#implementee_trait_ident>::#fn_name }
+        };
+        let entry = quote! {
+            #axvisor_api_path::__priv::ApiFnEntry {
+                api: #api_name,
+                function: stringify!(#fn_name),
+                ptr: #fn_path as *const (),
+                module: module_path!(),
+                order: #order,
+            }
+        };
+        api_fn_entries.extend(quote! {
+            #(#cfg_attrs)*
+            const _: () = {
+                #[used]
+                #[unsafe(link_section = "axvisor_api_fns")]
+                static ENTRY: #axvisor_api_path::__priv::ApiFnEntry = #entry;
+            };
+        });
+        api_fn_registrations.extend(quote! {
+            #(#cfg_attrs)*
+            {
+                static ENTRY: #axvisor_api_path::__priv::ApiFnEntry = #entry;
+                #axvisor_api_path::__priv::register_api_fn(&ENTRY)?;
+            }
+        });
     }
 
+    let multi_check = {
+        let message = if multi {
+            format!(
+                "`{implementee_name}` is not a `multi` API module, remove `multi` from `api_mod_impl`"
+            )
+        } else {
+            format!("`{implementee_name}` is a `multi` API module, add `multi` to `api_mod_impl`")
+        };
+        quote! {
+            const _: () = assert!(
+                super::#implementee_reuse_ident::__AXVISOR_API_MODULE_MULTI == #multi,
+                #message,
+            );
+        }
+    };
+
+    // Implementations of `multi` API modules are only reachable through the registry, as the link-time symbols are
+    // unique per API module.
+    let unique_impl_items = (!multi && !partial).then(|| {
+        quote! {
+            /// Duplicate implementation detection marker, holding the path of the implementing module.
+            #[doc(hidden)]
+            #[used]
+            #[unsafe(export_name = #impl_marker_symbol_name)]
+            pub static __AXVISOR_API_IMPL_MARKER: &str = module_path!();
+
+            /// Version of the implemented API module which this implementation is built against.
+            #[doc(hidden)]
+            #[used]
+            #[unsafe(export_name = #version_symbol_name)]
+            pub static __AXVISOR_API_IMPL_VERSION: #axvisor_api_path::__priv::ApiVersion =
+                super::#implementee_reuse_ident::__AXVISOR_API_MODULE_VERSION;
+        }
+    });
+    let impl_interface =
+        (!multi).then(|| quote! { #[#axvisor_api_path::__priv::crate_interface::impl_interface] });
+
+    // Partial implementations export the link-time symbols of their API functions directly instead of implementing the
+    // API trait, which requires all API functions.
+    let api_fn_impls = if partial {
+        quote! {
+            #api_fn_impls
+
+            #api_fn_exports
+
+            // Placed in the registry, which is always kept by the linker, with an empty function name never looked up.
+            const _: () = {
+                #[used]
+                #[unsafe(link_section = "axvisor_api_fns")]
+                static COVERAGE: #axvisor_api_path::__priv::ApiFnEntry = #axvisor_api_path::__priv::ApiFnEntry {
+                    api: #api_name,
+                    function: "",
+                    ptr: super::#implementee_reuse_ident::__axvisor_api_require_all_fns as *const (),
+                    module: module_path!(),
+                    order: 0,
+                };
+            };
+
+            const _: () = assert!(
+                super::#implementee_reuse_ident::__AXVISOR_API_MODULE_VERSION.major == 0
+                    && super::#implementee_reuse_ident::__AXVISOR_API_MODULE_VERSION.minor == 0,
+                "versioned API modules cannot be implemented partially",
+            );
+        }
+    } else {
+        quote! {
+            #[doc(hidden)]
+            pub struct __Impl;
+            #impl_interface
+            impl super::#implementee_reuse_ident::#implementee_trait_ident for __Impl {
+                #api_fn_impls
+            }
+        }
+    };
+
+    let register_impl = (cfg!(feature = "dynamic") && !multi).then(|| {
+        quote! {
+            /// Register the API functions implemented in this module to the runtime registry, replacing the
+            /// implementations registered before.
+            pub fn register_impl() -> #axvisor_api_path::__priv::AxvisorResult {
+                #api_fn_registrations
+                Ok(())
+            }
+        }
+    });
+
     quote! {
         #[doc(hidden)]
         use #implementee as #implementee_reuse_ident;
@@ -233,12 +902,17 @@ fn process_api_mod_impl(implementee: Path, input: ItemApiModImpl) -> TokenStream
         #vis #mod_token #mod_ident {
             #(#regular_items)*
 
-            #[doc(hidden)]
-            pub struct __Impl;
-            #[#axvisor_api_path::__priv::crate_interface::impl_interface]
-            impl super::#implementee_reuse_ident::#implementee_trait_ident for __Impl {
-                #api_fn_impls
-            }
+            #unique_impl_items
+
+            #multi_check
+
+            #api_fn_checks
+
+            #api_fn_impls
+
+            #api_fn_entries
+
+            #register_impl
         }
     }
 }
@@ -249,14 +923,41 @@ fn process_api_mod_impl(implementee: Path, input: ItemApiModImpl) -> TokenStream
 /// The module can contain regular items and API functions. API functions are defined with the `extern fn` syntax.
 ///
 /// **Does not work on outlined modules.** (i.e. `mod foo;` with content in `foo.rs`)
+///
+/// API functions can be marked with `#[api(max_duration = "1ms")]` to declare the maximum expected duration of a call
+/// to them. If the `watchdog` feature of `axvisor_api` is enabled, calls exceeding the duration are reported through
+/// the `diag` API, along with the location of the caller.
+///
+/// Constants provided by the implementation can be defined with the `const FOO: T;` syntax, i.e. without a value, and
+/// implemented with `#[api] const FOO: T = expr;`. They are accessed with the generated accessor function `foo() -> T`,
+/// which is an API function like the others.
+///
+/// For every API function `foo`, a `foo_checked` variant is also generated, which returns `Err(ApiNotImplemented)`
+/// instead of failing the link if `foo` is not implemented in the final binary. The implementations are looked up at
+/// runtime in the registry maintained by `api_mod_impl`, see the `registry` module of `axvisor_api`.
+///
+/// API functions can be gated by `cfg` attributes, which apply to the generated function, the API trait and the list of
+/// API functions in the module documentation alike. Implementations should gate the function under the same
+/// conditions, providing a function which is not available in the definition is a compile error.
+///
+/// # Arguments
+///
+/// - `version = "major.minor"`: declare the version of the API module. A `MODULE_API_VERSION` constant and a
+///   `check_version` function are generated, with which components built separately against a different version of
+///   the API module can detect incompatibility with the implementation at initialization.
+/// - `multi`: allow the API module to be implemented more than once, e.g. for notifications with multiple subscribers.
+///   Calls to the API functions are fanned out to all implementations, in the order declared by the implementations.
+///   API functions in such modules cannot return values, and their arguments must implement `Clone`. Cannot be used
+///   together with `version`.
+/// - `trace`: trace the calls to the API functions. Every call emits an entry event with the arguments, and an exit
+///   event with the time taken by the call, to the sink returned by `diag::api_trace_sink`. All arguments of the API
+///   functions must implement `Debug`.
 pub fn api_mod(attr: TokenStream1, input: TokenStream1) -> TokenStream1 {
-    if !attr.is_empty() {
-        return (quote_spanned! {
-            TokenStream::from(attr).span() => compile_error!("`api_mod` attribute does not accept any arguments")
-        }).into();
-    }
+    let mut args = ApiModArgs::default();
+    let parser = syn::meta::parser(|meta| args.parse(meta));
+    syn::parse_macro_input!(attr with parser);
 
-    process_api_mod_def(syn::parse_macro_input!(input as ItemApiModDef)).into()
+    process_api_mod_def(args, syn::parse_macro_input!(input as ItemApiModDef)).into()
 }
 
 #[proc_macro_attribute]
@@ -264,9 +965,28 @@ pub fn api_mod(attr: TokenStream1, input: TokenStream1) -> TokenStream1 {
 ///
 /// The module should contain the implementation of the API functions defined in another module. The path to the module
 /// defining the APIs should be passed as the argument.
+///
+/// With the `dynamic` feature of `axvisor_api`, a `register_impl` function is also generated, which registers the
+/// implementation to the runtime registry, e.g. to swap implementations in tests.
+///
+/// Implementations of `multi` API modules should be marked with `multi`, and can declare their order among the
+/// implementations with `order = N`, e.g. `#[api_mod_impl(path::to::api_mod, multi, order = -1)]`. Lower orders are
+/// called first, and implementations of the same order are called in the order of their module paths. The default
+/// order is 0.
+///
+/// An API module which is not `multi` can also be implemented by more than one implementation marked with `partial`,
+/// e.g. `#[api_mod_impl(path::to::api_mod, partial)]`, each of which implements a disjoint subset of the API functions.
+/// The final binary fails to link with an undefined symbol `__Axvisor<Module>ApiTrait_<function>` if the partial
+/// implementations do not cover all API functions together, or with a duplicate one if they overlap. Versioned API
+/// modules cannot be implemented partially.
+///
+/// Each API module which is not `multi` can be implemented only once in the final binary. Every implementation exports
+/// a marker symbol named after the implemented API module, so if more than one implementation gets linked, the linker
+/// reports a duplicate symbol `axvisor_api: duplicate `#[api_mod_impl]` of API module ...` together with the locations
+/// of all implementing modules.
 pub fn api_mod_impl(attr: TokenStream1, input: TokenStream1) -> TokenStream1 {
     process_api_mod_impl(
-        syn::parse_macro_input!(attr as Path),
+        syn::parse_macro_input!(attr as ApiModImplArgs),
         syn::parse_macro_input!(input as ItemApiModImpl),
     )
     .into()
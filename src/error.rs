@@ -0,0 +1,71 @@
+//! Error types of the APIs.
+
+use core::fmt;
+
+/// Errors returned by fallible APIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AxvisorError {
+    /// There is not enough memory or other resources.
+    NoMemory,
+    /// An argument is invalid, e.g. a misaligned address or an out-of-range value.
+    InvalidArg,
+    /// An address is not accessible, e.g. an unmapped guest physical address.
+    BadAddress,
+    /// The requested entity (virtual machine, file, handler, etc.) does not exist.
+    NotFound,
+    /// The entity to be created already exists.
+    AlreadyExists,
+    /// The operation is not supported by the implementation or the platform.
+    Unsupported,
+    /// The resource is busy, and the operation may succeed if retried later.
+    Busy,
+    /// The entity is in a state that does not allow the operation.
+    BadState,
+    /// The operation is not permitted.
+    PermissionDenied,
+    /// An I/O error occurred in the underlying device or store.
+    Io,
+}
+
+impl fmt::Display for AxvisorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Self::NoMemory => "out of memory",
+            Self::InvalidArg => "invalid argument",
+            Self::BadAddress => "bad address",
+            Self::NotFound => "not found",
+            Self::AlreadyExists => "already exists",
+            Self::Unsupported => "operation not supported",
+            Self::Busy => "resource busy",
+            Self::BadState => "bad state",
+            Self::PermissionDenied => "permission denied",
+            Self::Io => "I/O error",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl core::error::Error for AxvisorError {}
+
+/// Result type of fallible APIs.
+pub type AxvisorResult<T = ()> = Result<T, AxvisorError>;
+
+/// Error returned by the `_checked` variants of API functions, if the API function is not implemented in the final
+/// binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ApiNotImplemented;
+
+impl fmt::Display for ApiNotImplemented {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("API function not implemented")
+    }
+}
+
+impl core::error::Error for ApiNotImplemented {}
+
+impl From<ApiNotImplemented> for AxvisorError {
+    fn from(_: ApiNotImplemented) -> Self {
+        Self::Unsupported
+    }
+}
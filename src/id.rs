@@ -0,0 +1,118 @@
+//! Allocation of IDs for handles and tokens.
+//!
+//! This is a utility for implementations and components minting IDs, e.g. the
+//! [`CancelToken`](crate::time::CancelToken)s of timers, which get consistent exhaustion behavior without devising
+//! their own schemes. The API functions themselves accept IDs of any scheme.
+
+extern crate alloc;
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+/// An ID allocated by an [`IdAllocator`], consisting of a slot index and the generation of the slot.
+///
+/// The generation is bumped every time the slot is freed, so stale IDs of freed slots are never mistaken for the IDs
+/// allocated later in the same slots.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Id {
+    index: u32,
+    generation: u32,
+}
+
+impl Id {
+    /// Get the slot index of the ID, which is less than the capacity of the allocator.
+    pub const fn index(self) -> u32 {
+        self.index
+    }
+
+    /// Get the generation of the ID.
+    pub const fn generation(self) -> u32 {
+        self.generation
+    }
+
+    /// Convert the ID into a raw value, e.g. to be used as a token in APIs.
+    pub const fn to_raw(self) -> u64 {
+        ((self.generation as u64) << 32) | self.index as u64
+    }
+
+    /// Convert a raw value returned by [`Id::to_raw`] back into an ID.
+    pub const fn from_raw(raw: u64) -> Self {
+        Self {
+            index: raw as u32,
+            generation: (raw >> 32) as u32,
+        }
+    }
+}
+
+impl fmt::Debug for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Id({}@{})", self.index, self.generation)
+    }
+}
+
+/// An allocator of [`Id`]s with a fixed capacity.
+///
+/// Both allocation and deallocation take O(1) time. The allocation is deterministic: a fresh allocator hands out
+/// indices in ascending order, and the most recently freed index is reused first. When all IDs are in use, allocation
+/// fails with `None` instead of growing.
+///
+/// The allocator does not synchronize by itself, wrap it in a lock if it is shared.
+pub struct IdAllocator {
+    /// Current generation of each slot.
+    generations: Box<[u32]>,
+    /// Bitmap of allocated slots.
+    allocated: Box<[u64]>,
+    /// Stack of free slot indices, the top of which is allocated next.
+    free: Vec<u32>,
+}
+
+impl IdAllocator {
+    /// Create an allocator which can hand out up to `capacity` IDs at the same time.
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            generations: (0..capacity).map(|_| 0).collect(),
+            allocated: (0..capacity.div_ceil(64)).map(|_| 0).collect(),
+            free: (0..capacity).rev().collect(),
+        }
+    }
+
+    /// Get the maximum number of IDs that can be allocated at the same time.
+    pub fn capacity(&self) -> u32 {
+        self.generations.len() as u32
+    }
+
+    /// Get the number of allocated IDs.
+    pub fn allocated_count(&self) -> u32 {
+        self.capacity() - self.free.len() as u32
+    }
+
+    /// Allocate an ID, returning `None` if all IDs are in use.
+    pub fn alloc(&mut self) -> Option<Id> {
+        let index = self.free.pop()?;
+        self.allocated[index as usize / 64] |= 1 << (index % 64);
+        Some(Id {
+            index,
+            generation: self.generations[index as usize],
+        })
+    }
+
+    /// Check whether an ID is currently allocated.
+    pub fn is_allocated(&self, id: Id) -> bool {
+        id.index < self.capacity()
+            && self.allocated[id.index as usize / 64] & (1 << (id.index % 64)) != 0
+            && self.generations[id.index as usize] == id.generation
+    }
+
+    /// Free an ID.
+    ///
+    /// Returns `false` if the ID is not currently allocated, e.g. it has already been freed.
+    pub fn free(&mut self, id: Id) -> bool {
+        if !self.is_allocated(id) {
+            return false;
+        }
+
+        self.allocated[id.index as usize / 64] &= !(1 << (id.index % 64));
+        self.generations[id.index as usize] = id.generation.wrapping_add(1);
+        self.free.push(id.index);
+        true
+    }
+}
@@ -11,6 +11,9 @@
 //! - a standard way to define and implement APIs, including the [`api_mod`] and the [`api_mod_impl`] attributes, which
 //!   the components can utilize to define and implement their own APIs.
 //!
+//! - some common utilities for components built on top of the APIs, like the lock-free ring buffer in [`ring`], the
+//!   register block helpers in [`mmio`] and the ID allocator in [`id`].
+//!
 //! # How to define and implement APIs
 //!
 //! ## Define APIs
@@ -96,6 +99,30 @@
 //! }
 //! ```
 //!
+//! ## Versioning APIs
+//!
+//! An API module can declare its version with `#[api_mod(version = "major.minor")]`. Minor versions are expected to be
+//! backward compatible, while major versions are not. Every implementation records the version of the API module it
+//! is built against, so that components built separately against a different version can detect the incompatibility
+//! at initialization with the generated `check_version` function:
+//!
+//! ```rust, no_run, standalone_crate
+//! # use axvisor_api::{api_mod, __priv}; // some inconviniece brought by proc-macro-name and doctest
+//! #[api_mod(version = "1.2")]
+//! /// Versioned API
+//! pub mod versioned_demo {
+//!     /// Some function
+//!     extern fn some_func() -> usize;
+//! }
+//!
+//! fn main() {
+//!     assert_eq!(versioned_demo::MODULE_API_VERSION, axvisor_api::version::ApiVersion::new(1, 2));
+//!     if !versioned_demo::check_version() {
+//!         panic!("incompatible implementation of `versioned_demo`");
+//!     }
+//! }
+//! ```
+//!
 //! ## Tricks behind the macros
 //!
 //! [`api_mod`] and [`api_mod_impl`] are wrappers around the great [`crate_interface`] crate, with some macro tricks to
@@ -108,26 +135,349 @@ pub use axvisor_api_proc::{api_mod, api_mod_impl};
 
 #[api_mod]
 /// Memory-related API.
+///
+/// Addresses in this module are host addresses, i.e. [`HostPhysAddr`] and [`HostVirtAddr`]. Guest physical addresses
+/// are of the distinct type [`GuestPhysAddr`](crate::guest_memory::GuestPhysAddr), and must be translated explicitly,
+/// e.g. with [`gpa_to_hpa`](crate::guest_memory::gpa_to_hpa).
 pub mod memory {
+    extern crate alloc;
+    use alloc::vec::Vec;
+
+    /// Access permissions and memory attributes of mappings, shared by all APIs mapping or protecting memory.
+    ///
+    /// This is the flags type of [`axaddrspace`] (and of the page tables it uses), so it is passed to and from
+    /// `axaddrspace` without conversion.
+    pub use axaddrspace::MappingFlags;
+    pub use axaddrspace::{HostPhysAddr, HostVirtAddr};
     pub use memory_addr::{PhysAddr, VirtAddr};
 
     // API interfaces
 
     /// Allocate a frame.
-    extern fn alloc_frame() -> Option<PhysAddr>;
+    extern fn alloc_frame() -> Option<HostPhysAddr>;
+    /// Allocate a number of contiguous frames, with a specified alignment.
+    ///
+    /// Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if there are not enough free frames, or with
+    /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if `num_frames` is zero or the alignment is not
+    /// supported.
+    extern fn try_alloc_contiguous_frames(
+        num_frames: usize,
+        frame_align_pow2: usize,
+    ) -> crate::error::AxvisorResult<HostPhysAddr>;
     /// Allocate a number of contiguous frames, with a specified alignment.
-    extern fn alloc_contiguous_frames(
+    ///
+    /// This is a wrapper of [`try_alloc_contiguous_frames`] which discards the error.
+    pub fn alloc_contiguous_frames(
         num_frames: usize,
         frame_align_pow2: usize,
-    ) -> Option<PhysAddr>;
+    ) -> Option<HostPhysAddr> {
+        try_alloc_contiguous_frames(num_frames, frame_align_pow2).ok()
+    }
     /// Deallocate a frame.
-    extern fn dealloc_frame(addr: PhysAddr);
+    extern fn dealloc_frame(addr: HostPhysAddr);
     /// Deallocate a number of contiguous frames.
-    extern fn dealloc_contiguous_frames(first_addr: PhysAddr, num_frames: usize);
-    /// Convert a physical address to a virtual address.
-    extern fn phys_to_virt(addr: PhysAddr) -> VirtAddr;
-    /// Convert a virtual address to a physical address.
-    extern fn virt_to_phys(addr: VirtAddr) -> PhysAddr;
+    extern fn dealloc_contiguous_frames(first_addr: HostPhysAddr, num_frames: usize);
+    /// Allocate a frame filled with zeros, through the linear mappings.
+    ///
+    /// Returns `None` if there is no free frame, or the frame is not linearly mapped.
+    pub fn alloc_frame_zeroed() -> Option<HostPhysAddr> {
+        let frame = alloc_frame()?;
+        let Some(va) = phys_to_virt(frame) else {
+            dealloc_frame(frame);
+            return None;
+        };
+        // SAFETY: the frame is newly allocated, so it is not used by others, and is mapped at `va`.
+        unsafe { core::ptr::write_bytes(va.as_mut_ptr(), 0, PageSize::Size4K.size()) };
+        Some(frame)
+    }
+    /// Set the byte with which frames are filled when they are deallocated, or `None` to leave them as they are.
+    ///
+    /// This is a debug aid to detect use-after-free of frames, e.g. of guest frames still mapped to a guest, which read
+    /// the poison instead of stale data. It applies to all deallocations, including the ones of [`PhysFrame`]s and of
+    /// [`FramePool`]s, and is disabled by default.
+    extern fn set_debug_fill(fill: Option<u8>);
+
+    /// Size of a page or a block mapping, supported by the page tables of all architectures with 4K granules.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub enum PageSize {
+        /// 4 KiB.
+        Size4K,
+        /// 2 MiB.
+        Size2M,
+        /// 1 GiB.
+        Size1G,
+    }
+
+    impl PageSize {
+        /// Get the size in bytes.
+        pub const fn size(self) -> usize {
+            match self {
+                Self::Size4K => 0x1000,
+                Self::Size2M => 0x20_0000,
+                Self::Size1G => 0x4000_0000,
+            }
+        }
+
+        /// Check whether the size is larger than a 4K page.
+        pub const fn is_huge(self) -> bool {
+            !matches!(self, Self::Size4K)
+        }
+    }
+
+    /// Allocate a huge frame, i.e. contiguous frames of the given size aligned to the size, e.g. to back guest memory
+    /// with block mappings.
+    ///
+    /// The implementation may keep dedicated pools of huge frames. Fails with
+    /// [`NoMemory`](crate::error::AxvisorError::NoMemory) if there is no free huge frame of the size.
+    extern fn alloc_huge_frame(size: PageSize) -> crate::error::AxvisorResult<HostPhysAddr>;
+    /// Deallocate a huge frame allocated with [`alloc_huge_frame`].
+    extern fn dealloc_huge_frame(addr: HostPhysAddr, size: PageSize);
+
+    /// ID of a memory zone, i.e. a region of host physical memory set aside for a purpose, as configured for the
+    /// platform.
+    pub type ZoneId = usize;
+    /// ID of a NUMA node.
+    pub type NumaNodeId = usize;
+
+    /// Constraints and hints of frame allocations.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct AllocPolicy {
+        /// Zone to allocate from, or `None` for the general-purpose memory.
+        pub zone: Option<ZoneId>,
+        /// Preferred NUMA node, e.g. the one of the physical CPUs running a virtual machine, or `None` for any node.
+        /// Frames are allocated from other nodes if the preferred one runs out of free frames.
+        pub node: Option<NumaNodeId>,
+        /// Only allocate frames below 4 GiB, e.g. for devices only capable of 32-bit DMA.
+        pub below_4g: bool,
+    }
+
+    /// Allocate a frame with a policy.
+    ///
+    /// Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if there is no free frame satisfying the policy,
+    /// or with [`NotFound`](crate::error::AxvisorError::NotFound) if the zone does not exist.
+    extern fn alloc_frame_with_policy(
+        policy: AllocPolicy,
+    ) -> crate::error::AxvisorResult<HostPhysAddr>;
+    /// Allocate a number of contiguous frames, with a specified alignment and a policy.
+    ///
+    /// Fails like [`alloc_frame_with_policy`], or with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) like
+    /// [`try_alloc_contiguous_frames`].
+    extern fn alloc_contiguous_frames_with_policy(
+        num_frames: usize,
+        frame_align_pow2: usize,
+        policy: AllocPolicy,
+    ) -> crate::error::AxvisorResult<HostPhysAddr>;
+    /// Convert a physical address to a virtual address through the linear mappings.
+    ///
+    /// Returns `None` if the physical address is not covered by any of the [`linear_map_regions`].
+    extern fn phys_to_virt(addr: HostPhysAddr) -> Option<HostVirtAddr>;
+    /// Convert a virtual address in the linear mappings to a physical address.
+    ///
+    /// Returns `None` if the virtual address is not covered by any of the [`linear_map_regions`].
+    extern fn virt_to_phys(addr: HostVirtAddr) -> Option<HostPhysAddr>;
+    /// Get the `index`-th linear mapping region of the hypervisor's address space, or `None` if there are not that
+    /// many regions.
+    extern fn linear_map_region(index: usize) -> Option<LinearMap>;
+    /// Iterate over the linear mapping regions of the hypervisor's address space.
+    pub fn linear_map_regions() -> impl Iterator<Item = LinearMap> {
+        (0..).map_while(linear_map_region)
+    }
+
+    /// A region of physical memory linearly mapped in the hypervisor's address space, e.g. a RAM bank.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct LinearMap {
+        /// Start of the physical address range.
+        pub phys_start: HostPhysAddr,
+        /// Start of the virtual address range.
+        pub virt_start: HostVirtAddr,
+        /// Size of the region in bytes.
+        pub size: usize,
+    }
+
+    impl LinearMap {
+        /// Convert a physical address in the region to a virtual address, or `None` if it is not in the region.
+        pub fn phys_to_virt(&self, addr: HostPhysAddr) -> Option<HostVirtAddr> {
+            let offset = addr.as_usize().checked_sub(self.phys_start.as_usize())?;
+            (offset < self.size).then(|| self.virt_start + offset)
+        }
+
+        /// Convert a virtual address in the region to a physical address, or `None` if it is not in the region.
+        pub fn virt_to_phys(&self, addr: HostVirtAddr) -> Option<HostPhysAddr> {
+            let offset = addr.as_usize().checked_sub(self.virt_start.as_usize())?;
+            (offset < self.size).then(|| self.phys_start + offset)
+        }
+    }
+
+    /// Allocate frames to fill `frames`, all or nothing.
+    ///
+    /// Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if there are not enough free frames, in which case
+    /// no frame is allocated.
+    extern fn alloc_frames(frames: &mut [HostPhysAddr]) -> crate::error::AxvisorResult;
+    /// Deallocate frames allocated with [`alloc_frame`] or [`alloc_frames`].
+    extern fn dealloc_frames(frames: &[HostPhysAddr]);
+    /// Get the statistics of the frame allocator.
+    extern fn frame_stats() -> FrameStats;
+
+    /// Allocate a DMA-coherent buffer of `size` bytes aligned to `align` bytes, returning its physical and virtual
+    /// addresses.
+    ///
+    /// The buffer is mapped in a way that devices and CPUs observe each other's accesses without explicit cache
+    /// maintenance if the platform supports it. Otherwise, it is mapped as normal memory and the users must call
+    /// [`flush_dcache_range`] and [`invalidate_dcache_range`] around device accesses.
+    extern fn alloc_dma(
+        size: usize,
+        align: usize,
+    ) -> crate::error::AxvisorResult<(HostPhysAddr, HostVirtAddr)>;
+    /// Deallocate a DMA-coherent buffer allocated with [`alloc_dma`].
+    extern fn dealloc_dma(paddr: HostPhysAddr, vaddr: HostVirtAddr, size: usize);
+    /// Write back the data cache lines covering a range of memory, so that devices observe the data written by CPUs.
+    extern fn flush_dcache_range(vaddr: HostVirtAddr, size: usize);
+    /// Invalidate the data cache lines covering a range of memory, so that CPUs observe the data written by devices.
+    extern fn invalidate_dcache_range(vaddr: HostVirtAddr, size: usize);
+    /// Write back and invalidate the data cache lines covering a range of memory.
+    extern fn clean_invalidate_dcache(vaddr: HostVirtAddr, size: usize);
+    /// Invalidate the instruction cache lines covering a range of memory, e.g. after loading code into it.
+    ///
+    /// The data cache lines covering the range are written back first if the caches are not coherent.
+    extern fn invalidate_icache(vaddr: HostVirtAddr, size: usize);
+    /// Invalidate all TLB entries of the hypervisor's address space on all physical CPUs.
+    extern fn flush_tlb_all();
+    /// Invalidate the TLB entries of a virtual address of the hypervisor's address space on all physical CPUs.
+    extern fn flush_tlb_va(vaddr: HostVirtAddr);
+    /// Invalidate the stage-2 TLB entries of a range of the guest physical memory of a virtual machine on all physical
+    /// CPUs, e.g. after changing its stage-2 page table.
+    extern fn flush_guest_tlb(
+        vm_id: crate::vmm::VMId,
+        gpa_range: crate::guest_memory::GuestPhysAddrRange,
+    );
+
+    /// Map a device MMIO region into the hypervisor's address space, with uncached device memory attributes.
+    ///
+    /// `mmio_pa` and `size` are rounded to page boundaries, and the returned address corresponds to `mmio_pa`. Fails
+    /// with [`NoMemory`](crate::error::AxvisorError::NoMemory) if there is no free virtual address range, or with
+    /// [`PermissionDenied`](crate::error::AxvisorError::PermissionDenied) if the range is RAM, or is owned by the
+    /// hypervisor itself.
+    extern fn map_device(
+        mmio_pa: HostPhysAddr,
+        size: usize,
+    ) -> crate::error::AxvisorResult<HostVirtAddr>;
+    /// Unmap a device MMIO region mapped with [`map_device`].
+    extern fn unmap_device(va: HostVirtAddr, size: usize);
+
+    /// Change the access permissions of a range of the hypervisor's own virtual address space, enforcing W^X.
+    ///
+    /// Components generating code use this function to flip a buffer between writable and executable, and others may
+    /// use it to make data structures read-only after initialization. `va` and `len` must be page-aligned.
+    ///
+    /// Fails with [`PermissionDenied`](crate::error::AxvisorError::PermissionDenied) if `perms` is both writable and
+    /// executable, or with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the range is not page-aligned.
+    #[track_caller]
+    pub fn protect_hypervisor_range(
+        va: HostVirtAddr,
+        len: usize,
+        perms: MappingFlags,
+    ) -> crate::error::AxvisorResult {
+        use crate::error::AxvisorError;
+
+        if perms.contains(MappingFlags::WRITE | MappingFlags::EXECUTE) {
+            return Err(AxvisorError::PermissionDenied);
+        }
+        if !memory_addr::is_aligned_4k(va.as_usize()) || !memory_addr::is_aligned_4k(len) {
+            return Err(AxvisorError::InvalidArg);
+        }
+        apply_hypervisor_range_protection(va, len, perms, core::panic::Location::caller())
+    }
+    /// Apply the access permissions to a range of the hypervisor's own virtual address space, which has been checked
+    /// by [`protect_hypervisor_range`].
+    ///
+    /// The implementation should record every change in its audit log, including `caller`, i.e. the location where
+    /// [`protect_hypervisor_range`] is called.
+    extern fn apply_hypervisor_range_protection(
+        va: HostVirtAddr,
+        len: usize,
+        perms: MappingFlags,
+        caller: &'static core::panic::Location<'static>,
+    ) -> crate::error::AxvisorResult;
+
+    /// Start logging the pages of the guest memory of a virtual machine written by the guest or by DMA, e.g. for
+    /// incremental snapshots or memory introspection.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if the logging is already enabled.
+    extern fn enable_dirty_log(vm_id: crate::vmm::VMId) -> crate::error::AxvisorResult;
+    /// Stop logging the written pages of the guest memory of a virtual machine.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if the logging is not enabled.
+    extern fn disable_dirty_log(vm_id: crate::vmm::VMId) -> crate::error::AxvisorResult;
+    /// Fetch the pages in a range of the guest memory of a virtual machine written since the last fetch into `bitmap`,
+    /// and clear them, returning the number of written pages.
+    ///
+    /// Bit `i % 64` of `bitmap[i / 64]` is set if the `i`-th 4K page of the range is written. Fails with
+    /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the range is not page-aligned or the bitmap is too
+    /// small for it, with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or
+    /// with [`BadState`](crate::error::AxvisorError::BadState) if the logging is not enabled.
+    extern fn fetch_and_clear_dirty_bitmap(
+        vm_id: crate::vmm::VMId,
+        gpa_range: crate::guest_memory::GuestPhysAddrRange,
+        bitmap: &mut [u64],
+    ) -> crate::error::AxvisorResult<usize>;
+
+    /// Statistics of the frame allocator, in number of frames.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct FrameStats {
+        /// Total number of frames managed by the allocator.
+        pub total: usize,
+        /// Number of free frames.
+        pub free: usize,
+        /// Number of allocated frames.
+        pub allocated: usize,
+    }
+
+    /// A pool of frames allocated in a batch, the remaining of which are deallocated when the pool is dropped.
+    ///
+    /// Frames taken out of the pool with [`FramePool::take`] are owned by the caller, who is responsible for
+    /// deallocating them, or putting them back with [`FramePool::put`].
+    pub struct FramePool {
+        frames: Vec<HostPhysAddr>,
+    }
+
+    impl FramePool {
+        /// Allocate a pool of `num_frames` frames.
+        pub fn new(num_frames: usize) -> crate::error::AxvisorResult<Self> {
+            let mut frames = alloc::vec![HostPhysAddr::from_usize(0); num_frames];
+            alloc_frames(&mut frames)?;
+            Ok(Self { frames })
+        }
+
+        /// Get the number of frames remaining in the pool.
+        pub fn len(&self) -> usize {
+            self.frames.len()
+        }
+
+        /// Check whether the pool is empty.
+        pub fn is_empty(&self) -> bool {
+            self.frames.is_empty()
+        }
+
+        /// Take a frame out of the pool.
+        pub fn take(&mut self) -> Option<HostPhysAddr> {
+            self.frames.pop()
+        }
+
+        /// Put a frame back into the pool.
+        pub fn put(&mut self, frame: HostPhysAddr) {
+            self.frames.push(frame);
+        }
+    }
+
+    impl Drop for FramePool {
+        fn drop(&mut self) {
+            if !self.frames.is_empty() {
+                dealloc_frames(&self.frames);
+            }
+        }
+    }
 
     // Re-exports
     // TODO: determine whether it's proper and acceptable to place this definition here in this mod.
@@ -136,32 +486,117 @@ pub mod memory {
     pub struct AxMmHalApiImpl;
 
     impl axaddrspace::AxMmHal for AxMmHalApiImpl {
-        fn alloc_frame() -> Option<PhysAddr> {
+        fn alloc_frame() -> Option<HostPhysAddr> {
             alloc_frame()
         }
 
-        fn dealloc_frame(addr: PhysAddr) {
+        fn dealloc_frame(addr: HostPhysAddr) {
             dealloc_frame(addr)
         }
 
-        fn phys_to_virt(addr: PhysAddr) -> VirtAddr {
-            phys_to_virt(addr)
+        fn phys_to_virt(addr: HostPhysAddr) -> HostVirtAddr {
+            phys_to_virt(addr).expect("physical address not linearly mapped")
         }
 
-        fn virt_to_phys(addr: VirtAddr) -> PhysAddr {
-            virt_to_phys(addr)
+        fn virt_to_phys(addr: HostVirtAddr) -> HostPhysAddr {
+            virt_to_phys(addr).expect("virtual address not linearly mapped")
         }
     }
 
     /// A physical frame which will be automatically deallocated when dropped.
     pub type PhysFrame = axaddrspace::PhysFrame<AxMmHalApiImpl>;
+
+    /// Safe accessors of the contents of [`PhysFrame`]s through the linear mappings, besides
+    /// [`PhysFrame::fill`](axaddrspace::PhysFrame::fill).
+    pub trait PhysFrameExt {
+        /// Get the contents of the frame.
+        fn as_slice(&self) -> &[u8];
+        /// Get the contents of the frame mutably.
+        fn as_mut_slice(&mut self) -> &mut [u8];
+    }
+
+    impl PhysFrameExt for PhysFrame {
+        fn as_slice(&self) -> &[u8] {
+            // SAFETY: the frame is owned by `self` and mapped at `as_mut_ptr` through the linear mappings, and it
+            // cannot be written through `self` while it is borrowed.
+            unsafe { core::slice::from_raw_parts(self.as_mut_ptr(), PageSize::Size4K.size()) }
+        }
+
+        fn as_mut_slice(&mut self) -> &mut [u8] {
+            // SAFETY: as above, and `self` is borrowed mutably.
+            unsafe { core::slice::from_raw_parts_mut(self.as_mut_ptr(), PageSize::Size4K.size()) }
+        }
+    }
+}
+
+#[api_mod]
+/// Heap-related API.
+///
+/// Besides the global allocator, the hypervisor provides arenas, each of which is a bounded part of the heap used by a
+/// single component, so that the heap usage of components is attributable, and a leaking component exhausts its own
+/// arena rather than the whole heap.
+pub mod heap {
+    use core::{alloc::Layout, ptr::NonNull};
+
+    /// Arena ID.
+    pub type ArenaId = usize;
+
+    /// Usage of an arena, in bytes.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct ArenaUsage {
+        /// Maximum number of bytes the arena can allocate.
+        pub max: usize,
+        /// Number of bytes currently allocated.
+        pub used: usize,
+        /// Highest number of bytes allocated at the same time.
+        pub peak: usize,
+        /// Number of allocations failed due to exhaustion of the arena.
+        pub failures: u64,
+    }
+
+    /// Create an arena which can allocate up to `max_bytes` bytes, named after the component using it.
+    extern fn create_arena(
+        name: &'static str,
+        max_bytes: usize,
+    ) -> crate::error::AxvisorResult<ArenaId>;
+    /// Destroy an arena.
+    ///
+    /// Fails with [`BadState`](crate::error::AxvisorError::BadState) if there is memory still allocated in the arena.
+    extern fn destroy_arena(arena: ArenaId) -> crate::error::AxvisorResult;
+    /// Allocate memory in an arena.
+    ///
+    /// Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if the arena or the heap is exhausted.
+    extern fn arena_alloc(
+        arena: ArenaId,
+        layout: core::alloc::Layout,
+    ) -> crate::error::AxvisorResult<core::ptr::NonNull<u8>>;
+    /// Deallocate memory allocated with [`arena_alloc`] in the same arena with the same layout.
+    extern fn arena_dealloc(
+        arena: ArenaId,
+        ptr: core::ptr::NonNull<u8>,
+        layout: core::alloc::Layout,
+    );
+    /// Get the usage of an arena.
+    extern fn arena_usage(arena: ArenaId) -> crate::error::AxvisorResult<ArenaUsage>;
+
+    /// Allocate zeroed memory in an arena.
+    pub fn arena_alloc_zeroed(
+        arena: ArenaId,
+        layout: Layout,
+    ) -> crate::error::AxvisorResult<NonNull<u8>> {
+        let ptr = arena_alloc(arena, layout)?;
+        // SAFETY: the memory is just allocated with `layout`.
+        unsafe { ptr.as_ptr().write_bytes(0, layout.size()) };
+        Ok(ptr)
+    }
 }
 
 #[api_mod]
 /// Time-and-timer-related API.
 pub mod time {
     extern crate alloc;
-    use alloc::boxed::Box;
+    use super::vmm::VMId;
+    use alloc::{boxed::Box, collections::BTreeMap};
     use core::time::Duration;
 
     /// Time value.
@@ -170,8 +605,14 @@ pub mod time {
     pub type Nanos = u64;
     /// Tick count.
     pub type Ticks = u64;
-    /// Cancel token， used to cancel a scheduled timer event.
-    pub type CancelToken = usize;
+    /// Wall-clock time in nanoseconds since the Unix epoch, i.e. 1970-01-01 00:00:00 UTC.
+    pub type UnixNanos = u64;
+    /// Cancel token, identifying a scheduled timer event, owned by its [`TimerHandle`].
+    ///
+    /// A handle is usually held past the expiry of its timer, and cancels the timer with the token when dropped, so
+    /// implementations must never reuse a token which a handle can still refer to, e.g. by minting generational tokens
+    /// with [`Id::to_raw`](crate::id::Id::to_raw).
+    pub type CancelToken = u64;
 
     /// Get the current tick count.
     extern fn current_ticks() -> Ticks;
@@ -184,97 +625,3770 @@ pub mod time {
         Duration::from_nanos(current_time_nanos())
     }
 
+    /// Check whether the tick counters of all CPUs are synchronized, i.e. [`current_ticks`] never goes backwards even
+    /// if it is read on different CPUs.
+    ///
+    /// If not, components comparing ticks read on different CPUs (e.g. timestamps in trace data) should compensate
+    /// with [`corrected_ticks`].
+    extern fn is_monotonic_across_cpus() -> bool;
+    /// Re-measure the offsets between the tick counters of CPUs, updating the ones returned by
+    /// [`cpu_ticks_offset`].
+    ///
+    /// Fails with [`Unsupported`](crate::error::AxvisorError::Unsupported) if the platform is unable to measure the
+    /// offsets.
+    extern fn resync_clock() -> crate::error::AxvisorResult;
+    /// Get the correction of the tick counter of a CPU, i.e. the value to be added to the ticks read on the CPU to get
+    /// the ticks on the boot CPU.
+    extern fn cpu_ticks_offset(cpu_id: usize) -> i64;
+    /// Set the correction of the tick counter of a CPU, e.g. measured by a platform-specific method.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the CPU does not exist.
+    extern fn set_cpu_ticks_offset(cpu_id: usize, offset: i64) -> crate::error::AxvisorResult;
+    /// Correct the ticks read on a CPU to the ticks on the boot CPU, with the offset returned by
+    /// [`cpu_ticks_offset`].
+    pub fn corrected_ticks(cpu_id: usize, ticks: Ticks) -> Ticks {
+        ticks.saturating_add_signed(cpu_ticks_offset(cpu_id))
+    }
+
     /// Convert ticks to nanoseconds.
+    ///
+    /// The implementation should saturate at [`Nanos::MAX`] instead of wrapping around, e.g. with
+    /// [`mul_div_saturating`].
     extern fn ticks_to_nanos(ticks: Ticks) -> Nanos;
     /// Convert ticks to time.
     pub fn ticks_to_time(ticks: Ticks) -> TimeValue {
+        ticks_to_time_saturating(ticks)
+    }
+    /// Convert ticks to time, saturating at [`Nanos::MAX`] nanoseconds.
+    pub fn ticks_to_time_saturating(ticks: Ticks) -> TimeValue {
         Duration::from_nanos(ticks_to_nanos(ticks))
     }
     /// Convert nanoseconds to ticks.
+    ///
+    /// The implementation should saturate at [`Ticks::MAX`] instead of wrapping around, e.g. with
+    /// [`mul_div_saturating`].
     extern fn nanos_to_ticks(nanos: Nanos) -> Ticks;
-    /// Convert time to ticks.
+    /// Convert time to ticks, saturating at [`Ticks::MAX`].
     pub fn time_to_ticks(time: TimeValue) -> Ticks {
-        nanos_to_ticks(time.as_nanos() as Nanos)
+        nanos_to_ticks(Nanos::try_from(time.as_nanos()).unwrap_or(Nanos::MAX))
+    }
+    /// Get the frequency of the tick counter, i.e. the number of ticks per second.
+    extern fn ticks_per_sec() -> u64;
+    /// Convert time to ticks with the frequency returned by [`ticks_per_sec`], returning `None` if the result does
+    /// not fit in [`Ticks`].
+    pub fn time_to_ticks_checked(time: TimeValue) -> Option<Ticks> {
+        const NANOS_PER_SEC: u128 = 1_000_000_000;
+        let freq = ticks_per_sec() as u128;
+        // Neither the product nor the sum can overflow, as both factors of the product fit in 64 bits.
+        let ticks =
+            time.as_secs() as u128 * freq + time.subsec_nanos() as u128 * freq / NANOS_PER_SEC;
+        Ticks::try_from(ticks).ok()
+    }
+    /// Calculate `value * mul / div` with a 128-bit intermediate result, saturating at [`u64::MAX`].
+    ///
+    /// This is a helper for implementations of [`ticks_to_nanos`] and [`nanos_to_ticks`], e.g.
+    /// `mul_div_saturating(ticks, NANOS_PER_SEC, freq)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `div` is zero.
+    pub const fn mul_div_saturating(value: u64, mul: u64, div: u64) -> u64 {
+        let result = value as u128 * mul as u128 / div as u128;
+        if result > u64::MAX as u128 {
+            u64::MAX
+        } else {
+            result as u64
+        }
+    }
+
+    /// Get the wall-clock time of the host, e.g. to be served to guests by virtual RTCs.
+    ///
+    /// Returns `None` if the wall-clock time is unknown, i.e. the platform has no RTC and the time has never been set
+    /// with [`set_wall_clock_time`].
+    extern fn wall_clock_time() -> Option<UnixNanos>;
+    /// Set the wall-clock time of the host, e.g. from a network time source, updating the hardware RTC if there is
+    /// one.
+    ///
+    /// Virtual RTCs should keep the time set by guests as offsets from the host time, instead of calling this
+    /// function.
+    extern fn set_wall_clock_time(time: UnixNanos);
+    /// Get the wall-clock time when the host booted, i.e. when [`current_time`] was zero.
+    ///
+    /// Returns `None` if the wall-clock time is unknown.
+    pub fn boot_time() -> Option<UnixNanos> {
+        wall_clock_time().map(|now| now.saturating_sub(current_time_nanos()))
+    }
+
+    /// Get the time offset of a virtual machine, i.e. the amount by which its virtual time lags behind the host time.
+    ///
+    /// The offset is used to hide from the guest the time when it is paused or being migrated, e.g. by programming it
+    /// into `CNTVOFF_EL2` on aarch64. It is zero for virtual machines whose offset has never been set.
+    extern fn vm_time_offset(vm_id: VMId) -> TimeValue;
+    /// Set the time offset of a virtual machine.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist.
+    extern fn set_vm_time_offset(vm_id: VMId, offset: TimeValue) -> crate::error::AxvisorResult;
+    /// Get the current virtual time of a virtual machine, i.e. the host time minus the time offset of the virtual
+    /// machine.
+    pub fn vm_current_time(vm_id: VMId) -> TimeValue {
+        current_time().saturating_sub(vm_time_offset(vm_id))
+    }
+
+    /// Busy-wait until the given deadline, without yielding the current CPU.
+    pub fn busy_wait_until(deadline: TimeValue) {
+        while current_time() < deadline {
+            core::hint::spin_loop();
+        }
+    }
+    /// Busy-wait for the given duration, without yielding the current CPU.
+    ///
+    /// Use this function for short delays only, e.g. the ones required by device registers, and [`sleep`] otherwise.
+    pub fn busy_wait(duration: TimeValue) {
+        busy_wait_until(current_time().saturating_add(duration))
+    }
+    /// Sleep for the given duration, yielding the current CPU to other tasks or virtual CPUs.
+    ///
+    /// The implementation may fall back to busy-waiting if it is called in a context unable to yield, e.g. with
+    /// interrupts disabled.
+    extern fn sleep(duration: TimeValue);
+
+    /// Get the resolution of timers, i.e. the maximum delay between the deadline of a timer and the moment its
+    /// callback is run on an idle CPU, e.g. the period of the timer interrupt if it is not tickless.
+    ///
+    /// Timer emulation components may treat deadlines closer than this as already expired.
+    extern fn timer_resolution() -> TimeValue;
+    /// Handle of a pending timer, which cancels the timer when dropped unless it is detached.
+    #[derive(Debug)]
+    #[must_use = "the timer is cancelled when the handle is dropped, use `detach` to keep it"]
+    pub struct TimerHandle {
+        token: CancelToken,
+    }
+
+    impl TimerHandle {
+        /// Create a handle owning the timer with the given token, used by the implementations of the timer APIs.
+        ///
+        /// The token must not be reused for another timer while the handle exists, see [`CancelToken`].
+        pub fn from_raw(token: CancelToken) -> Self {
+            Self { token }
+        }
+
+        /// Get the token of the timer, e.g. to pass it to [`reschedule_timer`] or [`remaining_time`].
+        pub fn token(&self) -> CancelToken {
+            self.token
+        }
+
+        /// Release the handle without cancelling the timer, returning its token.
+        pub fn detach(self) -> CancelToken {
+            let token = self.token;
+            core::mem::forget(self);
+            token
+        }
+
+        /// Cancel the timer, returning whether it was still pending.
+        pub fn cancel(self) -> bool {
+            cancel_timer(self.detach())
+        }
+
+        /// Change the deadline of the timer, see [`reschedule_timer`].
+        pub fn reschedule(&self, new_deadline: TimeValue) -> crate::error::AxvisorResult {
+            reschedule_timer(self.token, new_deadline)
+        }
+
+        /// Get the time remaining until the deadline of the timer, see [`remaining_time`].
+        pub fn remaining_time(&self) -> Option<TimeValue> {
+            remaining_time(self.token)
+        }
+    }
+
+    impl Drop for TimerHandle {
+        fn drop(&mut self) {
+            cancel_timer(self.token);
+        }
     }
 
     /// Register a timer.
+    ///
+    /// The token of the returned handle must not be reused for another timer while the handle exists, see
+    /// [`CancelToken`].
     extern fn register_timer(
         deadline: TimeValue,
         callback: Box<dyn FnOnce(TimeValue) + Send + 'static>,
-    ) -> CancelToken;
-    /// Cancel a timer.
-    extern fn cancel_timer(token: CancelToken);
-}
+    ) -> TimerHandle;
+    /// Register a timer whose callback is preferably run on the given physical CPU.
+    ///
+    /// The CPU is a hint, e.g. the physical CPU hosting the virtual CPU which the timer belongs to, so that the
+    /// expiry can be handled locally without a cross-CPU interrupt. The implementation may run the callback on another
+    /// CPU if the given one is not available. The token of the returned handle must not be reused for another timer
+    /// while the handle exists, see [`CancelToken`].
+    extern fn register_timer_on(
+        cpu_id: usize,
+        deadline: TimeValue,
+        callback: Box<dyn FnOnce(TimeValue) + Send + 'static>,
+    ) -> TimerHandle;
+    /// Register a periodic timer, whose callback is run every `period` from now on until the timer is cancelled.
+    ///
+    /// The callback receives the deadline of the current period, which may be earlier than the current time if the
+    /// callback is delayed. Expirations missed due to the delay are not compensated. The token of the returned handle
+    /// must not be reused for another timer while the handle exists, see [`CancelToken`].
+    extern fn register_periodic_timer(
+        period: TimeValue,
+        callback: Box<dyn FnMut(TimeValue) + Send + 'static>,
+    ) -> TimerHandle;
+    /// Change the deadline of a pending timer, or the next deadline of a periodic timer.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the timer has expired or been cancelled.
+    extern fn reschedule_timer(
+        token: CancelToken,
+        new_deadline: TimeValue,
+    ) -> crate::error::AxvisorResult;
+    /// Get the time remaining until the (next) deadline of a pending timer.
+    ///
+    /// Returns `None` if the timer has expired or been cancelled, or [`Duration::ZERO`] if the deadline has passed
+    /// but the callback has not been run yet.
+    extern fn remaining_time(token: CancelToken) -> Option<TimeValue>;
+    /// Cancel a timer, returning whether it was still pending, i.e. `false` if it has expired or been cancelled.
+    extern fn cancel_timer(token: CancelToken) -> bool;
 
-#[api_mod]
-/// Virtual machine management API.
-pub mod vmm {
-    /// Virtual machine ID.
-    pub type VMId = usize;
-    /// Virtual CPU ID.
-    pub type VCpuId = usize;
-    /// Interrupt vector.
-    pub type InterruptVector = u8;
+    /// ID of a timer slot.
+    pub type TimerSlotId = usize;
+    /// Callback of a timer slot, run with the deadline each time the slot expires.
+    pub type TimerSlotCallback = Box<dyn Fn(TimeValue) + Send + Sync + 'static>;
 
-    /// Get the ID of the current virtual machine.
-    extern fn current_vm_id() -> VMId;
-    /// Get the ID of the current virtual CPU.
-    extern fn current_vcpu_id() -> VCpuId;
-    /// Get the number of virtual CPUs in a virtual machine.
-    extern fn vcpu_num(vm_id: VMId) -> Option<usize>;
-    /// Get the mask of active virtual CPUs in a virtual machine.
-    extern fn active_vcpus(vm_id: VMId) -> Option<usize>;
-    /// Get the number of virtual CPUs in the current virtual machine.
-    pub fn current_vm_vcpu_num() -> usize {
-        vcpu_num(current_vm_id()).unwrap()
+    /// Allocate a timer slot, a timer with a fixed callback which can be armed and disarmed repeatedly without
+    /// allocation, e.g. for the emulated timer of a virtual CPU reprogrammed by the guest at a high rate.
+    ///
+    /// Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if no more slots can be allocated.
+    extern fn allocate_timer_slot(
+        callback: TimerSlotCallback,
+    ) -> crate::error::AxvisorResult<TimerSlotId>;
+    /// Free a timer slot, disarming it first.
+    extern fn free_timer_slot(slot: TimerSlotId);
+    /// Arm a timer slot to expire at `deadline`, replacing the deadline it is armed with, if any.
+    ///
+    /// The slot is disarmed when it expires. Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the slot
+    /// does not exist.
+    extern fn arm_slot(slot: TimerSlotId, deadline: TimeValue) -> crate::error::AxvisorResult;
+    /// Disarm a timer slot, returning whether it was armed.
+    extern fn disarm_slot(slot: TimerSlotId) -> bool;
+
+    /// A timer slot, freed when dropped.
+    #[derive(Debug)]
+    pub struct TimerSlot {
+        id: TimerSlotId,
     }
-    /// Get the mask of active virtual CPUs in the current virtual machine.
-    pub fn current_vm_active_vcpus() -> usize {
-        active_vcpus(current_vm_id()).unwrap()
+
+    impl TimerSlot {
+        /// Allocate a timer slot running `callback` each time it expires.
+        pub fn new(
+            callback: impl Fn(TimeValue) + Send + Sync + 'static,
+        ) -> crate::error::AxvisorResult<Self> {
+            let id = allocate_timer_slot(Box::new(callback))?;
+            Ok(Self { id })
+        }
+
+        /// Get the ID of the slot.
+        pub fn id(&self) -> TimerSlotId {
+            self.id
+        }
+
+        /// Arm the slot to expire at `deadline`, replacing the deadline it is armed with, if any.
+        pub fn arm(&self, deadline: TimeValue) {
+            // The slot exists until it is dropped.
+            let _ = arm_slot(self.id, deadline);
+        }
+
+        /// Disarm the slot, returning whether it was armed.
+        pub fn disarm(&self) -> bool {
+            disarm_slot(self.id)
+        }
     }
 
-    /// Inject an interrupt to a virtual CPU.
-    extern fn inject_interrupt(vm_id: VMId, vcpu_id: VCpuId, vector: InterruptVector);
-    /// Notify that a virtual CPU timer has expired.
+    impl Drop for TimerSlot {
+        fn drop(&mut self) {
+            free_timer_slot(self.id);
+        }
+    }
+
+    /// Key of a timer in a [`TimerList`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct TimerListKey {
+        deadline: TimeValue,
+        seq: u64,
+    }
+
+    impl TimerListKey {
+        /// Get the deadline of the timer.
+        pub fn deadline(&self) -> TimeValue {
+            self.deadline
+        }
+    }
+
+    /// A list of logical timers ordered by deadline, with which a component multiplexes many timers onto one timer
+    /// registration (or one [`TimerSlot`]), armed with the earliest deadline.
     ///
-    /// TODO: determine whether we can skip this function.
-    extern fn notify_vcpu_timer_expired(vm_id: VMId, vcpu_id: VCpuId);
-}
+    /// Timers with the same deadline expire in the order they are added.
+    #[derive(Debug)]
+    pub struct TimerList<T> {
+        timers: BTreeMap<TimerListKey, T>,
+        next_seq: u64,
+    }
 
-#[api_mod]
-pub mod host {
-    /// Get the total number of cpus in the host system.
-    extern fn get_host_cpu_num() -> usize;
-}
+    impl<T> Default for TimerList<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
 
-#[api_mod]
-pub mod arch {
-    use super::vmm::InterruptVector;
+    impl<T> TimerList<T> {
+        /// Create an empty list.
+        pub const fn new() -> Self {
+            Self {
+                timers: BTreeMap::new(),
+                next_seq: 0,
+            }
+        }
 
-    #[cfg(target_arch = "aarch64")]
-    /// AArch64-specific API. Inject a virtual interrupt to the current virtual CPU using gich.
-    extern fn hardware_inject_virtual_interrupt(vector: InterruptVector);
+        /// Get the number of pending timers.
+        pub fn len(&self) -> usize {
+            self.timers.len()
+        }
 
-    #[cfg(target_arch = "aarch64")]
-    /// AArch64-specific API. Get the TYPER register of the GIC distributor. Used in virtual GIC initialization.
-    extern fn read_vgicd_typer() -> u32;
-    #[cfg(target_arch = "aarch64")]
-    /// AArch64-specific API. Get the IIDR register of the GIC distributor. Used in virtual GIC initialization.
-    extern fn read_vgicd_iidr() -> u32;
+        /// Check whether there is no pending timer.
+        pub fn is_empty(&self) -> bool {
+            self.timers.is_empty()
+        }
 
-    #[cfg(target_arch = "aarch64")]
-    /// AArch64-specific API. Get the base address of the GIC distributor in the host system.
-    extern fn get_host_gicd_base() -> crate::memory::PhysAddr;
-    #[cfg(target_arch = "aarch64")]
-    /// AArch64-specific API. Get the base address of the GIC redistributor in the host system.
-    extern fn get_host_gicr_base() -> crate::memory::PhysAddr;
+        /// Add a timer expiring at `deadline`, returning its key.
+        ///
+        /// The caller should re-arm the underlying timer if the deadline is earlier than the previous
+        /// [`next_deadline`](Self::next_deadline).
+        pub fn add(&mut self, deadline: TimeValue, value: T) -> TimerListKey {
+            let key = TimerListKey {
+                deadline,
+                seq: self.next_seq,
+            };
+            self.next_seq += 1;
+            self.timers.insert(key, value);
+            key
+        }
+
+        /// Cancel a pending timer, returning its value, or `None` if it has expired or been cancelled.
+        pub fn cancel(&mut self, key: TimerListKey) -> Option<T> {
+            self.timers.remove(&key)
+        }
+
+        /// Get the earliest deadline of the pending timers.
+        pub fn next_deadline(&self) -> Option<TimeValue> {
+            self.timers.first_key_value().map(|(key, _)| key.deadline)
+        }
+
+        /// Remove the earliest timer if it has expired at `now`, returning its deadline and value.
+        pub fn pop_expired(&mut self, now: TimeValue) -> Option<(TimeValue, T)> {
+            self.timers
+                .first_entry()
+                .filter(|entry| entry.key().deadline <= now)
+                .map(|entry| {
+                    let deadline = entry.key().deadline;
+                    (deadline, entry.remove())
+                })
+        }
+
+        /// Remove the timers expired at `now` in the order of their deadlines, calling `f` with the deadline and the
+        /// value of each, and returning the number of them.
+        pub fn expire(&mut self, now: TimeValue, mut f: impl FnMut(TimeValue, T)) -> usize {
+            let mut count = 0;
+            while let Some((deadline, value)) = self.pop_expired(now) {
+                f(deadline, value);
+                count += 1;
+            }
+            count
+        }
+
+        /// Cancel all pending timers.
+        pub fn clear(&mut self) {
+            self.timers.clear();
+        }
+    }
 }
 
-#[doc(hidden)]
-pub mod __priv {
-    pub mod crate_interface {
-        pub use crate_interface::{call_interface, def_interface, impl_interface};
+#[api_mod]
+/// Diagnostics-related API.
+pub mod diag {
+    use super::time::{Nanos, TimeValue};
+    use core::{fmt, panic::Location};
+
+    /// An API call which takes longer than its declared maximum duration.
+    ///
+    /// See the `max_duration` argument of the `#[api]` attribute in [`api_mod`](crate::api_mod).
+    #[derive(Debug, Clone, Copy)]
+    pub struct SlowApiCall {
+        /// Path of the API module.
+        pub module: &'static str,
+        /// Name of the API function.
+        pub function: &'static str,
+        /// Time taken by the call.
+        pub elapsed: TimeValue,
+        /// Declared maximum duration of the call.
+        pub max_duration: TimeValue,
+        /// Location of the caller.
+        pub caller: &'static Location<'static>,
     }
+
+    /// Report an API call which takes longer than its declared maximum duration.
+    extern fn report_slow_api_call(call: &SlowApiCall);
+
+    /// A call to an API function in a traced API module.
+    ///
+    /// See the `trace` argument of [`api_mod`](crate::api_mod).
+    #[derive(Debug, Clone, Copy)]
+    pub struct TracedApiCall {
+        /// Path of the API module.
+        pub module: &'static str,
+        /// Name of the API function.
+        pub function: &'static str,
+        /// Location of the caller.
+        pub caller: &'static Location<'static>,
+    }
+
+    /// Arguments of a traced API call, formatted as `name: value` pairs with [`Debug`](fmt::Debug).
+    #[derive(Clone, Copy)]
+    pub struct ApiCallArgs<'a>(pub &'a [(&'static str, &'a dyn fmt::Debug)]);
+
+    impl fmt::Debug for ApiCallArgs<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let mut map = f.debug_map();
+            for (name, value) in self.0 {
+                map.key(&format_args!("{name}")).value(value);
+            }
+            map.finish()
+        }
+    }
+
+    /// Receiver of the trace events of API calls in traced API modules.
+    pub trait ApiTraceSink: Sync {
+        /// Called before the API function is called.
+        fn enter(&self, call: &TracedApiCall, args: &ApiCallArgs);
+        /// Called after the API function returns, with the time taken by the call.
+        fn exit(&self, call: &TracedApiCall, elapsed: TimeValue);
+    }
+
+    /// Get the sink of the trace events of API calls, or `None` if tracing is disabled.
+    ///
+    /// This function is called on every call to an API function in a traced API module, and should be cheap.
+    extern fn api_trace_sink() -> Option<&'static dyn ApiTraceSink>;
+
+    /// Watchdog of a time-bounded API call, used by the code generated by [`api_mod`](crate::api_mod).
+    #[doc(hidden)]
+    pub struct ApiCallWatchdog {
+        #[cfg(feature = "watchdog")]
+        call: SlowApiCall,
+        #[cfg(feature = "watchdog")]
+        start: TimeValue,
+    }
+
+    impl ApiCallWatchdog {
+        /// Start watching an API call.
+        #[inline(always)]
+        #[track_caller]
+        #[allow(unused_variables)]
+        pub fn start(module: &'static str, function: &'static str, max_duration: Nanos) -> Self {
+            Self {
+                #[cfg(feature = "watchdog")]
+                call: SlowApiCall {
+                    module,
+                    function,
+                    elapsed: TimeValue::ZERO,
+                    max_duration: TimeValue::from_nanos(max_duration),
+                    caller: Location::caller(),
+                },
+                #[cfg(feature = "watchdog")]
+                start: super::time::current_time(),
+            }
+        }
+
+        /// Finish watching the API call, reporting it if it takes too long.
+        #[inline(always)]
+        pub fn finish(self) {
+            #[cfg(feature = "watchdog")]
+            {
+                let elapsed = super::time::current_time().saturating_sub(self.start);
+                if elapsed > self.call.max_duration {
+                    report_slow_api_call(&SlowApiCall {
+                        elapsed,
+                        ..self.call
+                    });
+                }
+            }
+        }
+    }
+
+    /// Tracer of an API call in a traced API module, used by the code generated by [`api_mod`](crate::api_mod).
+    #[doc(hidden)]
+    pub struct ApiCallTracer {
+        traced: Option<(&'static dyn ApiTraceSink, TracedApiCall, TimeValue)>,
+    }
+
+    impl ApiCallTracer {
+        /// Start tracing an API call, emitting the entry event.
+        #[inline(always)]
+        #[track_caller]
+        pub fn enter(module: &'static str, function: &'static str, args: &ApiCallArgs) -> Self {
+            let traced = api_trace_sink().map(|sink| {
+                let call = TracedApiCall {
+                    module,
+                    function,
+                    caller: Location::caller(),
+                };
+                sink.enter(&call, args);
+                (sink, call, super::time::current_time())
+            });
+            Self { traced }
+        }
+
+        /// Finish tracing the API call, emitting the exit event.
+        #[inline(always)]
+        pub fn exit(self) {
+            if let Some((sink, call, start)) = self.traced {
+                sink.exit(&call, super::time::current_time().saturating_sub(start));
+            }
+        }
+    }
+}
+
+#[api_mod]
+/// Virtual machine management API.
+pub mod vmm {
+    extern crate alloc;
+    use alloc::{boxed::Box, string::String, vec::Vec};
+
+    pub use axaddrspace::{GuestPhysAddr, HostPhysAddr};
+
+    /// Define an ID type as a transparent wrapper of an integer type.
+    #[cfg(not(feature = "legacy-ids"))]
+    macro_rules! def_id {
+        ($(#[$attr:meta])* $name:ident($inner:ty)) => {
+            $(#[$attr])*
+            #[repr(transparent)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+            pub struct $name($inner);
+
+            impl $name {
+                /// Create an ID from its raw value.
+                pub const fn new(raw: $inner) -> Self {
+                    Self(raw)
+                }
+
+                /// Get the raw value of the ID.
+                pub const fn as_raw(self) -> $inner {
+                    self.0
+                }
+            }
+
+            impl From<$inner> for $name {
+                fn from(raw: $inner) -> Self {
+                    Self(raw)
+                }
+            }
+
+            impl From<$name> for $inner {
+                fn from(id: $name) -> Self {
+                    id.0
+                }
+            }
+
+            impl core::fmt::Display for $name {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    core::fmt::Display::fmt(&self.0, f)
+                }
+            }
+        };
+    }
+
+    #[cfg(not(feature = "legacy-ids"))]
+    def_id! {
+        /// Virtual machine ID.
+        VMId(usize)
+    }
+    #[cfg(not(feature = "legacy-ids"))]
+    def_id! {
+        /// Virtual CPU ID, i.e. the index of a virtual CPU in its virtual machine.
+        VCpuId(usize)
+    }
+    #[cfg(not(feature = "legacy-ids"))]
+    def_id! {
+        /// Interrupt vector.
+        InterruptVector(u8)
+    }
+
+    #[cfg(not(feature = "legacy-ids"))]
+    impl VMId {
+        /// An ID which never refers to a virtual machine.
+        pub const INVALID: Self = Self(usize::MAX);
+
+        /// Check whether the ID is not [`VMId::INVALID`].
+        pub const fn is_valid(self) -> bool {
+            self.0 != Self::INVALID.0
+        }
+    }
+
+    #[cfg(not(feature = "legacy-ids"))]
+    impl VCpuId {
+        /// An ID which never refers to a virtual CPU.
+        pub const INVALID: Self = Self(usize::MAX);
+
+        /// Check whether the ID is not [`VCpuId::INVALID`].
+        pub const fn is_valid(self) -> bool {
+            self.0 != Self::INVALID.0
+        }
+
+        /// Check whether the ID is valid in a virtual machine with `vcpu_num` virtual CPUs.
+        pub const fn is_valid_for(self, vcpu_num: usize) -> bool {
+            self.0 < vcpu_num
+        }
+
+        /// Check whether the virtual CPU is set in a raw bitmask of virtual CPUs, in which bit `i` stands for the
+        /// virtual CPU with ID `i`. See [`VCpuMask::contains`] for sets of virtual CPUs.
+        pub const fn is_in_mask(self, mask: usize) -> bool {
+            self.0 < usize::BITS as usize && mask & (1 << self.0) != 0
+        }
+    }
+
+    /// Virtual machine ID.
+    #[cfg(feature = "legacy-ids")]
+    pub type VMId = usize;
+    /// Virtual CPU ID.
+    #[cfg(feature = "legacy-ids")]
+    pub type VCpuId = usize;
+    /// Interrupt vector.
+    #[cfg(feature = "legacy-ids")]
+    pub type InterruptVector = u8;
+
+    /// Set of virtual CPUs in a virtual machine, stored as a bitset in which bit `i` stands for the virtual CPU with ID
+    /// `i`.
+    ///
+    /// The first 64 virtual CPUs are stored inline, and the set grows on the heap for more.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+    pub struct VCpuMask {
+        low: u64,
+        /// Words of the virtual CPUs from 64 on, without trailing zero words.
+        high: Vec<u64>,
+    }
+
+    impl VCpuMask {
+        /// Create an empty set.
+        pub const fn new() -> Self {
+            Self {
+                low: 0,
+                high: Vec::new(),
+            }
+        }
+
+        /// Create a set of the first 64 virtual CPUs from a raw bitmask.
+        pub const fn from_bits(bits: u64) -> Self {
+            Self {
+                low: bits,
+                high: Vec::new(),
+            }
+        }
+
+        /// Get the bitmask of the first 64 virtual CPUs.
+        pub const fn low_bits(&self) -> u64 {
+            self.low
+        }
+
+        /// Get the index of a virtual CPU in the bitset.
+        #[allow(
+            clippy::useless_conversion,
+            reason = "`VCpuId` is `usize` with `legacy-ids`"
+        )]
+        fn index(vcpu_id: VCpuId) -> usize {
+            usize::from(vcpu_id)
+        }
+
+        /// Get the word holding the bit of a virtual CPU, and the bit in the word.
+        fn word_mut(&mut self, vcpu_id: VCpuId) -> (Option<&mut u64>, u64) {
+            let index = Self::index(vcpu_id);
+            let bit = 1 << (index % 64);
+            match index / 64 {
+                0 => (Some(&mut self.low), bit),
+                word => (self.high.get_mut(word - 1), bit),
+            }
+        }
+
+        /// Add a virtual CPU to the set.
+        pub fn insert(&mut self, vcpu_id: VCpuId) {
+            let word = Self::index(vcpu_id) / 64;
+            if word > self.high.len() {
+                self.high.resize(word, 0);
+            }
+            if let (Some(word), bit) = self.word_mut(vcpu_id) {
+                *word |= bit;
+            }
+        }
+
+        /// Remove a virtual CPU from the set.
+        pub fn remove(&mut self, vcpu_id: VCpuId) {
+            if let (Some(word), bit) = self.word_mut(vcpu_id) {
+                *word &= !bit;
+            }
+            while self.high.last() == Some(&0) {
+                self.high.pop();
+            }
+        }
+
+        /// Check whether a virtual CPU is in the set.
+        pub fn contains(&self, vcpu_id: VCpuId) -> bool {
+            let index = Self::index(vcpu_id);
+            let word = match index / 64 {
+                0 => self.low,
+                word => self.high.get(word - 1).copied().unwrap_or(0),
+            };
+            word & (1 << (index % 64)) != 0
+        }
+
+        /// Get the number of virtual CPUs in the set.
+        pub fn count(&self) -> usize {
+            self.words().map(|word| word.count_ones() as usize).sum()
+        }
+
+        /// Check whether the set is empty.
+        pub fn is_empty(&self) -> bool {
+            self.low == 0 && self.high.is_empty()
+        }
+
+        /// Iterate over the virtual CPUs in the set in ascending order of IDs.
+        pub fn iter(&self) -> impl Iterator<Item = VCpuId> + '_ {
+            self.words().enumerate().flat_map(|(index, word)| {
+                (0..64)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| VCpuId::from(index * 64 + bit))
+            })
+        }
+
+        /// Iterate over the words of the bitset.
+        fn words(&self) -> impl Iterator<Item = u64> + '_ {
+            core::iter::once(self.low).chain(self.high.iter().copied())
+        }
+    }
+
+    impl FromIterator<VCpuId> for VCpuMask {
+        fn from_iter<I: IntoIterator<Item = VCpuId>>(iter: I) -> Self {
+            let mut mask = Self::new();
+            for vcpu_id in iter {
+                mask.insert(vcpu_id);
+            }
+            mask
+        }
+    }
+
+    /// Get the ID of the current virtual machine.
+    extern fn current_vm_id() -> VMId;
+    /// Get the ID of the current virtual CPU.
+    extern fn current_vcpu_id() -> VCpuId;
+    /// Key of a per-virtual-CPU local variable, identified by its address.
+    ///
+    /// Keys are declared as statics, usually through [`vcpu_local_key!`](crate::vcpu_local_key) for typed access.
+    #[derive(Debug)]
+    pub struct LocalKey {
+        name: &'static str,
+    }
+
+    impl LocalKey {
+        /// Create a key, named for debugging.
+        pub const fn new(name: &'static str) -> Self {
+            Self { name }
+        }
+
+        /// Get the name of the key.
+        pub const fn name(&self) -> &'static str {
+            self.name
+        }
+    }
+
+    /// Set the value of a local variable of the current virtual CPU.
+    extern fn vcpu_local_set(key: &'static LocalKey, value: usize);
+    /// Get the value of a local variable of the current virtual CPU, or `None` if it has never been set.
+    extern fn vcpu_local_get(key: &'static LocalKey) -> Option<usize>;
+
+    /// Values which can be stored in per-virtual-CPU local variables.
+    pub trait LocalValue: Copy {
+        /// Convert the value into the raw value stored.
+        fn into_raw(self) -> usize;
+        /// Convert the raw value stored back into the value.
+        fn from_raw(raw: usize) -> Self;
+    }
+
+    macro_rules! impl_local_value {
+        ($($ty:ty),*) => {
+            $(
+                impl LocalValue for $ty {
+                    fn into_raw(self) -> usize {
+                        self as usize
+                    }
+
+                    fn from_raw(raw: usize) -> Self {
+                        raw as $ty
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_local_value!(usize, isize, u8, u16, u32, i8, i16, i32);
+
+    impl LocalValue for bool {
+        fn into_raw(self) -> usize {
+            self as usize
+        }
+
+        fn from_raw(raw: usize) -> Self {
+            raw != 0
+        }
+    }
+
+    impl<T> LocalValue for *mut T {
+        fn into_raw(self) -> usize {
+            self as usize
+        }
+
+        fn from_raw(raw: usize) -> Self {
+            raw as Self
+        }
+    }
+
+    /// Key of a per-virtual-CPU local variable of type `T`, declared with [`vcpu_local_key!`](crate::vcpu_local_key).
+    #[derive(Debug)]
+    pub struct TypedLocalKey<T: LocalValue> {
+        key: LocalKey,
+        _marker: core::marker::PhantomData<fn() -> T>,
+    }
+
+    impl<T: LocalValue> TypedLocalKey<T> {
+        /// Create a key, named for debugging.
+        pub const fn new(name: &'static str) -> Self {
+            Self {
+                key: LocalKey::new(name),
+                _marker: core::marker::PhantomData,
+            }
+        }
+
+        /// Get the untyped key.
+        pub fn key(&'static self) -> &'static LocalKey {
+            &self.key
+        }
+
+        /// Set the value of the variable of the current virtual CPU.
+        pub fn set(&'static self, value: T) {
+            vcpu_local_set(&self.key, value.into_raw())
+        }
+
+        /// Get the value of the variable of the current virtual CPU, or `None` if it has never been set.
+        pub fn get(&'static self) -> Option<T> {
+            vcpu_local_get(&self.key).map(T::from_raw)
+        }
+    }
+
+    /// Declare keys of typed per-virtual-CPU local variables.
+    ///
+    /// ```rust, no_run
+    /// axvisor_api::vcpu_local_key! {
+    ///     /// Whether the local APIC timer is armed.
+    ///     pub static LAPIC_TIMER_ARMED: bool;
+    /// }
+    ///
+    /// LAPIC_TIMER_ARMED.set(true);
+    /// assert_eq!(LAPIC_TIMER_ARMED.get(), Some(true));
+    /// ```
+    #[macro_export]
+    macro_rules! vcpu_local_key {
+        ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty;)*) => {
+            $(
+                $(#[$attr])*
+                $vis static $name: $crate::vmm::TypedLocalKey<$ty> =
+                    $crate::vmm::TypedLocalKey::new(concat!(module_path!(), "::", stringify!($name)));
+            )*
+        };
+    }
+
+    /// Get the number of virtual CPUs in a virtual machine.
+    extern fn vcpu_num(vm_id: VMId) -> Option<usize>;
+    /// Get the set of active virtual CPUs in a virtual machine.
+    extern fn active_vcpus(vm_id: VMId) -> Option<VCpuMask>;
+    /// Get the number of virtual CPUs in the current virtual machine.
+    pub fn current_vm_vcpu_num() -> usize {
+        vcpu_num(current_vm_id()).unwrap()
+    }
+    /// Get the set of active virtual CPUs in the current virtual machine.
+    pub fn current_vm_active_vcpus() -> VCpuMask {
+        active_vcpus(current_vm_id()).unwrap()
+    }
+
+    /// Type of a virtual machine, i.e. the kind of its guest.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub enum VmType {
+        /// A Linux guest.
+        Linux,
+        /// An ArceOS guest.
+        ArceOS,
+        /// A real-time OS guest.
+        Rtos,
+        /// A guest of other kinds.
+        Other,
+    }
+
+    /// An emulated or passthrough device of a virtual machine.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct VmDeviceDesc {
+        /// ID of the device.
+        pub id: DeviceId,
+        /// Name of the device.
+        pub name: String,
+        /// Guest physical address of the register region of the device, if any.
+        pub base: Option<GuestPhysAddr>,
+    }
+
+    /// A snapshot of the configuration of a virtual machine.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct VmConfigView {
+        /// Name of the virtual machine.
+        pub name: String,
+        /// Type of the virtual machine.
+        pub vm_type: VmType,
+        /// Size of the guest memory in bytes.
+        pub memory_size: usize,
+        /// Number of virtual CPUs.
+        pub vcpu_num: usize,
+        /// Path of the kernel image, if it is loaded from a file.
+        pub kernel_path: Option<String>,
+        /// Guest physical address where the kernel image is loaded.
+        pub kernel_load_addr: GuestPhysAddr,
+        /// Entry point of the boot virtual CPU.
+        pub entry_point: GuestPhysAddr,
+        /// Devices of the virtual machine.
+        pub devices: Vec<VmDeviceDesc>,
+    }
+
+    /// Get the configuration of a virtual machine.
+    extern fn vm_config(vm_id: VMId) -> Option<VmConfigView>;
+    /// Get the name of a virtual machine.
+    extern fn vm_name(vm_id: VMId) -> Option<String>;
+    /// Fill `buf` with the IDs of the existing virtual machines in ascending order.
+    ///
+    /// Returns the number of existing virtual machines, which may be larger than `buf.len()`.
+    extern fn fill_vm_ids(buf: &mut [VMId]) -> usize;
+    /// Get the IDs of the existing virtual machines in ascending order.
+    pub fn vm_ids() -> Vec<VMId> {
+        let mut ids = Vec::new();
+        loop {
+            let count = fill_vm_ids(&mut ids);
+            if count <= ids.len() {
+                ids.truncate(count);
+                return ids;
+            }
+            ids.resize(count, VMId::from(0usize));
+        }
+    }
+
+    /// Options to clone a virtual machine with [`clone_vm`].
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct CloneOptions {
+        /// Name of the new virtual machine, `None` to derive one from the name of the template.
+        pub name: Option<String>,
+        /// Start the new virtual machine after cloning, instead of leaving it paused.
+        pub start: bool,
+    }
+
+    /// Hook called when a virtual machine is cloned, with the IDs of the template and the new virtual machine.
+    ///
+    /// Device models use it to re-instantiate the devices of the template in the new virtual machine, e.g. to
+    /// re-open backends which cannot be shared.
+    pub type CloneHook =
+        Box<dyn Fn(VMId, VMId) -> crate::error::AxvisorResult + Send + Sync + 'static>;
+
+    /// Clone a virtual machine from a paused template virtual machine.
+    ///
+    /// The guest memory of the new virtual machine shares the frames of the template copy-on-write, the virtual CPU
+    /// states are copied, and the devices are re-instantiated by the hooks registered with
+    /// [`register_clone_hook`]. If any hook fails, the new virtual machine is destroyed and the error is returned.
+    ///
+    /// Fails with [`BadState`](crate::error::AxvisorError::BadState) if the template is not paused.
+    extern fn clone_vm(
+        template_vm_id: VMId,
+        options: CloneOptions,
+    ) -> crate::error::AxvisorResult<VMId>;
+    /// Register a hook called when a virtual machine is cloned, named after the component owning it.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if there is already a hook with the
+    /// same owner.
+    extern fn register_clone_hook(
+        owner: &'static str,
+        hook: CloneHook,
+    ) -> crate::error::AxvisorResult;
+    /// Unregister the clone hook of a component.
+    extern fn unregister_clone_hook(owner: &'static str) -> crate::error::AxvisorResult;
+
+    /// Limit the CPU features exposed to the guest of a virtual machine to the ones in `mask`, by adjusting the ID
+    /// registers (or `CPUID` on x86_64) the guest reads, and disabling the features where the hardware allows.
+    ///
+    /// The guest sees the features in both `mask` and [`host_cpu_features`](crate::arch::host_cpu_features). Fails with
+    /// [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if it has been started, or with
+    /// [`Unsupported`](crate::error::AxvisorError::Unsupported) if a host feature outside `mask` cannot be hidden.
+    extern fn set_guest_cpu_feature_mask(
+        vm_id: VMId,
+        mask: crate::arch::CpuFeatures,
+    ) -> crate::error::AxvisorResult;
+
+    /// Result of an interrupt injection.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum InjectionResult {
+        /// The interrupt is delivered to the virtual CPU.
+        Delivered,
+        /// The interrupt is queued, and will be delivered when the virtual CPU is able to take it.
+        Queued,
+        /// The interrupt is already pending on the virtual CPU, and is merged with the pending one.
+        Coalesced,
+        /// The virtual CPU is not running (e.g. not powered on yet), and the interrupt is dropped.
+        VcpuNotRunning,
+        /// The virtual machine or the virtual CPU does not exist, or the vector is invalid.
+        InvalidTarget,
+    }
+
+    impl InjectionResult {
+        /// Check whether the interrupt will be or has been taken by the virtual CPU, i.e. it is not dropped.
+        pub fn is_accepted(self) -> bool {
+            matches!(self, Self::Delivered | Self::Queued | Self::Coalesced)
+        }
+    }
+
+    /// Inject an interrupt to a virtual CPU.
+    extern fn inject_interrupt(vm_id: VMId, vcpu_id: VCpuId, vector: InterruptVector);
+    /// Inject an interrupt to a virtual CPU, and report what happened to it.
+    ///
+    /// Device models implementing level-triggered or resampled interrupts should use this function to learn whether
+    /// an injection is coalesced or dropped.
+    extern fn try_inject_interrupt(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+        vector: InterruptVector,
+    ) -> InjectionResult;
+    /// A message-signaled interrupt, i.e. a write of `data` to `addr` by a device.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MsiMessage {
+        /// Address written, e.g. the `GITS_TRANSLATER` of an ITS on aarch64, or in the `0xFEEx_xxxx` window on x86_64.
+        pub addr: u64,
+        /// Data written.
+        pub data: u32,
+    }
+
+    impl MsiMessage {
+        /// Decode the message in the x86 format, returning the destination APIC ID and the vector, or `None` if the
+        /// address is not in the `0xFEEx_xxxx` window.
+        ///
+        /// Only the physical destination mode with 8-bit APIC IDs is considered.
+        pub const fn x86_decode(self) -> Option<(u8, u8)> {
+            if self.addr >> 20 != 0xfee {
+                return None;
+            }
+            Some(((self.addr >> 12) as u8, self.data as u8))
+        }
+    }
+
+    /// Inject a message-signaled interrupt written by a device of a virtual machine, e.g. a passthrough device or
+    /// an emulated virtio-pci device.
+    ///
+    /// The message is translated by the emulated interrupt controller (e.g. the ITS on aarch64, or the local APICs on
+    /// x86_64) in the same way as [`translate_msi`]. Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg)
+    /// if the message cannot be translated.
+    extern fn inject_msi(vm_id: VMId, addr: u64, data: u32) -> crate::error::AxvisorResult;
+    /// Translate a message-signaled interrupt of a virtual machine to the virtual CPU and the interrupt it targets,
+    /// without injecting it.
+    extern fn translate_msi(vm_id: VMId, msg: MsiMessage) -> Option<(VCpuId, IrqVector)>;
+
+    /// Notify that a virtual CPU timer has expired.
+    ///
+    /// TODO: determine whether we can skip this function.
+    extern fn notify_vcpu_timer_expired(vm_id: VMId, vcpu_id: VCpuId);
+
+    /// ID of an emulated device in a virtual machine, assigned by the hypervisor when the device is registered.
+    pub type DeviceId = usize;
+
+    /// Quiesce an emulated device, e.g. before taking a snapshot, migrating or resetting the virtual machine.
+    ///
+    /// When this function returns, the device has finished or aborted its in-flight DMA and queue processing, and
+    /// will neither access guest memory nor raise interrupts until it is resumed with [`resume_device`]. Guest
+    /// accesses to the device in the meantime are stalled or deferred.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the device does not exist, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if it is already quiesced.
+    extern fn quiesce_device(vm_id: VMId, dev_id: DeviceId) -> crate::error::AxvisorResult;
+    /// Resume an emulated device quiesced with [`quiesce_device`].
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the device does not exist, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if it is not quiesced.
+    extern fn resume_device(vm_id: VMId, dev_id: DeviceId) -> crate::error::AxvisorResult;
+
+    /// Architecture-neutral snapshot of the register state of a virtual CPU, e.g. for crash analysis.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct VCpuSnapshot {
+        /// Program counter.
+        pub pc: u64,
+        /// Stack pointer.
+        pub sp: u64,
+        /// Processor state, i.e. `PSTATE` on aarch64, `sstatus` on riscv64, or `RFLAGS` on x86_64.
+        pub status: u64,
+        /// General-purpose registers in their architectural order, i.e. `x0`-`x30` on aarch64, `x0`-`x31` on
+        /// riscv64, or `RAX`-`R15` on x86_64.
+        pub gprs: Vec<u64>,
+    }
+
+    /// Sink of guest memory dumps, receiving chunks of guest memory with their guest physical addresses.
+    ///
+    /// Returning an error aborts the dump.
+    pub type DumpSink<'a> = &'a mut dyn FnMut(GuestPhysAddr, &[u8]) -> crate::error::AxvisorResult;
+
+    /// Take a snapshot of the register state of a virtual CPU.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the virtual CPU does
+    /// not exist, or with [`BadState`](crate::error::AxvisorError::BadState) if the virtual CPU is running on another
+    /// physical CPU, as its state would be inconsistent.
+    extern fn snapshot_vcpu(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+    ) -> crate::error::AxvisorResult<VCpuSnapshot>;
+    /// Dump a range of the guest memory of a virtual machine to a sink, in ascending order of addresses, returning the
+    /// number of bytes dumped.
+    ///
+    /// Parts of the range not backed by memory, e.g. emulated MMIO regions, are skipped. Fails with
+    /// [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or with the error
+    /// returned by the sink.
+    extern fn dump_guest_memory(
+        vm_id: VMId,
+        gpa_range: crate::guest_memory::GuestPhysAddrRange,
+        sink: DumpSink,
+    ) -> crate::error::AxvisorResult<usize>;
+
+    /// Channel of doorbells between virtual machines.
+    pub type DoorbellChannel = u32;
+    /// Handler of doorbells, receiving the channel rung.
+    pub type DoorbellHandler = Box<dyn Fn(DoorbellChannel) + Send + Sync + 'static>;
+
+    /// Ring a doorbell of a virtual machine, calling the handler registered for the channel.
+    ///
+    /// The handler is called asynchronously, and doorbells rung again before the handler is called may be merged. Fails
+    /// with [`NotFound`](crate::error::AxvisorError::NotFound) if no handler is registered for the channel.
+    extern fn signal_vm(target_vm: VMId, channel: DoorbellChannel) -> crate::error::AxvisorResult;
+    /// Register a handler for the doorbells of a channel of a virtual machine, e.g. to inject an interrupt to the
+    /// driver of an inter-VM transport.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if there is already a handler for the
+    /// channel.
+    extern fn register_doorbell_handler(
+        vm_id: VMId,
+        channel: DoorbellChannel,
+        handler: DoorbellHandler,
+    ) -> crate::error::AxvisorResult;
+    /// Unregister the handler for the doorbells of a channel of a virtual machine.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if there is no handler for the channel.
+    extern fn unregister_doorbell_handler(
+        vm_id: VMId,
+        channel: DoorbellChannel,
+    ) -> crate::error::AxvisorResult;
+
+    /// Reason of a VM exit, shared by all architectures.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub enum ExitReason {
+        /// An MMIO access to an emulated region.
+        Mmio {
+            /// Guest physical address accessed.
+            addr: GuestPhysAddr,
+            /// Width of the access.
+            width: axaddrspace::device::AccessWidth,
+            /// Whether the access is a write.
+            is_write: bool,
+        },
+        /// A port I/O access, x86_64 only.
+        Pio {
+            /// Port accessed.
+            port: u16,
+            /// Width of the access.
+            width: axaddrspace::device::AccessWidth,
+            /// Whether the access is a write.
+            is_write: bool,
+        },
+        /// A hypercall, e.g. `hvc` or `smc` on aarch64, `ecall` from VS-mode on riscv64, or `vmcall` on x86_64.
+        Hypercall {
+            /// Hypercall number.
+            nr: u64,
+        },
+        /// The guest waits for interrupts or events, e.g. `wfi`/`wfe` on aarch64 and riscv64, or `hlt` on x86_64.
+        Halt,
+        /// A trapped system register access.
+        SysRegAccess {
+            /// Encoding of the register.
+            encoding: crate::arch::SysRegEncoding,
+            /// Whether the access is a write.
+            is_write: bool,
+        },
+        /// A fault on a guest physical address not backed by memory or an emulated region.
+        NestedPageFault {
+            /// Guest physical address accessed.
+            addr: GuestPhysAddr,
+            /// Whether the access is a write.
+            is_write: bool,
+        },
+        /// An external interrupt arrives at the physical CPU.
+        ExternalInterrupt {
+            /// Host interrupt number.
+            vector: usize,
+        },
+        /// The guest becomes able to take interrupts, x86_64 only.
+        InterruptWindow,
+        /// The guest requests to power off or reset the system, e.g. through PSCI or SBI.
+        SystemEvent,
+        /// An exit specific to an architecture, with the raw exit code, e.g. the exception class of `ESR_EL2` on
+        /// aarch64, `scause` on riscv64, or the basic exit reason of VMX on x86_64.
+        ArchSpecific {
+            /// Raw exit code.
+            code: u64,
+        },
+        /// The guest entry fails or the virtual CPU is in an unrecoverable state, with the raw error code.
+        Failure {
+            /// Raw error code.
+            code: u64,
+        },
+    }
+
+    impl ExitReason {
+        /// Get the name of the kind of the exit, e.g. for statistics and tracing.
+        pub const fn name(&self) -> &'static str {
+            match self {
+                Self::Mmio { .. } => "mmio",
+                Self::Pio { .. } => "pio",
+                Self::Hypercall { .. } => "hypercall",
+                Self::Halt => "halt",
+                Self::SysRegAccess { .. } => "sysreg",
+                Self::NestedPageFault { .. } => "npf",
+                Self::ExternalInterrupt { .. } => "irq",
+                Self::InterruptWindow => "irq-window",
+                Self::SystemEvent => "system-event",
+                Self::ArchSpecific { .. } => "arch",
+                Self::Failure { .. } => "failure",
+            }
+        }
+    }
+
+    /// Phase of the handling of a VM exit at which exit hooks are called.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ExitPhase {
+        /// Before the exit is handled, right after the VM exit.
+        Before,
+        /// After the exit is handled, right before the next VM entry.
+        After,
+    }
+
+    /// ID of an exit hook.
+    pub type ExitHookId = usize;
+
+    /// Hook observing VM exits, receiving the virtual CPU which exits, the phase and the reason of the exit.
+    ///
+    /// Hooks are called in the run loop of the virtual CPU, and should return quickly.
+    pub type ExitHook = Box<dyn Fn(VCpuId, ExitPhase, &ExitReason) + Send + Sync + 'static>;
+
+    /// Register a hook observing the VM exits of a virtual machine.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist.
+    extern fn register_exit_hook(
+        vm_id: VMId,
+        hook: ExitHook,
+    ) -> crate::error::AxvisorResult<ExitHookId>;
+    /// Unregister an exit hook.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the hook does not exist.
+    extern fn unregister_exit_hook(id: ExitHookId) -> crate::error::AxvisorResult;
+
+    /// Type of a guest memory access.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AccessType {
+        /// Data read.
+        Read,
+        /// Data write.
+        Write,
+        /// Instruction fetch.
+        Execute,
+    }
+
+    /// Information about the instruction causing a fault, as decoded by the hardware or the hypervisor.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct InstructionInfo {
+        /// Length of the instruction in bytes, `0` if unknown.
+        pub len: u8,
+        /// Width of the access, if known.
+        pub width: Option<axaddrspace::device::AccessWidth>,
+        /// Index of the general-purpose register transferred, if known.
+        pub reg: Option<u8>,
+        /// Whether the value read is sign-extended.
+        pub sign_extend: bool,
+    }
+
+    /// An exception to be injected to a guest, in the architecture-specific encoding, e.g. the exception class of
+    /// `ESR_EL1` on aarch64, `scause` on riscv64, or the vector on x86_64.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct GuestException {
+        /// Exception number.
+        pub vector: u32,
+        /// Error code or syndrome, if any.
+        pub error_code: Option<u32>,
+    }
+
+    /// Resolution of a guest fault by a fault handler.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum FaultResolution {
+        /// The cause of the fault is fixed, e.g. the page is populated, and the instruction should be retried.
+        Retry,
+        /// The access is emulated, and the instruction should be skipped.
+        Emulated,
+        /// The exception should be injected to the guest.
+        Inject(GuestException),
+    }
+
+    /// ID of a fault handler.
+    pub type FaultHandlerId = usize;
+
+    /// Handler of stage-2 faults, receiving the faulting virtual CPU, the guest physical address, the type of the
+    /// access and the instruction information.
+    ///
+    /// The handler returns `None` if it does not handle the fault, in which case the next handler is tried, and the
+    /// hypervisor falls back to its default behavior if no handler handles it.
+    pub type FaultHandler = Box<
+        dyn Fn(VCpuId, GuestPhysAddr, AccessType, &InstructionInfo) -> Option<FaultResolution>
+            + Send
+            + Sync
+            + 'static,
+    >;
+
+    /// Register a handler of stage-2 faults of a virtual machine, e.g. for lazy population or ballooning.
+    ///
+    /// Handlers are tried in the order of registration. Fails with [`NotFound`](crate::error::AxvisorError::NotFound)
+    /// if the virtual machine does not exist.
+    extern fn register_fault_handler(
+        vm_id: VMId,
+        handler: FaultHandler,
+    ) -> crate::error::AxvisorResult<FaultHandlerId>;
+    /// Unregister a fault handler.
+    extern fn unregister_fault_handler(id: FaultHandlerId) -> crate::error::AxvisorResult;
+
+    /// Kind of a handler of guest events.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum HandlerKind {
+        /// Handler of MMIO accesses, whose range is a guest physical address range.
+        Mmio,
+        /// Handler of port I/O accesses, whose range is a port range.
+        Pio,
+        /// Handler of hypercalls, whose range is a hypercall number range.
+        Hypercall,
+        /// Handler of system register accesses, whose range is a [`SysRegEncoding`](crate::arch::SysRegEncoding)
+        /// range.
+        SysReg,
+        /// Handler of guest memory access faults, whose range is a guest physical address range.
+        Fault,
+    }
+
+    /// Description of a handler of guest events registered in a virtual machine, used for debugging.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct HandlerDesc {
+        /// Kind of the handler.
+        pub kind: HandlerKind,
+        /// Start of the range handled, the meaning of which depends on [`HandlerDesc::kind`].
+        pub start: u64,
+        /// Size of the range handled.
+        pub size: u64,
+        /// Tag of the component which registered the handler, e.g. the name of a device model.
+        pub owner: &'static str,
+    }
+
+    impl core::fmt::Display for HandlerDesc {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(
+                f,
+                "{:?} [{:#x}, {:#x}) by {}",
+                self.kind,
+                self.start,
+                self.start.saturating_add(self.size),
+                self.owner
+            )
+        }
+    }
+
+    /// List the handlers of guest events registered in a virtual machine, including the ones registered for all
+    /// virtual machines, sorted by kind and start of range.
+    extern fn list_handlers(vm_id: VMId) -> Vec<HandlerDesc>;
+
+    /// Yield the physical CPU running the current virtual CPU to other runnable virtual CPUs or tasks, e.g. on a
+    /// guest spin-wait hint.
+    extern fn yield_current_vcpu();
+    /// Block the current virtual CPU until it is woken up with [`wake_vcpu`] or an interrupt is injected to it, e.g.
+    /// on a guest `wfi` or `hlt`.
+    ///
+    /// This function returns immediately if the virtual CPU has been woken up since it last blocked.
+    extern fn block_current_vcpu();
+    /// Wake up a virtual CPU blocked with [`block_current_vcpu`].
+    extern fn wake_vcpu(vm_id: VMId, vcpu_id: VCpuId) -> crate::error::AxvisorResult;
+    /// Set the scheduling priority of a virtual CPU, where a larger value means a higher priority.
+    ///
+    /// The range of meaningful priorities depends on the scheduler of the hypervisor, and values out of the range
+    /// are clamped.
+    extern fn set_vcpu_priority(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+        prio: i32,
+    ) -> crate::error::AxvisorResult;
+
+    /// Restrict a virtual CPU to run on a set of physical CPUs, migrating it if it is on another one.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the virtual CPU does
+    /// not exist, or with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if none of the physical CPUs exists.
+    extern fn set_vcpu_affinity(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+        host_cpus: crate::host::HostCpuMask,
+    ) -> crate::error::AxvisorResult;
+    /// Get the set of physical CPUs a virtual CPU may run on.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the virtual CPU does
+    /// not exist.
+    extern fn vcpu_affinity(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+    ) -> crate::error::AxvisorResult<crate::host::HostCpuMask>;
+    /// Get the physical CPU a virtual CPU is running on, or is queued on if it is runnable.
+    ///
+    /// Returns `None` if the virtual CPU does not exist, or is blocked. The result may be stale as soon as it is
+    /// returned, unless the virtual CPU is the current one.
+    extern fn current_host_cpu_of_vcpu(vm_id: VMId, vcpu_id: VCpuId) -> Option<usize>;
+
+    /// Handler of budget overruns of virtual CPUs with deadline parameters, receiving the virtual CPU and the time by
+    /// which it exceeded its budget.
+    pub type OverrunHandler =
+        Box<dyn Fn(VMId, VCpuId, crate::time::TimeValue) + Send + Sync + 'static>;
+
+    /// Give a virtual CPU guaranteed execution of `budget` in every `period`, enforced by the scheduler of the
+    /// hypervisor.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if `budget` is zero or longer than `period`,
+    /// or with [`Busy`](crate::error::AxvisorError::Busy) if the parameters fail the admission control, i.e. the
+    /// physical CPU would be overcommitted.
+    extern fn set_vcpu_deadline_params(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+        period: crate::time::TimeValue,
+        budget: crate::time::TimeValue,
+    ) -> crate::error::AxvisorResult;
+    /// Remove the deadline parameters of a virtual CPU, making it scheduled as a normal one.
+    extern fn clear_vcpu_deadline_params(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+    ) -> crate::error::AxvisorResult;
+    /// Set the handler of budget overruns of virtual CPUs with deadline parameters, replacing the previous one.
+    extern fn set_overrun_handler(handler: Option<OverrunHandler>);
+
+    /// Real-time scheduling parameters of a virtual CPU, scheduled as a server with a budget replenished every period,
+    /// e.g. a sporadic server.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct SchedParams {
+        /// Execution time the virtual CPU may consume in every period, or zero for no reservation, in which case the
+        /// virtual CPU is scheduled as a normal one by its priority.
+        pub budget: crate::time::TimeValue,
+        /// Replenishment period of the budget.
+        pub period: crate::time::TimeValue,
+        /// Priority of the virtual CPU while it has budget left, as in [`set_vcpu_priority`].
+        pub priority: i32,
+    }
+
+    /// Set the real-time scheduling parameters of a virtual CPU at once, replacing the ones set with
+    /// [`set_vcpu_priority`] and [`set_vcpu_deadline_params`].
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the virtual CPU does
+    /// not exist, with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the budget is longer than the period,
+    /// with [`Busy`](crate::error::AxvisorError::Busy) if the parameters fail the admission control, or with
+    /// [`Unsupported`](crate::error::AxvisorError::Unsupported) if the scheduler of the hypervisor does not support
+    /// budgets.
+    extern fn set_vcpu_sched_params(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+        params: SchedParams,
+    ) -> crate::error::AxvisorResult;
+    /// Get the real-time scheduling parameters of a virtual CPU.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the virtual CPU does
+    /// not exist.
+    extern fn vcpu_sched_params(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+    ) -> crate::error::AxvisorResult<SchedParams>;
+
+    /// Interrupt number of a guest interrupt line, e.g. the INTID of an SPI on GIC, the interrupt source ID on PLIC, or
+    /// the GSI on x86.
+    pub type IrqVector = u32;
+
+    /// Trigger mode of a guest interrupt line.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum IrqTrigger {
+        /// Edge-triggered.
+        #[default]
+        Edge,
+        /// Level-triggered.
+        Level,
+    }
+
+    /// Constraints on a guest interrupt line to be allocated with [`alloc_guest_irq`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct IrqConstraints {
+        /// Allocate within `min..=max` only, `None` for any interrupt line available to devices (e.g. SPIs on GIC).
+        pub range: Option<(IrqVector, IrqVector)>,
+        /// The interrupt line to allocate if it is available, e.g. to keep the number used by a legacy device.
+        pub preferred: Option<IrqVector>,
+        /// Trigger mode of the interrupt line.
+        pub trigger: IrqTrigger,
+    }
+
+    /// Allocate a guest interrupt line for an emulated device in a virtual machine.
+    ///
+    /// Device models and the device tree (or ACPI tables) of the virtual machine should take the interrupt numbers
+    /// from here, instead of hard-coding them. Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if no
+    /// interrupt line satisfying the constraints is available, or with
+    /// [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist.
+    extern fn alloc_guest_irq(
+        vm_id: VMId,
+        constraints: IrqConstraints,
+    ) -> crate::error::AxvisorResult<IrqVector>;
+    /// Free a guest interrupt line allocated with [`alloc_guest_irq`].
+    extern fn free_guest_irq(vm_id: VMId, irq: IrqVector);
+
+    /// Route a host physical interrupt to a guest interrupt line of a virtual machine, e.g. for a passthrough device.
+    ///
+    /// The hypervisor forwards the host interrupt to the guest interrupt line whenever it arrives, and takes care of
+    /// its acknowledgement and deactivation, e.g. with hardware-deactivated virtual interrupts if supported. Fails with
+    /// [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if the host interrupt is already routed.
+    extern fn map_host_irq_to_guest(
+        host_irq: usize,
+        vm_id: VMId,
+        guest_irq: IrqVector,
+    ) -> crate::error::AxvisorResult;
+    /// Stop routing a host physical interrupt to the guest.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the host interrupt is not routed.
+    extern fn unmap_host_irq(host_irq: usize) -> crate::error::AxvisorResult;
+    /// Set or clear the pending state of a guest interrupt line of a virtual machine, e.g. to model the level of a
+    /// level-triggered interrupt line of an emulated device.
+    extern fn set_irq_pending(
+        vm_id: VMId,
+        guest_irq: IrqVector,
+        pending: bool,
+    ) -> crate::error::AxvisorResult;
+
+    /// Handler of the end of interrupts (EOIs) of a guest interrupt, receiving the interrupt.
+    pub type EoiHandler = Box<dyn Fn(IrqVector) + Send + Sync + 'static>;
+
+    /// Register a handler called when the guest of a virtual machine signals the end of a guest interrupt, e.g. to
+    /// re-enable the host interrupt routed to a level-triggered guest interrupt line.
+    ///
+    /// The EOIs of the interrupt are trapped while the handler is registered, e.g. with the EOI maintenance interrupt
+    /// of list registers on aarch64, or the EOI-exit bitmap on x86_64. Fails with
+    /// [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or with
+    /// [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if there is already a handler for the interrupt.
+    extern fn register_eoi_handler(
+        vm_id: VMId,
+        guest_irq: IrqVector,
+        handler: EoiHandler,
+    ) -> crate::error::AxvisorResult;
+    /// Unregister the EOI handler of a guest interrupt of a virtual machine.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if there is no handler for the interrupt.
+    extern fn unregister_eoi_handler(
+        vm_id: VMId,
+        guest_irq: IrqVector,
+    ) -> crate::error::AxvisorResult;
+
+    /// A set of guest interrupts, with IDs less than [`InterruptBitmap::CAPACITY`].
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct InterruptBitmap {
+        bits: [u64; InterruptBitmap::CAPACITY / 64],
+    }
+
+    impl InterruptBitmap {
+        /// Number of interrupt IDs covered by the bitmap, enough for the SGIs, PPIs and SPIs of a GIC, the vectors of
+        /// a local APIC, and the sources of a PLIC.
+        pub const CAPACITY: usize = 1024;
+
+        /// Create an empty set.
+        pub const fn new() -> Self {
+            Self {
+                bits: [0; Self::CAPACITY / 64],
+            }
+        }
+
+        /// Add an interrupt to the set, returning `false` if its ID is out of the capacity.
+        pub fn insert(&mut self, irq: IrqVector) -> bool {
+            let irq = irq as usize;
+            if irq >= Self::CAPACITY {
+                return false;
+            }
+            self.bits[irq / 64] |= 1 << (irq % 64);
+            true
+        }
+
+        /// Remove an interrupt from the set.
+        pub fn remove(&mut self, irq: IrqVector) {
+            if let Some(word) = self.bits.get_mut(irq as usize / 64) {
+                *word &= !(1 << (irq % 64));
+            }
+        }
+
+        /// Check whether an interrupt is in the set.
+        pub fn contains(&self, irq: IrqVector) -> bool {
+            self.bits
+                .get(irq as usize / 64)
+                .is_some_and(|word| word & (1 << (irq % 64)) != 0)
+        }
+
+        /// Check whether the set is empty.
+        pub fn is_empty(&self) -> bool {
+            self.bits.iter().all(|&word| word == 0)
+        }
+
+        /// Get the number of interrupts in the set.
+        pub fn len(&self) -> usize {
+            self.bits
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum()
+        }
+
+        /// Iterate over the interrupts in the set in ascending order.
+        pub fn iter(&self) -> impl Iterator<Item = IrqVector> + '_ {
+            self.bits.iter().enumerate().flat_map(|(index, &word)| {
+                (0..64)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| (index * 64 + bit) as IrqVector)
+            })
+        }
+    }
+
+    /// Get the interrupts injected into a virtual CPU but not yet acknowledged by the guest, including the shared
+    /// interrupts targeting it, as recorded by the emulated interrupt controller.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the virtual CPU does
+    /// not exist.
+    extern fn pending_interrupts(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+    ) -> crate::error::AxvisorResult<InterruptBitmap>;
+    /// Check whether a guest interrupt is enabled, i.e. unmasked, in the emulated interrupt controller of a virtual
+    /// machine.
+    ///
+    /// Interrupts private to virtual CPUs, e.g. the SGIs and PPIs of a GIC, are reported as enabled if they are
+    /// enabled on any virtual CPU. Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual
+    /// machine does not exist, or with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the interrupt does
+    /// not exist.
+    extern fn is_interrupt_enabled(
+        vm_id: VMId,
+        irq: IrqVector,
+    ) -> crate::error::AxvisorResult<bool>;
+}
+
+#[api_mod]
+/// Host interrupt API, through which components take host interrupts away from the generic handler of the hypervisor,
+/// e.g. to drive the interrupts of passthrough devices.
+pub mod interrupt {
+    extern crate alloc;
+    use alloc::boxed::Box;
+
+    /// Number of a host physical interrupt.
+    pub type HostIrq = usize;
+    /// Handler of a claimed host interrupt, receiving the interrupt.
+    ///
+    /// The handler is called in the interrupt context, and the interrupt stays active, i.e. is not raised again, until
+    /// it is completed with [`complete_host_irq`], which may happen later outside the handler.
+    pub type HostIrqHandler = Box<dyn Fn(HostIrq) + Send + Sync + 'static>;
+
+    /// Exclusive ownership of a claimed host interrupt, which is released to the hypervisor when dropped.
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    #[must_use = "the interrupt is released when the handle is dropped"]
+    pub struct HostIrqHandle {
+        irq: HostIrq,
+    }
+
+    impl HostIrqHandle {
+        /// Create a handle owning the claimed interrupt, used by the implementations of [`claim_host_irq`].
+        pub fn from_raw(irq: HostIrq) -> Self {
+            Self { irq }
+        }
+
+        /// Get the interrupt.
+        pub fn irq(&self) -> HostIrq {
+            self.irq
+        }
+    }
+
+    impl Drop for HostIrqHandle {
+        fn drop(&mut self) {
+            release_host_irq(self.irq);
+        }
+    }
+
+    /// Claim a host interrupt exclusively, so that it is delivered to the handler instead of the generic handler of
+    /// the hypervisor until the returned handle is dropped.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the interrupt does not exist, or with
+    /// [`Busy`](crate::error::AxvisorError::Busy) if it is claimed, used by the hypervisor itself, or routed to a
+    /// guest with [`map_host_irq_to_guest`](crate::vmm::map_host_irq_to_guest).
+    extern fn claim_host_irq(
+        irq: HostIrq,
+        handler: HostIrqHandler,
+    ) -> crate::error::AxvisorResult<HostIrqHandle>;
+    /// Complete the handling of a claimed host interrupt, i.e. signal the end of interrupt to the interrupt
+    /// controller, so that the interrupt can be raised again.
+    extern fn complete_host_irq(handle: &HostIrqHandle);
+    /// Release a claimed host interrupt to the generic handler of the hypervisor, called when its handle is dropped.
+    ///
+    /// The interrupt is completed first if it is active.
+    extern fn release_host_irq(irq: HostIrq);
+}
+
+#[api_mod]
+/// Guest-memory-access API.
+pub mod guest_memory {
+    extern crate alloc;
+    pub use axaddrspace::{GuestPhysAddr, GuestPhysAddrRange};
+
+    use super::vmm::VMId;
+    use alloc::boxed::Box;
+
+    /// Read bytes from the guest physical memory of a virtual machine into `buf`.
+    ///
+    /// Returns the number of bytes read, which is less than `buf.len()` if the range is not fully accessible.
+    extern fn read_guest_memory(vm_id: VMId, gpa: GuestPhysAddr, buf: &mut [u8]) -> usize;
+    /// Write bytes from `buf` into the guest physical memory of a virtual machine.
+    ///
+    /// Returns the number of bytes written, which is less than `buf.len()` if the range is not fully accessible.
+    extern fn write_guest_memory(vm_id: VMId, gpa: GuestPhysAddr, buf: &[u8]) -> usize;
+    /// Translate a guest physical address of a virtual machine to the host physical address backing it, through the
+    /// stage-2 (nested) page table.
+    ///
+    /// Returns `None` if the guest physical address is not backed by memory, e.g. it is not mapped, or is an emulated
+    /// MMIO region.
+    extern fn gpa_to_hpa(vm_id: VMId, gpa: GuestPhysAddr) -> Option<crate::memory::HostPhysAddr>;
+    /// Translate a guest physical address of a virtual machine to the host virtual address through which the
+    /// hypervisor can access it.
+    ///
+    /// Note that only the page containing the address is guaranteed to be contiguous in the host address space.
+    pub fn gpa_to_hva(vm_id: VMId, gpa: GuestPhysAddr) -> Option<crate::memory::HostVirtAddr> {
+        crate::memory::phys_to_virt(gpa_to_hpa(vm_id, gpa)?)
+    }
+
+    /// Memory usage of a virtual machine, in bytes.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct MemUsage {
+        /// Size of the guest memory as configured.
+        pub configured: usize,
+        /// Size of the guest memory backed by host frames.
+        pub backed: usize,
+        /// Size of the guest memory reclaimed by the host, e.g. through a balloon device.
+        pub reclaimed: usize,
+    }
+
+    /// Reclaim the backing frames of guest pages of a virtual machine, returning them to the host frame allocator,
+    /// e.g. for the pages inflated into a balloon device.
+    ///
+    /// The pages are unmapped from the guest, and are backed by fresh zeroed frames on the next access, or by
+    /// [`restore_guest_pages`]. Fails with [`BadAddress`](crate::error::AxvisorError::BadAddress) if any of the
+    /// addresses is not a page-aligned address of guest memory, in which case no page is reclaimed.
+    extern fn reclaim_guest_pages(
+        vm_id: VMId,
+        gpa_list: &[GuestPhysAddr],
+    ) -> crate::error::AxvisorResult;
+    /// Back reclaimed guest pages of a virtual machine with frames again, e.g. for the pages deflated from a balloon
+    /// device.
+    ///
+    /// Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if frames cannot be allocated.
+    extern fn restore_guest_pages(
+        vm_id: VMId,
+        gpa_list: &[GuestPhysAddr],
+    ) -> crate::error::AxvisorResult;
+    /// Get the memory usage of a virtual machine.
+    extern fn vm_memory_usage(vm_id: VMId) -> Option<MemUsage>;
+
+    /// Share the backing frame of a guest page of virtual machine `vm_a` with a guest page of virtual machine `vm_b`
+    /// (possibly the same one), e.g. for pages with identical contents found by memory deduplication.
+    ///
+    /// The original backing frame of the page of `vm_b` is freed, and both pages are mapped read-only and become
+    /// copy-on-write. Fails with [`BadAddress`](crate::error::AxvisorError::BadAddress) if any of the addresses is not
+    /// a page-aligned address of guest memory.
+    extern fn share_page(
+        vm_a: VMId,
+        gpa_a: GuestPhysAddr,
+        vm_b: VMId,
+        gpa_b: GuestPhysAddr,
+    ) -> crate::error::AxvisorResult;
+    /// Make a range of the guest memory of a virtual machine copy-on-write, i.e. mapped read-only, and copied to a
+    /// new frame on the first write to each page, e.g. to snapshot or clone a virtual machine.
+    extern fn mark_cow(vm_id: VMId, gpa_range: GuestPhysAddrRange) -> crate::error::AxvisorResult;
+    /// Handler notified when a copy-on-write guest page is copied on a write, receiving the virtual machine and the
+    /// guest physical address of the page.
+    pub type CowBreakHandler = Box<dyn Fn(VMId, GuestPhysAddr) + Send + Sync + 'static>;
+    /// Set the handler notified when a copy-on-write guest page is copied, replacing the previous one.
+    extern fn set_cow_break_handler(handler: Option<CowBreakHandler>);
+
+    /// Eagerly allocate and map the backing frames of a range of the guest physical memory of a virtual machine,
+    /// preferably with huge pages, so that the guest does not take stage-2 page faults on first access.
+    ///
+    /// Ranges already populated are skipped. Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if frames
+    /// cannot be allocated, in which case part of the range may have been populated, or with
+    /// [`BadAddress`](crate::error::AxvisorError::BadAddress) if the range is not guest memory.
+    extern fn populate_guest_range(
+        vm_id: VMId,
+        gpa: GuestPhysAddr,
+        len: usize,
+    ) -> crate::error::AxvisorResult;
+
+    /// Map a range of host physical memory into the guest physical memory of a virtual machine, with mappings of the
+    /// given page size, e.g. 2M or 1G blocks for huge-frame-backed guest memory.
+    ///
+    /// `gpa`, `hpa` and `size` must be aligned to the page size. Fails with
+    /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if they are not, with
+    /// [`Unsupported`](crate::error::AxvisorError::Unsupported) if the stage-2 page table of the virtual machine does
+    /// not support the page size, with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if part of the
+    /// range is already mapped, or with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does
+    /// not exist.
+    extern fn map_guest_range(
+        vm_id: VMId,
+        gpa: GuestPhysAddr,
+        hpa: crate::memory::HostPhysAddr,
+        size: usize,
+        flags: crate::memory::MappingFlags,
+        page_size: crate::memory::PageSize,
+    ) -> crate::error::AxvisorResult;
+    /// Unmap a range of the guest physical memory of a virtual machine, flushing the stage-2 TLB entries of the range.
+    ///
+    /// Block mappings partially covered by the range are split. Fails with
+    /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if `gpa` or `size` is not page-aligned, or with
+    /// [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist.
+    extern fn unmap_guest_range(
+        vm_id: VMId,
+        gpa: GuestPhysAddr,
+        size: usize,
+    ) -> crate::error::AxvisorResult;
+
+    /// Byte order of a value stored in guest memory.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Endianness {
+        /// Little-endian.
+        Little,
+        /// Big-endian.
+        Big,
+    }
+
+    /// Primitive integer types that can be accessed in guest memory with an explicit byte order.
+    pub trait GuestPrimitive: Copy {
+        /// Raw byte representation of the type.
+        type Bytes: AsRef<[u8]> + AsMut<[u8]> + Default;
+
+        /// Create a value from its representation in the given byte order.
+        fn from_bytes(bytes: Self::Bytes, endianness: Endianness) -> Self;
+        /// Return the representation of the value in the given byte order.
+        fn to_bytes(self, endianness: Endianness) -> Self::Bytes;
+    }
+
+    macro_rules! impl_guest_primitive {
+        ($($ty:ty),*) => {
+            $(
+                impl GuestPrimitive for $ty {
+                    type Bytes = [u8; core::mem::size_of::<$ty>()];
+
+                    fn from_bytes(bytes: Self::Bytes, endianness: Endianness) -> Self {
+                        match endianness {
+                            Endianness::Little => <$ty>::from_le_bytes(bytes),
+                            Endianness::Big => <$ty>::from_be_bytes(bytes),
+                        }
+                    }
+
+                    fn to_bytes(self, endianness: Endianness) -> Self::Bytes {
+                        match endianness {
+                            Endianness::Little => self.to_le_bytes(),
+                            Endianness::Big => self.to_be_bytes(),
+                        }
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_guest_primitive!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+    /// Read a value stored in the given byte order from guest memory.
+    ///
+    /// Returns `None` if the value is not fully accessible.
+    pub fn guest_read<T: GuestPrimitive>(
+        vm_id: VMId,
+        gpa: GuestPhysAddr,
+        endianness: Endianness,
+    ) -> Option<T> {
+        let mut bytes = T::Bytes::default();
+        let len = bytes.as_ref().len();
+        (read_guest_memory(vm_id, gpa, bytes.as_mut()) == len)
+            .then(|| T::from_bytes(bytes, endianness))
+    }
+    /// Write a value to guest memory in the given byte order.
+    ///
+    /// Returns `false` if the value is not fully written.
+    pub fn guest_write<T: GuestPrimitive>(
+        vm_id: VMId,
+        gpa: GuestPhysAddr,
+        value: T,
+        endianness: Endianness,
+    ) -> bool {
+        let bytes = value.to_bytes(endianness);
+        write_guest_memory(vm_id, gpa, bytes.as_ref()) == bytes.as_ref().len()
+    }
+
+    /// Read a little-endian value from guest memory.
+    pub fn guest_read_le<T: GuestPrimitive>(vm_id: VMId, gpa: GuestPhysAddr) -> Option<T> {
+        guest_read(vm_id, gpa, Endianness::Little)
+    }
+    /// Read a big-endian value from guest memory.
+    pub fn guest_read_be<T: GuestPrimitive>(vm_id: VMId, gpa: GuestPhysAddr) -> Option<T> {
+        guest_read(vm_id, gpa, Endianness::Big)
+    }
+    /// Write a value to guest memory in little-endian byte order.
+    pub fn guest_write_le<T: GuestPrimitive>(vm_id: VMId, gpa: GuestPhysAddr, value: T) -> bool {
+        guest_write(vm_id, gpa, value, Endianness::Little)
+    }
+    /// Write a value to guest memory in big-endian byte order.
+    pub fn guest_write_be<T: GuestPrimitive>(vm_id: VMId, gpa: GuestPhysAddr, value: T) -> bool {
+        guest_write(vm_id, gpa, value, Endianness::Big)
+    }
+
+    /// Read a packed structure from guest memory, byte by byte, without any byte-order conversion.
+    ///
+    /// Returns `None` if the structure is not fully accessible.
+    ///
+    /// # Safety
+    ///
+    /// `T` must be valid for any bit pattern, e.g. a `#[repr(C, packed)]` structure of integers.
+    pub unsafe fn guest_read_struct<T: Copy>(vm_id: VMId, gpa: GuestPhysAddr) -> Option<T> {
+        // Zeroed rather than uninitialized, so that the bytes are initialized when viewed as a slice.
+        let mut value = core::mem::MaybeUninit::<T>::zeroed();
+        // SAFETY: the pointer is valid for writes of `size_of::<T>()` initialized bytes, which are not otherwise
+        // accessed while the slice is alive.
+        let buf = unsafe {
+            core::slice::from_raw_parts_mut(
+                value.as_mut_ptr() as *mut u8,
+                core::mem::size_of::<T>(),
+            )
+        };
+        if read_guest_memory(vm_id, gpa, buf) != buf.len() {
+            return None;
+        }
+        // SAFETY: all bytes are initialized, and `T` is valid for any bit pattern as required of the caller.
+        Some(unsafe { value.assume_init() })
+    }
+    /// Write a packed structure to guest memory, byte by byte, without any byte-order conversion.
+    ///
+    /// Returns `false` if the structure is not fully written.
+    ///
+    /// # Safety
+    ///
+    /// `T` must not contain any padding bytes, e.g. a `#[repr(C, packed)]` structure of integers.
+    pub unsafe fn guest_write_struct<T: Copy>(vm_id: VMId, gpa: GuestPhysAddr, value: &T) -> bool {
+        // SAFETY: `value` is valid for reads of `size_of::<T>()` bytes, which are all initialized as `T` has no padding
+        // bytes as required of the caller.
+        let buf = unsafe {
+            core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+        };
+        write_guest_memory(vm_id, gpa, buf) == buf.len()
+    }
+}
+
+#[api_mod]
+/// Shared-filesystem-related API, used by backends of shared-filesystem devices like virtio-fs or 9p.
+///
+/// The hypervisor exports namespaces (e.g. a host directory or an in-memory store) to virtual machines, each of which
+/// is identified by a tag, like the mount tag of virtio-fs or 9p.
+pub mod fsshare {
+    use super::vmm::VMId;
+
+    /// Shared filesystem namespace ID.
+    pub type ShareId = usize;
+    /// Node (file, directory, etc.) ID in a shared filesystem namespace.
+    pub type NodeId = u64;
+    /// Handle of an opened file.
+    pub type FileHandle = u64;
+
+    /// Kind of a node.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum NodeKind {
+        /// Regular file.
+        File,
+        /// Directory.
+        Directory,
+        /// Symbolic link.
+        Symlink,
+    }
+
+    /// Attributes of a node.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct NodeAttr {
+        /// ID of the node.
+        pub node: NodeId,
+        /// Kind of the node.
+        pub kind: NodeKind,
+        /// Size of the node in bytes.
+        pub size: u64,
+        /// Permission bits of the node.
+        pub mode: u32,
+    }
+
+    /// Options to open a file.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct OpenOptions {
+        /// Open for reading.
+        pub read: bool,
+        /// Open for writing.
+        pub write: bool,
+        /// Truncate the file to zero length.
+        pub truncate: bool,
+        /// Append to the end of the file on every write.
+        pub append: bool,
+    }
+
+    /// An entry in a directory.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct DirEntry {
+        /// ID of the node.
+        pub node: NodeId,
+        /// Kind of the node.
+        pub kind: NodeKind,
+        /// Length of the name of the entry.
+        pub name_len: usize,
+    }
+
+    /// Get the shared filesystem namespace exported to a virtual machine with the given tag.
+    extern fn open_share(vm_id: VMId, tag: &str) -> crate::error::AxvisorResult<ShareId>;
+    /// Get the root directory of a shared filesystem namespace.
+    extern fn root_node(share: ShareId) -> NodeId;
+    /// Look up an entry by name in a directory.
+    extern fn lookup(
+        share: ShareId,
+        parent: NodeId,
+        name: &str,
+    ) -> crate::error::AxvisorResult<NodeAttr>;
+    /// Get the attributes of a node.
+    extern fn get_attr(share: ShareId, node: NodeId) -> crate::error::AxvisorResult<NodeAttr>;
+    /// Create a node in a directory.
+    extern fn create(
+        share: ShareId,
+        parent: NodeId,
+        name: &str,
+        kind: NodeKind,
+        mode: u32,
+    ) -> crate::error::AxvisorResult<NodeAttr>;
+    /// Remove an entry by name from a directory.
+    extern fn remove(share: ShareId, parent: NodeId, name: &str) -> crate::error::AxvisorResult;
+    /// Read the `index`-th entry of a directory, with its name written into `name_buf`.
+    ///
+    /// Returns `Ok(None)` if there are no more entries. The name is truncated if `name_buf` is too small, which can be
+    /// detected by comparing [`DirEntry::name_len`] with the length of `name_buf`.
+    extern fn read_dir(
+        share: ShareId,
+        dir: NodeId,
+        index: usize,
+        name_buf: &mut [u8],
+    ) -> crate::error::AxvisorResult<Option<DirEntry>>;
+
+    /// Open a file.
+    extern fn open(
+        share: ShareId,
+        node: NodeId,
+        options: OpenOptions,
+    ) -> crate::error::AxvisorResult<FileHandle>;
+    /// Read from an opened file at `offset`.
+    ///
+    /// Returns the number of bytes read, `0` means the end of the file.
+    extern fn read(
+        share: ShareId,
+        handle: FileHandle,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> crate::error::AxvisorResult<usize>;
+    /// Write to an opened file at `offset`.
+    ///
+    /// Returns the number of bytes written.
+    extern fn write(
+        share: ShareId,
+        handle: FileHandle,
+        offset: u64,
+        buf: &[u8],
+    ) -> crate::error::AxvisorResult<usize>;
+    /// Flush the written data of an opened file to the underlying store.
+    extern fn flush(share: ShareId, handle: FileHandle) -> crate::error::AxvisorResult;
+    /// Close an opened file.
+    extern fn release(share: ShareId, handle: FileHandle);
+
+    /// Look up a node by a `/`-separated path relative to the root directory of a shared filesystem namespace.
+    pub fn lookup_path(share: ShareId, path: &str) -> crate::error::AxvisorResult<NodeAttr> {
+        let mut attr = get_attr(share, root_node(share))?;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            attr = lookup(share, attr.node, name)?;
+        }
+        Ok(attr)
+    }
+}
+
+#[api_mod]
+/// Block storage API, providing the storage backing the block devices of virtual machines, e.g. host disks or
+/// ramdisks.
+pub mod block {
+    /// ID of a volume, as configured for the virtual machines.
+    pub type VolumeId = usize;
+    /// Handle of an opened volume.
+    pub type BlockHandle = u64;
+    /// Logical block address, i.e. the index of a block in a volume.
+    pub type Lba = u64;
+
+    /// Open a volume.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the volume does not exist.
+    extern fn open(volume_id: VolumeId) -> crate::error::AxvisorResult<BlockHandle>;
+    /// Close an opened volume.
+    extern fn close(handle: BlockHandle);
+    /// Get the size of a block of an opened volume in bytes, e.g. 512.
+    extern fn block_size(handle: BlockHandle) -> usize;
+    /// Get the capacity of an opened volume in blocks.
+    extern fn capacity(handle: BlockHandle) -> u64;
+    /// Check whether an opened volume is read-only.
+    extern fn is_read_only(handle: BlockHandle) -> bool;
+    /// Read blocks from an opened volume, starting at `lba`, into `buf`, whose length is a multiple of the block size.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the length of `buf` is not a multiple of
+    /// the block size or the blocks are out of the capacity, or with [`Io`](crate::error::AxvisorError::Io) if the
+    /// underlying device fails.
+    extern fn read_blocks(
+        handle: BlockHandle,
+        lba: Lba,
+        buf: &mut [u8],
+    ) -> crate::error::AxvisorResult;
+    /// Write blocks to an opened volume, starting at `lba`, from `buf`, whose length is a multiple of the block size.
+    ///
+    /// The written data may be cached until [`flush`] is called. Fails like [`read_blocks`], or with
+    /// [`PermissionDenied`](crate::error::AxvisorError::PermissionDenied) if the volume is read-only.
+    extern fn write_blocks(
+        handle: BlockHandle,
+        lba: Lba,
+        buf: &[u8],
+    ) -> crate::error::AxvisorResult;
+    /// Flush the written data of an opened volume to the underlying device.
+    extern fn flush(handle: BlockHandle) -> crate::error::AxvisorResult;
+}
+
+#[api_mod]
+/// Task-related API, through which components run code in host execution contexts, e.g. I/O threads of virtio
+/// backends.
+pub mod task {
+    extern crate alloc;
+    use alloc::boxed::Box;
+
+    pub use super::time::sleep;
+
+    /// Task ID.
+    pub type TaskId = u64;
+
+    /// Spawn a task running `entry`, named for debugging.
+    ///
+    /// The task exits when `entry` returns. Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if the task
+    /// cannot be created.
+    extern fn spawn(
+        name: &'static str,
+        entry: Box<dyn FnOnce() + Send + 'static>,
+    ) -> crate::error::AxvisorResult<TaskId>;
+    /// Get the ID of the current task.
+    extern fn current_task_id() -> TaskId;
+    /// Yield the current CPU to other tasks.
+    extern fn yield_now();
+    /// Exit the current task.
+    extern fn exit() -> !;
+}
+
+#[api_mod]
+/// Synchronization-related API, providing blocking primitives backed by the scheduler of the hypervisor, for
+/// components which need to sleep while waiting, e.g. for I/O completion or inter-VM events.
+pub mod sync {
+    /// Semaphore ID.
+    pub type SemaphoreId = usize;
+    /// Wait queue ID.
+    pub type WaitQueueId = usize;
+
+    /// Create a semaphore with `initial` permits.
+    extern fn create_semaphore(initial: usize) -> crate::error::AxvisorResult<SemaphoreId>;
+    /// Destroy a semaphore. Tasks waiting on it are woken up.
+    extern fn destroy_semaphore(sem: SemaphoreId);
+    /// Take a permit of a semaphore, blocking until one is available.
+    extern fn sem_wait(sem: SemaphoreId);
+    /// Take a permit of a semaphore without blocking, returning `false` if none is available.
+    extern fn sem_try_wait(sem: SemaphoreId) -> bool;
+    /// Release a permit to a semaphore, waking up a waiting task if any.
+    extern fn sem_post(sem: SemaphoreId);
+
+    /// Create a wait queue.
+    extern fn create_wait_queue() -> crate::error::AxvisorResult<WaitQueueId>;
+    /// Destroy a wait queue. Tasks waiting on it are woken up.
+    extern fn destroy_wait_queue(wq: WaitQueueId);
+    /// Block on a wait queue until `condition` returns `true`, which is checked before blocking and every time the
+    /// task is woken up.
+    extern fn wait_queue_wait_until(wq: WaitQueueId, condition: &dyn Fn() -> bool);
+    /// Wake up a task waiting on a wait queue, returning `false` if there is none.
+    extern fn wait_queue_notify_one(wq: WaitQueueId) -> bool;
+    /// Wake up all tasks waiting on a wait queue.
+    extern fn wait_queue_notify_all(wq: WaitQueueId);
+
+    /// A semaphore, destroyed when dropped.
+    #[derive(Debug)]
+    pub struct Semaphore(SemaphoreId);
+
+    impl Semaphore {
+        /// Create a semaphore with `initial` permits.
+        pub fn new(initial: usize) -> crate::error::AxvisorResult<Self> {
+            create_semaphore(initial).map(Self)
+        }
+
+        /// Take a permit, blocking until one is available.
+        pub fn wait(&self) {
+            sem_wait(self.0)
+        }
+
+        /// Take a permit without blocking, returning `false` if none is available.
+        pub fn try_wait(&self) -> bool {
+            sem_try_wait(self.0)
+        }
+
+        /// Release a permit.
+        pub fn post(&self) {
+            sem_post(self.0)
+        }
+    }
+
+    impl Drop for Semaphore {
+        fn drop(&mut self) {
+            destroy_semaphore(self.0)
+        }
+    }
+
+    /// A wait queue, destroyed when dropped.
+    #[derive(Debug)]
+    pub struct WaitQueue(WaitQueueId);
+
+    impl WaitQueue {
+        /// Create a wait queue.
+        pub fn new() -> crate::error::AxvisorResult<Self> {
+            create_wait_queue().map(Self)
+        }
+
+        /// Block until `condition` returns `true`, which is checked before blocking and on every notification.
+        pub fn wait_until(&self, condition: impl Fn() -> bool) {
+            wait_queue_wait_until(self.0, &condition)
+        }
+
+        /// Wake up a waiting task, returning `false` if there is none.
+        pub fn notify_one(&self) -> bool {
+            wait_queue_notify_one(self.0)
+        }
+
+        /// Wake up all waiting tasks.
+        pub fn notify_all(&self) {
+            wait_queue_notify_all(self.0)
+        }
+    }
+
+    impl Drop for WaitQueue {
+        fn drop(&mut self) {
+            destroy_wait_queue(self.0)
+        }
+    }
+}
+
+#[api_mod]
+/// Hypercall-related API, through which components like PSCI or debug-console emulation handle hypercalls.
+pub mod hypercall {
+    extern crate alloc;
+    use super::vmm::{VCpuId, VMId};
+    use alloc::boxed::Box;
+
+    /// Hypercall number, e.g. the function ID of SMCCC on aarch64, or the value of `rax` of `vmcall` on x86_64.
+    pub type HypercallNr = u64;
+
+    /// A hypercall issued by a guest.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Hypercall {
+        /// Hypercall number.
+        pub nr: HypercallNr,
+        /// Values of the argument registers.
+        pub args: [u64; 6],
+    }
+
+    /// Values returned to the guest in the return registers, e.g. `x0` to `x3` on aarch64.
+    pub type HypercallRet = [u64; 4];
+
+    /// Handler of hypercalls, receiving the calling virtual machine and virtual CPU.
+    pub type HypercallHandler =
+        Box<dyn Fn(VMId, VCpuId, &Hypercall) -> HypercallRet + Send + Sync + 'static>;
+
+    /// Register a handler for a hypercall number.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if the number is already handled.
+    extern fn register_hypercall(
+        nr: HypercallNr,
+        handler: HypercallHandler,
+    ) -> crate::error::AxvisorResult;
+    /// Register a handler for `count` hypercall numbers starting from `first`, e.g. the function IDs of a SMCCC
+    /// service.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if any of the numbers is already
+    /// handled, or with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if `count` is zero.
+    extern fn register_hypercall_range(
+        first: HypercallNr,
+        count: u64,
+        handler: HypercallHandler,
+    ) -> crate::error::AxvisorResult;
+    /// Unregister the handler for a hypercall number, along with all the numbers registered with it.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the number is not handled.
+    extern fn unregister_hypercall(nr: HypercallNr) -> crate::error::AxvisorResult;
+}
+
+#[api_mod]
+/// Event-bus API, through which components notify each other of events without depending on each other directly.
+pub mod event {
+    extern crate alloc;
+    use alloc::boxed::Box;
+
+    /// Topic of events.
+    ///
+    /// Topics below [`EventTopic::CUSTOM_BASE`] are reserved for the well-known topics defined here, whose payloads are
+    /// documented with them. Other components define their own topics starting from [`EventTopic::CUSTOM_BASE`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct EventTopic(pub u32);
+
+    impl EventTopic {
+        /// A virtual machine is started, with the [`VMId`](crate::vmm::VMId) as payload in native endianness.
+        pub const VM_STARTED: Self = Self(1);
+        /// A virtual machine is shut down, with the [`VMId`](crate::vmm::VMId) as payload in native endianness.
+        pub const VM_SHUTDOWN: Self = Self(2);
+        /// A virtual machine is reset, with the [`VMId`](crate::vmm::VMId) as payload in native endianness.
+        pub const VM_RESET: Self = Self(3);
+        /// An emulated device is reset, with the [`VMId`](crate::vmm::VMId) and the
+        /// [`DeviceId`](crate::vmm::DeviceId) as payload in native endianness.
+        pub const DEVICE_RESET: Self = Self(4);
+        /// The first topic available to components.
+        pub const CUSTOM_BASE: Self = Self(0x1000);
+    }
+
+    /// ID of a subscription.
+    pub type SubscriptionId = usize;
+
+    /// Callback of a subscription, receiving the topic and the payload of events.
+    pub type EventCallback = Box<dyn Fn(EventTopic, &[u8]) + Send + Sync + 'static>;
+
+    /// Publish an event to all subscribers of the topic.
+    ///
+    /// The callbacks of the subscribers are called synchronously in the context of the caller, in the order of
+    /// subscription. Callbacks must not subscribe or unsubscribe.
+    extern fn publish(topic: EventTopic, payload: &[u8]);
+    /// Subscribe to a topic.
+    extern fn subscribe(topic: EventTopic, callback: EventCallback) -> SubscriptionId;
+    /// Cancel a subscription.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the subscription does not exist.
+    extern fn unsubscribe(id: SubscriptionId) -> crate::error::AxvisorResult;
+}
+
+#[api_mod]
+/// Metrics-related API, through which components report counters, gauges and histograms, either global or attributed
+/// to virtual machines and devices, to be collected by the hypervisor, e.g. for its shell or a management agent.
+pub mod metrics {
+    use super::vmm::{DeviceId, VMId};
+
+    /// ID of a counter.
+    pub type CounterId = u64;
+    /// ID of a gauge.
+    pub type GaugeId = u64;
+    /// ID of a histogram.
+    pub type HistogramId = u64;
+
+    /// Handle of a counter, e.g. of queue kicks, descriptors processed, or errors.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Counter(CounterId);
+
+    impl Counter {
+        /// Create a handle from the ID of a counter.
+        pub const fn from_id(id: CounterId) -> Self {
+            Self(id)
+        }
+
+        /// Get the ID of the counter.
+        pub const fn id(self) -> CounterId {
+            self.0
+        }
+
+        /// Increase the counter by one.
+        pub fn inc(self) {
+            counter_add(self.0, 1)
+        }
+
+        /// Increase the counter by `delta`.
+        pub fn add(self, delta: u64) {
+            counter_add(self.0, delta)
+        }
+
+        /// Get the current value of the counter.
+        pub fn get(self) -> u64 {
+            counter_value(self.0)
+        }
+    }
+
+    /// Handle of a gauge, i.e. a value which goes up and down, e.g. the number of requests in flight.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Gauge(GaugeId);
+
+    impl Gauge {
+        /// Create a handle from the ID of a gauge.
+        pub const fn from_id(id: GaugeId) -> Self {
+            Self(id)
+        }
+
+        /// Get the ID of the gauge.
+        pub const fn id(self) -> GaugeId {
+            self.0
+        }
+
+        /// Set the value of the gauge.
+        pub fn set(self, value: i64) {
+            gauge_set(self.0, value)
+        }
+    }
+
+    /// Handle of a histogram, e.g. of request latencies.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Histogram(HistogramId);
+
+    impl Histogram {
+        /// Create a handle from the ID of a histogram.
+        pub const fn from_id(id: HistogramId) -> Self {
+            Self(id)
+        }
+
+        /// Get the ID of the histogram.
+        pub const fn id(self) -> HistogramId {
+            self.0
+        }
+
+        /// Record a value in the histogram.
+        pub fn record(self, value: u64) {
+            histogram_record(self.0, value)
+        }
+    }
+
+    /// Value of a metric in a snapshot.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum MetricValue<'a> {
+        /// Value of a counter.
+        Counter(u64),
+        /// Value of a gauge.
+        Gauge(i64),
+        /// Value of a histogram.
+        Histogram {
+            /// Upper bounds (inclusive) of the buckets.
+            bounds: &'a [u64],
+            /// Number of values in each bucket, with an extra one at the end for values above the last bound.
+            counts: &'a [u64],
+            /// Sum of all values recorded.
+            sum: u64,
+        },
+    }
+
+    /// A metric in a snapshot.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MetricSample<'a> {
+        /// Name of the metric.
+        pub name: &'a str,
+        /// Virtual machine and device which the metric is attributed to, `None` for global metrics.
+        pub device: Option<(VMId, DeviceId)>,
+        /// Value of the metric.
+        pub value: MetricValue<'a>,
+    }
+
+    /// Register a global counter with value zero.
+    extern fn register_counter(name: &'static str) -> Counter;
+    /// Get the handle of the counter named `name` of an emulated device, creating the counter with value zero if it
+    /// does not exist.
+    ///
+    /// Counters are removed along with the device. Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if
+    /// the device does not exist.
+    extern fn device_counter(
+        vm_id: VMId,
+        dev_id: DeviceId,
+        name: &'static str,
+    ) -> crate::error::AxvisorResult<Counter>;
+    /// Increase a counter by `delta`, wrapping around on overflow.
+    extern fn counter_add(id: CounterId, delta: u64);
+    /// Get the current value of a counter.
+    extern fn counter_value(id: CounterId) -> u64;
+    /// Call `f` with the device, name and value of every counter of the devices in a virtual machine.
+    extern fn for_each_device_counter(vm_id: VMId, f: &mut dyn FnMut(DeviceId, &str, u64));
+
+    /// Register a global gauge with value zero.
+    extern fn register_gauge(name: &'static str) -> Gauge;
+    /// Set the value of a gauge.
+    extern fn gauge_set(id: GaugeId, value: i64);
+
+    /// Register a global histogram with buckets of the given upper bounds (inclusive), which must be sorted.
+    extern fn register_histogram(name: &'static str, bounds: &'static [u64]) -> Histogram;
+    /// Record a value in a histogram.
+    extern fn histogram_record(id: HistogramId, value: u64);
+
+    /// Call `f` with every metric registered, including the ones of devices.
+    extern fn snapshot(f: &mut dyn FnMut(&MetricSample));
+}
+
+#[api_mod]
+/// IOMMU-related API.
+///
+/// Each IOMMU domain, i.e. an I/O virtual address space shared by a group of devices, has a single IOVA allocator
+/// managed by the hypervisor, which is shared by all components mapping DMA buffers into the domain, like passthrough
+/// device drivers and virtual IOMMUs.
+pub mod iommu {
+    /// IOMMU domain ID.
+    pub type DomainId = usize;
+    /// I/O virtual address.
+    pub type Iova = u64;
+
+    /// Constraints on an IOVA range to be allocated with [`alloc_iova`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct IovaConstraints {
+        /// Maximum DMA address width of the devices accessing the range in bits, e.g. `32` for devices only capable of
+        /// 32-bit DMA, `None` for no limit other than the one of the domain.
+        pub addr_width: Option<u8>,
+        /// Allocate within `start..end` only, `None` for anywhere in the domain.
+        pub window: Option<(Iova, Iova)>,
+    }
+
+    impl IovaConstraints {
+        /// Create constraints limiting the DMA address width to `bits`.
+        pub const fn with_addr_width(bits: u8) -> Self {
+            Self {
+                addr_width: Some(bits),
+                window: None,
+            }
+        }
+    }
+
+    /// Usage statistics of the IOVA space of a domain.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct IovaStats {
+        /// Size of the allocatable IOVA space in bytes.
+        pub total: u64,
+        /// Size of the allocated IOVA ranges in bytes.
+        pub allocated: u64,
+        /// Number of allocations failed due to exhaustion of the IOVA space.
+        pub failures: u64,
+    }
+
+    /// Allocate an IOVA range of `size` bytes aligned to `align` bytes in a domain.
+    ///
+    /// Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if no range satisfying the constraints is
+    /// available, with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if `size` is zero or `align` is not a
+    /// power of two, or with [`NotFound`](crate::error::AxvisorError::NotFound) if the domain does not exist.
+    extern fn alloc_iova(
+        domain: DomainId,
+        size: u64,
+        align: u64,
+        constraints: IovaConstraints,
+    ) -> crate::error::AxvisorResult<Iova>;
+    /// Free an IOVA range allocated with [`alloc_iova`].
+    extern fn free_iova(domain: DomainId, iova: Iova, size: u64);
+    /// Get the usage statistics of the IOVA space of a domain.
+    extern fn iova_stats(domain: DomainId) -> crate::error::AxvisorResult<IovaStats>;
+}
+
+#[api_mod]
+/// Filter-program-related API.
+///
+/// Filter programs are small programs in a bounded bytecode loaded at runtime, which decide whether to allow events
+/// like packets or hypercalls, so that such policies can be changed without rebuilding the hypervisor. The bytecode
+/// format is defined by the implementation, which verifies every program on loading so that its execution always
+/// terminates and only accesses the context passed to it.
+pub mod filter {
+    use super::vmm::VMId;
+
+    /// Token of a loaded filter program.
+    pub type ProgToken = u64;
+
+    /// Kind of a filter program, which determines the context passed to it and where it can be attached.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ProgramKind {
+        /// A packet filter, whose context is a packet.
+        Packet,
+        /// A hypercall filter, whose context is the hypercall number and arguments.
+        Hypercall,
+        /// A tracing predicate, whose context is the record of a trace event.
+        Trace,
+    }
+
+    /// A point where filter programs can be attached.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AttachPoint {
+        /// Packets sent or received by the virtual network devices of a virtual machine.
+        Packet(VMId),
+        /// Hypercalls issued by a virtual machine.
+        Hypercall(VMId),
+        /// Events of a trace point.
+        Trace(u32),
+    }
+
+    impl AttachPoint {
+        /// Get the kind of filter programs which can be attached to this point.
+        pub const fn program_kind(self) -> ProgramKind {
+            match self {
+                Self::Packet(_) => ProgramKind::Packet,
+                Self::Hypercall(_) => ProgramKind::Hypercall,
+                Self::Trace(_) => ProgramKind::Trace,
+            }
+        }
+    }
+
+    /// Decision of filter programs on an event.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum FilterAction {
+        /// The event is allowed.
+        Allow,
+        /// The event is denied, e.g. the packet is dropped, or the hypercall fails.
+        Deny,
+    }
+
+    /// Verify and load a filter program.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the program fails the verification.
+    extern fn load_program(
+        kind: ProgramKind,
+        bytecode: &[u8],
+    ) -> crate::error::AxvisorResult<ProgToken>;
+    /// Unload a filter program, detaching it from all points.
+    extern fn unload_program(prog: ProgToken) -> crate::error::AxvisorResult;
+    /// Attach a filter program to a point.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the kind of the program does not match
+    /// [`AttachPoint::program_kind`].
+    extern fn attach_program(prog: ProgToken, point: AttachPoint) -> crate::error::AxvisorResult;
+    /// Detach a filter program from a point.
+    extern fn detach_program(prog: ProgToken, point: AttachPoint) -> crate::error::AxvisorResult;
+    /// Run the filter programs attached to a point on an event, used by the components owning the point.
+    ///
+    /// The event is denied if any of the programs denies it, and allowed if no program is attached.
+    extern fn run_filters(point: AttachPoint, ctx: &[u8]) -> FilterAction;
+}
+
+#[api_mod]
+/// Power-and-update-related API.
+///
+/// The hypervisor can be updated by loading a new image and handing the running virtual machines over to it, in a way
+/// similar to kexec. Components with state to be kept across the update register hooks to serialize their state, which
+/// is retrieved by their counterparts in the new image.
+pub mod power {
+    extern crate alloc;
+    use alloc::{boxed::Box, vec::Vec};
+
+    /// A new hypervisor image to hand off to.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct HandoffImage {
+        /// Host physical address where the image is placed.
+        pub addr: crate::memory::HostPhysAddr,
+        /// Size of the image in bytes.
+        pub size: usize,
+        /// Keep the virtual machines running across the update, instead of only preserving their memory.
+        pub keep_vms_running: bool,
+    }
+
+    /// Hook serializing the state of a component into the buffer before the hand-off.
+    pub type HandoffHook =
+        Box<dyn Fn(&mut Vec<u8>) -> crate::error::AxvisorResult + Send + Sync + 'static>;
+
+    /// Register a hook to serialize the state of a component before the hand-off, named after the component.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if the component already has a hook.
+    extern fn register_handoff_hook(
+        component: &'static str,
+        hook: HandoffHook,
+    ) -> crate::error::AxvisorResult;
+    /// Unregister the hand-off hook of a component.
+    extern fn unregister_handoff_hook(component: &'static str) -> crate::error::AxvisorResult;
+    /// Prepare to hand off to a new hypervisor image.
+    ///
+    /// The virtual machines are paused, the hooks are called, and the image is verified and loaded, with the memory of
+    /// the virtual machines and the serialized states preserved. Nothing is changed if this function fails, e.g. with
+    /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the image fails the verification, or with the error
+    /// returned by a hook. Otherwise, the hypervisor jumps to the new image when the hand-off is committed, which is
+    /// not covered by this API yet.
+    extern fn prepare_handoff(image: HandoffImage) -> crate::error::AxvisorResult;
+    /// Get the state of a component serialized by the previous hypervisor image, used after a hand-off.
+    ///
+    /// Returns `None` if the hypervisor is not started by a hand-off, or the component had no hook.
+    extern fn handoff_state(component: &str) -> Option<Vec<u8>>;
+}
+
+#[api_mod]
+/// Configuration-related API.
+pub mod config {
+    extern crate alloc;
+    use super::vmm::VMId;
+    use alloc::{string::String, vec::Vec};
+    use core::str::FromStr;
+
+    /// Options of a device specified by the user, parsed from a string like
+    /// `virtio-net,mac=52:54:00:12:34:56,queues=4`.
+    ///
+    /// The first comma-separated element is the kind of the device, and the following ones are options, each of which
+    /// is either `key=value` or a bare `key` as a flag. Empty elements are ignored.
+    #[derive(Debug, Clone, PartialEq, Eq, Default)]
+    pub struct ArgMap {
+        kind: String,
+        args: Vec<(String, Option<String>)>,
+    }
+
+    impl ArgMap {
+        /// Parse options from a string.
+        pub fn parse(s: &str) -> Self {
+            let mut elements = s
+                .split(',')
+                .map(str::trim)
+                .filter(|element| !element.is_empty());
+            let kind = elements.next().unwrap_or_default().into();
+            let args = elements
+                .map(|element| match element.split_once('=') {
+                    Some((key, value)) => (key.trim().into(), Some(value.trim().into())),
+                    None => (element.into(), None),
+                })
+                .collect();
+            Self { kind, args }
+        }
+
+        /// Get the kind of the device, i.e. the first element.
+        pub fn kind(&self) -> &str {
+            &self.kind
+        }
+
+        /// Get the value of an option, or `None` if the option is not given or is a flag.
+        ///
+        /// If an option is given more than once, the last one wins.
+        pub fn get(&self, key: &str) -> Option<&str> {
+            self.args
+                .iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .and_then(|(_, value)| value.as_deref())
+        }
+
+        /// Get the value of an option parsed as `T`.
+        ///
+        /// Returns `Ok(None)` if the option is not given, or fails with
+        /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the value cannot be parsed.
+        pub fn get_as<T: FromStr>(&self, key: &str) -> crate::error::AxvisorResult<Option<T>> {
+            self.get(key)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| crate::error::AxvisorError::InvalidArg)
+                })
+                .transpose()
+        }
+
+        /// Check whether a flag is set, i.e. given as a bare `key`, or as `key=on`, `key=true`, `key=yes` or `key=1`.
+        pub fn flag(&self, key: &str) -> bool {
+            match self.args.iter().rev().find(|(k, _)| k == key) {
+                Some((_, None)) => true,
+                Some((_, Some(value))) => matches!(value.as_str(), "on" | "true" | "yes" | "1"),
+                None => false,
+            }
+        }
+
+        /// Iterate over the options in the order given, with `None` values for flags.
+        pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+            self.args
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_deref()))
+        }
+    }
+
+    /// Get the option string of a device of a virtual machine specified by the user, e.g.
+    /// `virtio-net,mac=52:54:00:12:34:56,queues=4`.
+    extern fn device_args_str(vm_id: VMId, device_name: &str) -> Option<String>;
+    /// Get the parsed options of a device of a virtual machine specified by the user.
+    pub fn device_args(vm_id: VMId, device_name: &str) -> Option<ArgMap> {
+        device_args_str(vm_id, device_name).map(|s| ArgMap::parse(&s))
+    }
+}
+
+#[api_mod]
+/// Console-related API, used by components like emulated UARTs and virtio-console to access the host console.
+pub mod console {
+    extern crate alloc;
+    use alloc::boxed::Box;
+    use core::fmt;
+
+    /// Handler of input bytes from the host console.
+    pub type InputHandler = Box<dyn Fn(u8) + Send + Sync + 'static>;
+
+    /// Write bytes to the host console.
+    extern fn put_bytes(bytes: &[u8]);
+    /// Read a byte from the host console without blocking, returning `None` if there is no input.
+    ///
+    /// Always returns `None` while an input handler is registered.
+    extern fn get_byte() -> Option<u8>;
+    /// Register a handler called with every byte of input from the host console, instead of polling with
+    /// [`get_byte`].
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if there is already a handler.
+    extern fn register_input_handler(handler: InputHandler) -> crate::error::AxvisorResult;
+    /// Unregister the input handler.
+    extern fn unregister_input_handler() -> crate::error::AxvisorResult;
+
+    /// An adapter of the host console implementing [`fmt::Write`], so that it can be used with [`write!`].
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Console;
+
+    impl fmt::Write for Console {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            put_bytes(s.as_bytes());
+            Ok(())
+        }
+    }
+}
+
+#[api_mod]
+/// Randomness-related API.
+pub mod rand {
+    use super::{guest_memory::GuestPhysAddr, vmm::VMId};
+
+    /// Fill `buf` with random bytes from the host entropy source, e.g. a hardware RNG or a CSPRNG seeded by one.
+    ///
+    /// This is suitable for seeding guests (e.g. virtio-rng) and for randomizing layouts in components.
+    extern fn fill_random(buf: &mut [u8]);
+    /// Get a random `u64` from the host entropy source.
+    pub fn random_u64() -> u64 {
+        let mut buf = [0; 8];
+        fill_random(&mut buf);
+        u64::from_ne_bytes(buf)
+    }
+
+    /// Write a fresh random seed of `len` bytes into the guest memory of a virtual machine at `gpa` before it boots,
+    /// and describe it in the firmware tables generated for the guest, e.g. `/chosen/rng-seed` and
+    /// `/chosen/kaslr-seed` in the device tree.
+    ///
+    /// The seed is drawn from the host entropy source every time, so that clones of a virtual machine never share it.
+    /// Fails with [`BadAddress`](crate::error::AxvisorError::BadAddress) if the range is not guest memory.
+    extern fn provision_guest_seed(
+        vm_id: VMId,
+        gpa: GuestPhysAddr,
+        len: usize,
+    ) -> crate::error::AxvisorResult;
+}
+
+#[api_mod]
+/// Guest device tree construction API.
+///
+/// Components, e.g. the ones emulating virtual devices, contribute nodes to the device trees constructed for the
+/// virtual machines, which are finalized and handed to the guests when the virtual machines boot. Paths are absolute,
+/// e.g. `/soc/virtio_mmio@a000000`.
+pub mod fdt {
+    use super::{guest_memory::GuestPhysAddrRange, vmm::VMId};
+
+    /// Value of a device tree property.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum FdtPropValue<'a> {
+        /// An empty value, for boolean properties like `dma-coherent`.
+        Empty,
+        /// A list of 32-bit cells, e.g. `reg` or `interrupts`.
+        Cells(&'a [u32]),
+        /// A 64-bit value, encoded as two cells.
+        U64(u64),
+        /// A string, e.g. `status`.
+        Str(&'a str),
+        /// A list of strings, e.g. `compatible`.
+        Strs(&'a [&'a str]),
+        /// Raw bytes.
+        Bytes(&'a [u8]),
+    }
+
+    /// A device tree property.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FdtProp<'a> {
+        /// Name of the property.
+        pub name: &'a str,
+        /// Value of the property.
+        pub value: FdtPropValue<'a>,
+    }
+
+    impl<'a> FdtProp<'a> {
+        /// Create a property.
+        pub const fn new(name: &'a str, value: FdtPropValue<'a>) -> Self {
+            Self { name, value }
+        }
+    }
+
+    /// An interrupt of a device tree node.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FdtInterrupt<'a> {
+        /// Path of the interrupt controller, or `None` for the default interrupt controller of the virtual machine.
+        pub parent: Option<&'a str>,
+        /// The interrupt specifier, whose format is defined by the interrupt controller, e.g. `<0 32 4>` for a
+        /// level-triggered SPI 32 of a GIC.
+        pub cells: &'a [u32],
+    }
+
+    /// Add a node with the given properties to the device tree of a virtual machine.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the parent node does not
+    /// exist, with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if the node already exists, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if the device tree is already finalized.
+    extern fn add_node(vm_id: VMId, path: &str, props: &[FdtProp]) -> crate::error::AxvisorResult;
+    /// Reserve a range of guest memory in the device tree of a virtual machine, so that the guest does not use it as
+    /// regular memory, e.g. for shared memory with a virtual device.
+    ///
+    /// Fails with [`BadAddress`](crate::error::AxvisorError::BadAddress) if the range is not guest memory, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if the device tree is already finalized.
+    extern fn reserve_memory(vm_id: VMId, range: GuestPhysAddrRange)
+    -> crate::error::AxvisorResult;
+    /// Add an interrupt to a node in the device tree of a virtual machine, setting the `interrupts` or
+    /// `interrupts-extended` property of the node.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the node or the interrupt controller does not
+    /// exist, with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the specifier does not match the
+    /// `#interrupt-cells` of the interrupt controller, or with [`BadState`](crate::error::AxvisorError::BadState) if
+    /// the device tree is already finalized.
+    extern fn add_interrupt(
+        vm_id: VMId,
+        node: &str,
+        interrupt: FdtInterrupt,
+    ) -> crate::error::AxvisorResult;
+}
+
+#[api_mod]
+/// Guest ACPI table construction API.
+///
+/// Analogous to [`fdt`](crate::fdt), components contribute fragments to the ACPI tables constructed for the virtual
+/// machines, which are assembled, checksummed and handed to the guests when the virtual machines boot.
+pub mod acpi {
+    use super::{guest_memory::GuestPhysAddr, vmm::VMId};
+
+    /// An ECAM segment of the PCI configuration space, described in the MCFG table.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct McfgSegment {
+        /// Base address of the ECAM region in guest physical memory.
+        pub base: GuestPhysAddr,
+        /// PCI segment group number.
+        pub segment: u16,
+        /// First bus number decoded by the region.
+        pub start_bus: u8,
+        /// Last bus number decoded by the region.
+        pub end_bus: u8,
+    }
+
+    /// A fragment of the ACPI tables.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum AcpiFragment<'a> {
+        /// An interrupt controller structure in the MADT, e.g. an I/O APIC or an interrupt source override, encoded
+        /// with its type and length bytes.
+        MadtEntry(&'a [u8]),
+        /// AML code appended to the DSDT, e.g. a `Device` describing a virtual device.
+        DsdtAml(&'a [u8]),
+        /// An ECAM segment in the MCFG table.
+        McfgSegment(McfgSegment),
+    }
+
+    impl AcpiFragment<'_> {
+        /// Check whether the fragment is well-formed, i.e. the length byte of a MADT entry matches its size, the AML
+        /// code is not empty, and the bus range of an ECAM segment is not empty.
+        pub fn is_well_formed(&self) -> bool {
+            match self {
+                Self::MadtEntry(entry) => entry.len() >= 2 && entry[1] as usize == entry.len(),
+                Self::DsdtAml(aml) => !aml.is_empty(),
+                Self::McfgSegment(segment) => segment.start_bus <= segment.end_bus,
+            }
+        }
+    }
+
+    /// Add a fragment to the ACPI tables of a virtual machine.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the fragment is not
+    /// [well-formed](AcpiFragment::is_well_formed), with [`NotFound`](crate::error::AxvisorError::NotFound) if the
+    /// virtual machine does not exist, or with [`BadState`](crate::error::AxvisorError::BadState) if the tables are
+    /// already assembled.
+    extern fn add_table_fragment(
+        vm_id: VMId,
+        fragment: AcpiFragment,
+    ) -> crate::error::AxvisorResult;
+}
+
+#[api_mod]
+/// Guest image loading API.
+///
+/// Boot-protocol components, e.g. the ones booting Linux `Image`s, `bzImage`s or ELF files, ask the hypervisor to
+/// fetch the bytes of the images into guest memory with this API, and parse the headers in guest memory themselves.
+pub mod loader {
+    use super::{guest_memory::GuestPhysAddr, memory::HostPhysAddr, vmm::VMId};
+
+    /// Source of an image.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ImageSource<'a> {
+        /// A file in the host file system, e.g. on a ramdisk.
+        HostFile(&'a str),
+        /// A blob embedded in the hypervisor or a component.
+        Blob(&'a [u8]),
+        /// A range of host physical memory, e.g. an image loaded by the bootloader.
+        MemoryRange {
+            /// Start of the range.
+            start: HostPhysAddr,
+            /// Size of the range in bytes.
+            size: usize,
+        },
+    }
+
+    /// Load the whole image into the guest memory of a virtual machine at `gpa`, returning the size of the image.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the host file does not
+    /// exist, with [`BadAddress`](crate::error::AxvisorError::BadAddress) if the image does not fit in the guest memory
+    /// at `gpa`, or with [`Io`](crate::error::AxvisorError::Io) if the image cannot be read.
+    extern fn load_image(
+        vm_id: VMId,
+        source: ImageSource,
+        gpa: GuestPhysAddr,
+    ) -> crate::error::AxvisorResult<usize>;
+    /// Get the size of an image in bytes, e.g. to decide where to load it.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the host file does not exist.
+    extern fn image_size(source: ImageSource) -> crate::error::AxvisorResult<usize>;
+}
+
+#[api_mod]
+/// Guest debugging API, on which debugger components, e.g. a GDB server, are built.
+pub mod debug {
+    extern crate alloc;
+    use super::{
+        guest_memory::GuestPhysAddr,
+        vmm::{VCpuId, VMId},
+    };
+    use alloc::boxed::Box;
+
+    /// A debug event raised by a vCPU of a guest.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum DebugEvent {
+        /// The vCPU hits a breakpoint set with [`set_guest_breakpoint`].
+        Breakpoint {
+            /// The vCPU hitting the breakpoint.
+            vcpu_id: VCpuId,
+            /// Guest physical address of the breakpoint.
+            gpa: GuestPhysAddr,
+        },
+        /// The vCPU completes a step, with single-stepping enabled by [`single_step_vcpu`].
+        SingleStep {
+            /// The stepping vCPU.
+            vcpu_id: VCpuId,
+        },
+    }
+
+    /// Handler of debug events, receiving the virtual machine raising the event.
+    ///
+    /// The vCPU raising the event is paused until the handler returns.
+    pub type DebugEventHandler = Box<dyn Fn(VMId, DebugEvent) + Send + Sync + 'static>;
+
+    /// Set a breakpoint at a guest physical address of a virtual machine, for all its vCPUs.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, with
+    /// [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if the breakpoint is already set, with
+    /// [`BadAddress`](crate::error::AxvisorError::BadAddress) if the address is not backed by guest memory, or with
+    /// [`NoMemory`](crate::error::AxvisorError::NoMemory) if no more breakpoints can be set.
+    extern fn set_guest_breakpoint(vm_id: VMId, gpa: GuestPhysAddr) -> crate::error::AxvisorResult;
+    /// Clear a breakpoint set with [`set_guest_breakpoint`].
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or the
+    /// breakpoint is not set.
+    extern fn clear_guest_breakpoint(
+        vm_id: VMId,
+        gpa: GuestPhysAddr,
+    ) -> crate::error::AxvisorResult;
+    /// Enable or disable single-stepping of a vCPU, which raises a [`DebugEvent::SingleStep`] after each instruction
+    /// it executes.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the vCPU does not
+    /// exist, or with [`Unsupported`](crate::error::AxvisorError::Unsupported) if the platform cannot single-step
+    /// guests.
+    extern fn single_step_vcpu(
+        vm_id: VMId,
+        vcpu_id: VCpuId,
+        enable: bool,
+    ) -> crate::error::AxvisorResult;
+    /// Register the handler of debug events. Events raised without a handler are injected into the guest as they would
+    /// be on bare metal.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if a handler is already registered.
+    extern fn register_debug_event_handler(
+        handler: DebugEventHandler,
+    ) -> crate::error::AxvisorResult;
+}
+
+#[api_mod]
+/// Virtual machine save and restore API, on which checkpoint/restore and live migration components are built.
+///
+/// The state of a virtual machine, including its guest memory, virtual CPUs and devices, is saved into a stream of
+/// opaque chunks, which is restored in the same order into a new virtual machine, possibly on another host running the
+/// same hypervisor.
+pub mod migration {
+    use super::vmm::{VMId, VmConfigView};
+
+    /// Handle of a save session.
+    pub type SaveSession = u64;
+    /// Handle of a restore session.
+    pub type RestoreSession = u64;
+
+    /// Progress of a save session after saving a chunk.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SaveProgress {
+        /// A chunk of the given size is saved, and more chunks follow.
+        Continue(usize),
+        /// The last chunk, of the given size, is saved, and the session is ended.
+        Done(usize),
+    }
+
+    /// Begin to save the state of a paused virtual machine.
+    ///
+    /// To migrate a running virtual machine, its guest memory is pre-copied with the help of the dirty tracking below,
+    /// before pausing it and saving the state. Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the
+    /// virtual machine does not exist, or with [`BadState`](crate::error::AxvisorError::BadState) if it is not paused
+    /// or already being saved.
+    extern fn begin_save(vm_id: VMId) -> crate::error::AxvisorResult<SaveSession>;
+    /// Save the next chunk of the state into `buf`.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the session does not exist, or with
+    /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if `buf` is too small for the next chunk, which never
+    /// happens with buffers of at least a page.
+    extern fn save_next_chunk(
+        session: SaveSession,
+        buf: &mut [u8],
+    ) -> crate::error::AxvisorResult<SaveProgress>;
+    /// Begin to restore a saved state into a new paused virtual machine with the given configuration.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the configuration is invalid.
+    extern fn begin_restore(config: VmConfigView) -> crate::error::AxvisorResult<RestoreSession>;
+    /// Restore the next chunk of the state, in the order the chunks are saved.
+    ///
+    /// Returns the ID of the restored virtual machine when the last chunk is restored, and the session is ended. Fails
+    /// with [`NotFound`](crate::error::AxvisorError::NotFound) if the session does not exist, or with
+    /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the chunk is malformed or does not match the
+    /// configuration, in which case the session is ended and the new virtual machine is destroyed.
+    extern fn restore_chunk(
+        session: RestoreSession,
+        buf: &[u8],
+    ) -> crate::error::AxvisorResult<Option<VMId>>;
+    /// Start tracking the pages of the guest memory of a virtual machine written since the last collection.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if the tracking is already enabled.
+    extern fn enable_dirty_tracking(vm_id: VMId) -> crate::error::AxvisorResult;
+    /// Stop tracking the written pages of the guest memory of a virtual machine.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if the tracking is not enabled.
+    extern fn disable_dirty_tracking(vm_id: VMId) -> crate::error::AxvisorResult;
+    /// Collect the pages of the guest memory of a virtual machine written since the last collection into `bitmap`,
+    /// returning the number of them.
+    ///
+    /// Bit `i % 64` of `bitmap[i / 64]` is set if the `i`-th 4K page of the guest physical address space is written.
+    /// Pages beyond the bitmap are not collected and stay dirty. Fails with
+    /// [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine does not exist, or with
+    /// [`BadState`](crate::error::AxvisorError::BadState) if the tracking is not enabled.
+    extern fn collect_dirty_bitmap(
+        vm_id: VMId,
+        bitmap: &mut [u64],
+    ) -> crate::error::AxvisorResult<usize>;
+}
+
+#[api_mod]
+pub mod host {
+    /// Get the total number of cpus in the host system.
+    extern fn get_host_cpu_num() -> usize;
+
+    /// A set of physical CPUs of the host, with IDs less than [`HostCpuMask::CAPACITY`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct HostCpuMask {
+        bits: [u64; HostCpuMask::CAPACITY / 64],
+    }
+
+    impl HostCpuMask {
+        /// Number of CPU IDs covered by the mask.
+        pub const CAPACITY: usize = 256;
+
+        /// Create an empty set.
+        pub const fn new() -> Self {
+            Self {
+                bits: [0; Self::CAPACITY / 64],
+            }
+        }
+
+        /// Create a set of the first `num` CPUs, e.g. all CPUs with [`get_host_cpu_num`].
+        pub fn first(num: usize) -> Self {
+            let mut mask = Self::new();
+            (0..num.min(Self::CAPACITY)).for_each(|cpu_id| {
+                mask.insert(cpu_id);
+            });
+            mask
+        }
+
+        /// Add a CPU to the set, returning `false` if its ID is out of the capacity.
+        pub fn insert(&mut self, cpu_id: usize) -> bool {
+            if cpu_id >= Self::CAPACITY {
+                return false;
+            }
+            self.bits[cpu_id / 64] |= 1 << (cpu_id % 64);
+            true
+        }
+
+        /// Remove a CPU from the set.
+        pub fn remove(&mut self, cpu_id: usize) {
+            if let Some(word) = self.bits.get_mut(cpu_id / 64) {
+                *word &= !(1 << (cpu_id % 64));
+            }
+        }
+
+        /// Check whether a CPU is in the set.
+        pub fn contains(&self, cpu_id: usize) -> bool {
+            self.bits
+                .get(cpu_id / 64)
+                .is_some_and(|word| word & (1 << (cpu_id % 64)) != 0)
+        }
+
+        /// Check whether the set is empty.
+        pub fn is_empty(&self) -> bool {
+            self.bits.iter().all(|&word| word == 0)
+        }
+
+        /// Get the number of CPUs in the set.
+        pub fn count(&self) -> usize {
+            self.bits
+                .iter()
+                .map(|word| word.count_ones() as usize)
+                .sum()
+        }
+
+        /// Iterate over the CPUs in the set in ascending order of IDs.
+        pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+            self.bits.iter().enumerate().flat_map(|(index, &word)| {
+                (0..64)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| index * 64 + bit)
+            })
+        }
+    }
+}
+
+#[api_mod]
+/// Architecture-specific API.
+///
+/// API functions specific to an architecture are gated by `target_arch`, but the types in their signatures are not,
+/// and are only marked as specific in their docs: [`crate_interface::def_interface`] drops the `cfg` attributes of the
+/// functions in the API trait it generates, so the types must be available on all architectures.
+pub mod arch {
+    extern crate alloc;
+    use super::vmm::{InterruptVector, VCpuId, VMId};
+    use alloc::boxed::Box;
+    use axaddrspace::device::AccessWidth;
+
+    /// Encoding of a system register, i.e. the `op0`, `op1`, `CRn`, `CRm` and `op2` fields on AArch64 (see
+    /// [`aarch64_sysreg_encoding`]), the MSR index on x86_64, or the CSR number on RISC-V.
+    pub type SysRegEncoding = u32;
+
+    /// A trapped guest access to a system register.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SysRegAccess {
+        /// The guest reads the register.
+        Read,
+        /// The guest writes the value to the register.
+        Write(u64),
+    }
+
+    /// Handler of trapped guest accesses to a system register.
+    ///
+    /// The handler returns the value to be read by the guest for reads (the returned value is ignored for writes), or
+    /// `None` if the access is not handled, in which case the hypervisor falls back to its default behavior.
+    pub type SysRegHandler =
+        Box<dyn Fn(VMId, VCpuId, SysRegAccess) -> Option<u64> + Send + Sync + 'static>;
+
+    /// Register a handler for trapped guest accesses to a system register.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if there is already a handler for the
+    /// register.
+    extern fn register_sysreg_handler(
+        encoding: SysRegEncoding,
+        handler: SysRegHandler,
+    ) -> crate::error::AxvisorResult;
+    /// Unregister the handler for trapped guest accesses to a system register.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if there is no handler for the register.
+    extern fn unregister_sysreg_handler(encoding: SysRegEncoding) -> crate::error::AxvisorResult;
+
+    /// Hardware ID of a guest address space, i.e. the VMID on aarch64 and riscv64, or the VPID on x86_64, which tags
+    /// the TLB entries of the guest.
+    pub type HwVmId = u16;
+
+    /// Allocate a hardware ID for a guest address space.
+    ///
+    /// Fails with [`NoMemory`](crate::error::AxvisorError::NoMemory) if all IDs supported by the hardware are in use.
+    extern fn allocate_vmid() -> crate::error::AxvisorResult<HwVmId>;
+    /// Free a hardware ID allocated with [`allocate_vmid`], flushing the TLB entries tagged with it.
+    extern fn free_vmid(vmid: HwVmId);
+    /// Invalidate the TLB entries tagged with a hardware ID on all physical CPUs.
+    extern fn flush_tlb_by_vmid(vmid: HwVmId);
+
+    /// A set of CPU features, which guests may use if exposed to them.
+    ///
+    /// The features of all architectures share one set, with each feature documented with its architecture.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct CpuFeatures(pub u64);
+
+    impl CpuFeatures {
+        /// No features.
+        pub const EMPTY: Self = Self(0);
+        /// AArch64. The Scalable Vector Extension.
+        pub const SVE: Self = Self(1 << 0);
+        /// AArch64. The Scalable Vector Extension version 2.
+        pub const SVE2: Self = Self(1 << 1);
+        /// AArch64. The Virtualization Host Extensions.
+        pub const VHE: Self = Self(1 << 2);
+        /// AArch64. The system register interface of GICv3.
+        pub const GICV3: Self = Self(1 << 3);
+        /// AArch64. Direct injection of virtual LPIs of GICv4.
+        pub const GICV4: Self = Self(1 << 4);
+        /// AArch64. Pointer authentication.
+        pub const PAUTH: Self = Self(1 << 5);
+        /// AArch64. The Memory Tagging Extension.
+        pub const MTE: Self = Self(1 << 6);
+        /// riscv64. The vector extension.
+        pub const RV_VECTOR: Self = Self(1 << 16);
+        /// riscv64. Supervisor-mode timer interrupts (`Sstc`).
+        pub const SSTC: Self = Self(1 << 17);
+        /// riscv64. The Advanced Interrupt Architecture.
+        pub const AIA: Self = Self(1 << 18);
+        /// x86_64. Extended page tables of VMX.
+        pub const VMX_EPT: Self = Self(1 << 32);
+        /// x86_64. Virtual processor identifiers of VMX.
+        pub const VMX_VPID: Self = Self(1 << 33);
+        /// x86_64. Unrestricted guests of VMX.
+        pub const VMX_UNRESTRICTED_GUEST: Self = Self(1 << 34);
+        /// x86_64. Posted interrupts of VMX.
+        pub const VMX_POSTED_INTERRUPTS: Self = Self(1 << 35);
+        /// x86_64. TSC scaling of VMX.
+        pub const VMX_TSC_SCALING: Self = Self(1 << 36);
+        /// x86_64. The AVX-512 foundation instructions.
+        pub const AVX512F: Self = Self(1 << 37);
+
+        /// Check whether all features in `other` are in the set.
+        pub const fn contains(self, other: Self) -> bool {
+            self.0 & other.0 == other.0
+        }
+
+        /// Check whether the set is empty.
+        pub const fn is_empty(self) -> bool {
+            self.0 == 0
+        }
+
+        /// Get the features in either set.
+        pub const fn union(self, other: Self) -> Self {
+            Self(self.0 | other.0)
+        }
+
+        /// Get the features in both sets.
+        pub const fn intersection(self, other: Self) -> Self {
+            Self(self.0 & other.0)
+        }
+
+        /// Get the features in the set but not in `other`.
+        pub const fn difference(self, other: Self) -> Self {
+            Self(self.0 & !other.0)
+        }
+    }
+
+    impl core::ops::BitOr for CpuFeatures {
+        type Output = Self;
+
+        fn bitor(self, rhs: Self) -> Self {
+            self.union(rhs)
+        }
+    }
+
+    impl core::ops::BitAnd for CpuFeatures {
+        type Output = Self;
+
+        fn bitand(self, rhs: Self) -> Self {
+            self.intersection(rhs)
+        }
+    }
+
+    /// Get the features supported by all physical CPUs of the host, read from their ID registers (or `CPUID` and the
+    /// VMX capability MSRs on x86_64).
+    extern fn host_cpu_features() -> CpuFeatures;
+
+    /// Handler of overflows of host performance counters, receiving the index of the overflowed counter.
+    ///
+    /// The handler is called in the interrupt context of the physical CPU owning the counter.
+    pub type PmuOverflowHandler = Box<dyn Fn(usize) + Send + Sync + 'static>;
+
+    /// Read a performance counter of the current physical CPU, i.e. `PMEVCNTR<n>_EL0` on aarch64, `hpmcounter<n>` on
+    /// riscv64, or `IA32_PMC<n>` on x86_64.
+    ///
+    /// Returns `None` if the counter is not implemented, or is lent to the guest running on the physical CPU.
+    extern fn read_host_pmu_counter(idx: usize) -> Option<u64>;
+    /// Enable the virtual PMU of a virtual CPU, whose performance counters are backed by the host ones and switched
+    /// with the virtual CPU, so that their overflows are delivered to the guest instead of the handler.
+    ///
+    /// Fails with [`NotFound`](crate::error::AxvisorError::NotFound) if the virtual machine or the virtual CPU does
+    /// not exist, or with [`Unsupported`](crate::error::AxvisorError::Unsupported) if the host PMU cannot be
+    /// virtualized.
+    extern fn enable_guest_pmu(vm_id: VMId, vcpu_id: VCpuId) -> crate::error::AxvisorResult;
+    /// Register the handler of overflows of host performance counters, e.g. for a profiler sampling the hypervisor.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if a handler is already registered.
+    extern fn register_pmu_overflow_handler(
+        handler: PmuOverflowHandler,
+    ) -> crate::error::AxvisorResult;
+
+    /// Get the [`SysRegEncoding`] of an AArch64 system register, in the same layout as the ISS of `ESR_EL2` for
+    /// trapped `MSR`/`MRS` instructions.
+    pub const fn aarch64_sysreg_encoding(
+        op0: u32,
+        op1: u32,
+        crn: u32,
+        crm: u32,
+        op2: u32,
+    ) -> SysRegEncoding {
+        (op0 << 20) | (op2 << 17) | (op1 << 14) | (crn << 10) | (crm << 1)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Inject a virtual interrupt to the current virtual CPU using gich.
+    extern fn hardware_inject_virtual_interrupt(vector: InterruptVector);
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Inject a software-generated interrupt (SGI) to the virtual CPUs in `target_list` of a
+    /// virtual machine, e.g. on a guest write to `ICC_SGI1R_EL1` or `GICD_SGIR`.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if `sgi_id` is not less than 16.
+    extern fn inject_sgi(
+        vm_id: VMId,
+        sgi_id: u8,
+        target_list: crate::vmm::VCpuMask,
+    ) -> crate::error::AxvisorResult;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Inject a software-generated interrupt (SGI) to all virtual CPUs of a virtual machine
+    /// except the current one, i.e. the `IRM` mode of `ICC_SGI1R_EL1`.
+    extern fn broadcast_sgi(vm_id: VMId, sgi_id: u8) -> crate::error::AxvisorResult;
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Get the TYPER register of the GIC distributor. Used in virtual GIC initialization.
+    extern fn read_vgicd_typer() -> u32;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Get the IIDR register of the GIC distributor. Used in virtual GIC initialization.
+    extern fn read_vgicd_iidr() -> u32;
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Get the base address of the GIC distributor in the host system.
+    extern fn get_host_gicd_base() -> crate::memory::HostPhysAddr;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Get the base address of the GIC redistributor in the host system.
+    extern fn get_host_gicr_base() -> crate::memory::HostPhysAddr;
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Read a register of the GIC redistributor of a physical CPU in the host system, at
+    /// `offset` from the base of the redistributor.
+    extern fn read_gicr_register(cpu_id: usize, offset: usize, width: AccessWidth) -> u64;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Write a register of the GIC redistributor of a physical CPU in the host system, at
+    /// `offset` from the base of the redistributor.
+    extern fn write_gicr_register(cpu_id: usize, offset: usize, width: AccessWidth, value: u64);
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Get the number of implemented list registers (`ICH_LR<n>_EL2`).
+    extern fn available_lr_count() -> usize;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Read a list register (`ICH_LR<n>_EL2`) of the current physical CPU.
+    extern fn read_lr(index: usize) -> u64;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Write a list register (`ICH_LR<n>_EL2`) of the current physical CPU.
+    extern fn write_lr(index: usize, value: u64);
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Read `ICH_VMCR_EL2` of the current physical CPU.
+    extern fn read_ich_vmcr() -> u64;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Write `ICH_VMCR_EL2` of the current physical CPU.
+    extern fn write_ich_vmcr(value: u64);
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Read `ICH_HCR_EL2` of the current physical CPU.
+    extern fn read_ich_hcr() -> u64;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Write `ICH_HCR_EL2` of the current physical CPU.
+    extern fn write_ich_hcr(value: u64);
+
+    /// AArch64-specific. A 32-byte command of the GIC ITS.
+    pub type ItsCommand = [u64; 4];
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Get the base address of the GIC ITS in the host system, if there is one.
+    extern fn get_host_gits_base() -> Option<crate::memory::HostPhysAddr>;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Submit commands to the command queue of the GIC ITS in the host system, and wait for
+    /// them to be consumed.
+    ///
+    /// Returns the number of commands submitted, which is less than `commands.len()` if the queue is full or there is
+    /// no ITS.
+    extern fn submit_its_commands(commands: &[ItsCommand]) -> usize;
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Power on the virtual CPU with the given MPIDR in a virtual machine, which starts
+    /// executing at `entry` with `context_id` in `x0`, as `CPU_ON` of PSCI.
+    ///
+    /// Fails with [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if there is no such virtual CPU, or with
+    /// [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if it is already on.
+    extern fn vcpu_power_on(
+        vm_id: VMId,
+        target_mpidr: u64,
+        entry: crate::guest_memory::GuestPhysAddr,
+        context_id: u64,
+    ) -> crate::error::AxvisorResult;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Power off a virtual CPU in a virtual machine, as `CPU_OFF` of PSCI.
+    extern fn vcpu_power_off(vm_id: VMId, vcpu_id: VCpuId) -> crate::error::AxvisorResult;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Suspend a virtual CPU in a virtual machine until it is woken up by an interrupt, with
+    /// the `power_state` parameter of `CPU_SUSPEND` of PSCI.
+    extern fn vcpu_suspend(vm_id: VMId, vcpu_id: VCpuId, state: u32)
+    -> crate::error::AxvisorResult;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Get the virtual CPU with the given MPIDR in a virtual machine.
+    extern fn mpidr_to_vcpu(vm_id: VMId, mpidr: u64) -> Option<VCpuId>;
+
+    /// AArch64-specific. Return value of an SMC call, i.e. `x0`-`x3` as defined by the SMC Calling Convention.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct SmcResult {
+        /// Values of `x0`-`x3`.
+        pub regs: [u64; 4],
+    }
+
+    impl SmcResult {
+        /// The `NOT_SUPPORTED` error code of the SMC Calling Convention, returned in `x0`.
+        pub const NOT_SUPPORTED: Self = Self {
+            regs: [-1i64 as u64, 0, 0, 0],
+        };
+    }
+
+    /// AArch64-specific. Decision of an SMC filter on an SMC call of a guest.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SmcFilterAction {
+        /// Forward the call to the EL3 firmware unchanged, and return its result to the guest.
+        Forward,
+        /// Complete the call with the given result, without involving the EL3 firmware.
+        Complete(SmcResult),
+        /// Deny the call, returning [`SmcResult::NOT_SUPPORTED`] to the guest.
+        Deny,
+    }
+
+    /// AArch64-specific. Filter of SMC calls of guests, receiving the caller, the function ID in `w0`, and the
+    /// arguments in `x1`-`x6`.
+    pub type SmcFilter =
+        Box<dyn Fn(VMId, VCpuId, u32, &[u64; 6]) -> SmcFilterAction + Send + Sync + 'static>;
+
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Issue an SMC call to the EL3 firmware with the function ID and the arguments in
+    /// `x1`-`x6`, e.g. to forward a call of a guest.
+    ///
+    /// The caller is responsible for only forwarding calls which cannot break the isolation between virtual machines.
+    extern fn forward_smc(func_id: u32, args: [u64; 6]) -> SmcResult;
+    #[cfg(target_arch = "aarch64")]
+    /// AArch64-specific API. Register the filter deciding how the SMC calls of guests not handled by the hypervisor
+    /// itself (e.g. PSCI) are handled. Without a filter, such calls are denied.
+    ///
+    /// Fails with [`AlreadyExists`](crate::error::AxvisorError::AlreadyExists) if a filter is already registered.
+    extern fn register_smc_filter(filter: SmcFilter) -> crate::error::AxvisorResult;
+
+    /// RISC-V-specific. Return value of an SBI call.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SbiRet {
+        /// Error code, `0` on success.
+        pub error: isize,
+        /// Return value.
+        pub value: usize,
+    }
+
+    #[cfg(target_arch = "riscv64")]
+    /// RISC-V-specific. Bit of the virtual supervisor software interrupt in `hvip`.
+    pub const HVIP_VSSIP: usize = 1 << 2;
+    #[cfg(target_arch = "riscv64")]
+    /// RISC-V-specific. Bit of the virtual supervisor timer interrupt in `hvip`.
+    pub const HVIP_VSTIP: usize = 1 << 6;
+    #[cfg(target_arch = "riscv64")]
+    /// RISC-V-specific. Bit of the virtual supervisor external interrupt in `hvip`.
+    pub const HVIP_VSEIP: usize = 1 << 10;
+
+    #[cfg(target_arch = "riscv64")]
+    /// RISC-V-specific API. Forward an SBI call to the SBI implementation of the host system. Used in SBI emulation
+    /// for calls that are not handled by the hypervisor itself.
+    extern fn sbi_call(eid: usize, fid: usize, args: [usize; 6]) -> SbiRet;
+
+    #[cfg(target_arch = "riscv64")]
+    /// RISC-V-specific API. Read a hypervisor or virtual supervisor CSR (e.g. `hstatus`, `vsatp`) of the current
+    /// virtual CPU, identified by its CSR number.
+    extern fn read_hcsr(csr: u16) -> usize;
+    #[cfg(target_arch = "riscv64")]
+    /// RISC-V-specific API. Write a hypervisor or virtual supervisor CSR (e.g. `hstatus`, `vsatp`) of the current
+    /// virtual CPU, identified by its CSR number.
+    extern fn write_hcsr(csr: u16, value: usize);
+
+    #[cfg(target_arch = "riscv64")]
+    /// RISC-V-specific API. Set bits in `hvip` of the current virtual CPU, i.e. inject virtual interrupts like
+    /// [`HVIP_VSEIP`]. Used in PLIC/AIA emulation.
+    extern fn set_hvip(bits: usize);
+    #[cfg(target_arch = "riscv64")]
+    /// RISC-V-specific API. Clear bits in `hvip` of the current virtual CPU, i.e. retract injected virtual interrupts.
+    extern fn clear_hvip(bits: usize);
+
+    #[cfg(target_arch = "x86_64")]
+    /// x86_64-specific API. Read a field of the VMCS of the current virtual CPU, identified by its field encoding.
+    extern fn read_vmcs_field(field: u32) -> u64;
+    #[cfg(target_arch = "x86_64")]
+    /// x86_64-specific API. Write a field of the VMCS of the current virtual CPU, identified by its field encoding.
+    extern fn write_vmcs_field(field: u32, value: u64);
+
+    #[cfg(target_arch = "x86_64")]
+    /// x86_64-specific API. Inject an exception to the current virtual CPU on the next VM entry, with an optional
+    /// error code.
+    extern fn inject_exception(vector: InterruptVector, error_code: Option<u32>);
+
+    #[cfg(target_arch = "x86_64")]
+    /// x86_64-specific API. Read a guest MSR of the current virtual CPU.
+    extern fn read_guest_msr(msr: u32) -> u64;
+    #[cfg(target_arch = "x86_64")]
+    /// x86_64-specific API. Write a guest MSR of the current virtual CPU.
+    extern fn write_guest_msr(msr: u32, value: u64);
+
+    #[cfg(target_arch = "x86_64")]
+    /// x86_64-specific API. Invalidate the EPT-derived TLB entries of a virtual machine.
+    extern fn flush_ept_tlb(vm_id: crate::vmm::VMId);
+
+    /// x86_64-specific. An interrupt remapping table entry (IRTE) of VT-d, in the remapped format.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Irte {
+        /// Lower 64 bits.
+        pub low: u64,
+        /// Upper 64 bits.
+        pub high: u64,
+    }
+
+    impl Irte {
+        /// Reserved bits in [`Irte::low`], including the posted-interrupt mode bit, which guests cannot use.
+        const LOW_RESERVED: u64 = 0xff00_f000;
+        /// Reserved bits in [`Irte::high`].
+        const HIGH_RESERVED: u64 = !0xf_ffff;
+
+        /// Check whether the entry is present.
+        pub const fn is_present(self) -> bool {
+            self.low & 1 != 0
+        }
+
+        /// Get the vector of the interrupt.
+        pub const fn vector(self) -> u8 {
+            (self.low >> 16) as u8
+        }
+
+        /// Get the destination APIC ID of the interrupt.
+        pub const fn destination(self) -> u32 {
+            (self.low >> 32) as u32
+        }
+
+        /// Get the source ID (bus, device and function) allowed to raise the interrupt.
+        pub const fn source_id(self) -> u16 {
+            self.high as u16
+        }
+
+        /// Check whether the entry is well-formed, i.e. no reserved bit is set.
+        pub const fn is_well_formed(self) -> bool {
+            self.low & Self::LOW_RESERVED == 0 && self.high & Self::HIGH_RESERVED == 0
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    /// x86_64-specific API. Shadow an IRTE programmed by a guest into its virtual interrupt remapping table, used by
+    /// virtual IOMMUs.
+    ///
+    /// The entry is validated and translated into a host IRTE, whose destination is the physical CPU running the
+    /// targeted virtual CPU (or a posted-interrupt descriptor), and the index of the host IRTE is returned. Fails with
+    /// [`InvalidArg`](crate::error::AxvisorError::InvalidArg) if the entry is not well-formed, or with
+    /// [`PermissionDenied`](crate::error::AxvisorError::PermissionDenied) if the source or the destination does not
+    /// belong to the virtual machine.
+    extern fn shadow_irte(
+        vm_id: crate::vmm::VMId,
+        guest_index: u16,
+        irte: Irte,
+    ) -> crate::error::AxvisorResult<u16>;
+    #[cfg(target_arch = "x86_64")]
+    /// x86_64-specific API. Remove the shadow of an IRTE of a guest, e.g. when the guest clears or invalidates it.
+    extern fn unshadow_irte(
+        vm_id: crate::vmm::VMId,
+        guest_index: u16,
+    ) -> crate::error::AxvisorResult;
+}
+
+pub mod error;
+pub mod id;
+pub mod mmio;
+pub mod registry;
+pub mod ring;
+#[cfg(feature = "std")]
+pub mod testing;
+pub mod version;
+pub mod virtio;
+
+#[doc(hidden)]
+pub mod __priv {
+    pub mod crate_interface {
+        pub use crate_interface::{call_interface, def_interface, impl_interface};
+    }
+
+    pub use crate::diag::{ApiCallArgs, ApiCallTracer, ApiCallWatchdog};
+    pub use crate::error::{ApiNotImplemented, AxvisorResult};
+    #[cfg(feature = "dynamic")]
+    pub use crate::registry::register as register_api_fn;
+    pub use crate::registry::{ApiFnCache, ApiFnEntry, for_each as for_each_api_fn};
+    pub use crate::version::ApiVersion;
+
+    /// Implemented by the marker of an API function if the function is available in the current build configuration.
+    #[diagnostic::on_unimplemented(
+        message = "API function `{Self}` is not available in this build configuration",
+        label = "implemented here, but `cfg`'d out in the definition of the API module",
+        note = "gate the implementation with the same `cfg` attributes as the definition"
+    )]
+    pub trait ApiFnDefined {}
+
+    /// Check that an API function is available, used by implementations of API modules.
+    pub const fn assert_api_fn_defined<T: ApiFnDefined>() {}
 }
 
 #[cfg(test)]
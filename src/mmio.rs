@@ -0,0 +1,265 @@
+//! Helpers for describing and accessing memory-mapped register blocks.
+//!
+//! Register blocks are described with the [`mmio_regs!`](crate::mmio_regs) macro, which generates:
+//!
+//! - a marker type for each register, implementing [`Register`] (and [`Readable`] / [`Writable`] according to its
+//!   access mode), which can be used with [`MmioRegion`] to access real device registers with the right width;
+//! - a register storage type implementing [`RegisterBlock`], holding the value of each register with its reset value,
+//!   which device emulators can use to dispatch guest accesses by offset.
+
+use memory_addr::VirtAddr;
+
+/// Values that a memory-mapped register can hold.
+pub trait MmioValue: Copy {
+    /// Truncate a `u64` into the value.
+    fn from_u64(value: u64) -> Self;
+    /// Zero-extend the value into a `u64`.
+    fn to_u64(self) -> u64;
+}
+
+macro_rules! impl_mmio_value {
+    ($($ty:ty),*) => {
+        $(
+            impl MmioValue for $ty {
+                fn from_u64(value: u64) -> Self {
+                    value as $ty
+                }
+
+                fn to_u64(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_mmio_value!(u8, u16, u32, u64);
+
+/// A memory-mapped register.
+pub trait Register {
+    /// Type of the value of the register, which also determines the access width.
+    type Value: MmioValue;
+    /// Offset of the register in the register block.
+    const OFFSET: usize;
+    /// Value of the register after reset.
+    const RESET: Self::Value;
+    /// Name of the register.
+    const NAME: &'static str;
+}
+
+/// Marker trait for registers that can be read.
+pub trait Readable: Register {}
+/// Marker trait for registers that can be written.
+pub trait Writable: Register {}
+
+/// Registers whose value is stored in the register storage type `B`.
+pub trait RegisterOf<B>: Register {
+    /// Get the stored value of the register.
+    fn get(block: &B) -> Self::Value;
+    /// Set the stored value of the register.
+    fn set(block: &mut B, value: Self::Value);
+}
+
+/// Register storage of an emulated register block.
+pub trait RegisterBlock {
+    /// Read the register at `offset`.
+    ///
+    /// Returns `None` if there is no readable register at `offset`.
+    fn read(&self, offset: usize) -> Option<u64>;
+    /// Write the register at `offset`, truncating `value` to the width of the register.
+    ///
+    /// Returns `false` if there is no writable register at `offset`.
+    fn write(&mut self, offset: usize, value: u64) -> bool;
+    /// Reset all registers to their reset values.
+    fn reset(&mut self);
+}
+
+/// A memory-mapped register block mapped in the hypervisor's address space.
+#[derive(Debug, Clone, Copy)]
+pub struct MmioRegion {
+    base: VirtAddr,
+}
+
+impl MmioRegion {
+    /// Create a register block accessor at `base`.
+    ///
+    /// # Safety
+    ///
+    /// `base` must point to a valid device mapping covering all registers accessed through this accessor.
+    pub const unsafe fn new(base: VirtAddr) -> Self {
+        Self { base }
+    }
+
+    /// Get the base address of the register block.
+    pub const fn base(&self) -> VirtAddr {
+        self.base
+    }
+
+    /// Read a register.
+    pub fn read<R: Readable>(&self) -> R::Value {
+        // SAFETY: guaranteed by the caller of `new`.
+        unsafe { core::ptr::read_volatile((self.base + R::OFFSET).as_ptr_of::<R::Value>()) }
+    }
+
+    /// Write a register.
+    pub fn write<R: Writable>(&self, value: R::Value) {
+        // SAFETY: guaranteed by the caller of `new`.
+        unsafe {
+            core::ptr::write_volatile((self.base + R::OFFSET).as_mut_ptr_of::<R::Value>(), value)
+        }
+    }
+
+    /// Read-modify-write a register.
+    pub fn modify<R: Readable + Writable>(&self, f: impl FnOnce(R::Value) -> R::Value) {
+        self.write::<R>(f(self.read::<R>()))
+    }
+}
+
+/// Describe a block of memory-mapped registers.
+///
+/// Each register is described as `offset => NAME: type access [= reset]`, where `type` is one of `u8`, `u16`, `u32`
+/// and `u64`, `access` is one of `rw`, `ro` and `wo`, and the reset value defaults to `0`.
+///
+/// A marker type implementing [`Register`] is generated for each register next to the storage type, so register
+/// blocks sharing register names should be placed in different modules.
+///
+/// ```rust
+/// use axvisor_api::mmio::RegisterBlock;
+///
+/// axvisor_api::mmio_regs! {
+///     /// Registers of a demo device.
+///     pub struct DemoRegs {
+///         /// Control register.
+///         0x00 => CTRL: u32 rw,
+///         /// Status register.
+///         0x04 => STATUS: u32 ro = 0x1,
+///         /// Data register.
+///         0x08 => DATA: u64 wo,
+///     }
+/// }
+///
+/// let mut regs = DemoRegs::new();
+/// assert_eq!(regs.read(DemoRegs::STATUS), Some(0x1));
+/// assert!(regs.write(0x00, 0x42));
+/// assert!(!regs.write(0x04, 0x42));
+/// assert_eq!(regs.get::<CTRL>(), 0x42);
+/// ```
+#[macro_export]
+macro_rules! mmio_regs {
+    (@reset) => { 0 };
+    (@reset $reset:expr) => { $reset };
+    (@readable rw) => { true };
+    (@readable ro) => { true };
+    (@readable wo) => { false };
+    (@writable rw) => { true };
+    (@writable ro) => { false };
+    (@writable wo) => { true };
+    (@access rw $reg:ident) => {
+        impl $crate::mmio::Readable for $reg {}
+        impl $crate::mmio::Writable for $reg {}
+    };
+    (@access ro $reg:ident) => {
+        impl $crate::mmio::Readable for $reg {}
+    };
+    (@access wo $reg:ident) => {
+        impl $crate::mmio::Writable for $reg {}
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$reg_attr:meta])*
+                $offset:literal => $reg:ident : $ty:ident $access:ident $(= $reset:expr)?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[allow(non_snake_case)]
+        #[derive(Debug, Clone)]
+        $vis struct $name {
+            $($reg: $ty,)*
+        }
+
+        impl $name {
+            $(
+                #[doc = concat!("Offset of the `", stringify!($reg), "` register.")]
+                pub const $reg: usize = $offset;
+            )*
+
+            /// Create the register storage with all registers set to their reset values.
+            pub fn new() -> Self {
+                Self {
+                    $($reg: <$reg as $crate::mmio::Register>::RESET,)*
+                }
+            }
+
+            /// Get the stored value of a register.
+            pub fn get<R: $crate::mmio::RegisterOf<Self>>(&self) -> R::Value {
+                R::get(self)
+            }
+
+            /// Set the stored value of a register.
+            pub fn set<R: $crate::mmio::RegisterOf<Self>>(&mut self, value: R::Value) {
+                R::set(self, value)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl $crate::mmio::RegisterBlock for $name {
+            #[allow(unreachable_patterns)]
+            fn read(&self, offset: usize) -> Option<u64> {
+                match offset {
+                    $($offset if $crate::mmio_regs!(@readable $access) => {
+                        Some($crate::mmio::MmioValue::to_u64(self.$reg))
+                    })*
+                    _ => None,
+                }
+            }
+
+            #[allow(unreachable_patterns)]
+            fn write(&mut self, offset: usize, value: u64) -> bool {
+                match offset {
+                    $($offset if $crate::mmio_regs!(@writable $access) => {
+                        self.$reg = $crate::mmio::MmioValue::from_u64(value);
+                        true
+                    })*
+                    _ => false,
+                }
+            }
+
+            fn reset(&mut self) {
+                *self = Self::new();
+            }
+        }
+
+        $(
+            $(#[$reg_attr])*
+            #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+            $vis struct $reg;
+
+            impl $crate::mmio::Register for $reg {
+                type Value = $ty;
+                const OFFSET: usize = $offset;
+                const RESET: $ty = $crate::mmio_regs!(@reset $($reset)?);
+                const NAME: &'static str = stringify!($reg);
+            }
+
+            impl $crate::mmio::RegisterOf<$name> for $reg {
+                fn get(block: &$name) -> $ty {
+                    block.$reg
+                }
+
+                fn set(block: &mut $name, value: $ty) {
+                    block.$reg = value;
+                }
+            }
+
+            $crate::mmio_regs!(@access $access $reg);
+        )*
+    };
+}
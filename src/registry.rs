@@ -0,0 +1,283 @@
+//! Registry of the implementations of API functions, with which API functions can be looked up at runtime.
+//!
+//! Every [`api_mod_impl`](crate::api_mod_impl) places an [`ApiFnEntry`] for each API function it implements in the
+//! `axvisor_api_fns` link section. The entries are collected by the linker, and enumerated with the
+//! `__start_axvisor_api_fns` and `__stop_axvisor_api_fns` symbols it provides, so a missing implementation is detected
+//! at runtime instead of failing the link. This relies on the ELF linker behavior, which all supported targets share.
+//!
+//! With the `dynamic` feature, entries can also be registered at runtime with `register`, e.g. by implementations in
+//! separately linked objects, or by tests swapping implementations. Runtime entries take precedence over the link-time
+//! ones, and all API functions are dispatched through the registry instead of the link-time symbols of
+//! [`crate_interface`]. The implementation found for each API function is cached in an [`ApiFnCache`], so calls only
+//! scan the registry again after it changes.
+//!
+//! With the `std` feature, the thread-local overrides of [`testing`](crate::testing) take precedence over both.
+
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// An entry in the registry, describing the implementation of an API function.
+#[derive(Debug)]
+#[repr(C)]
+pub struct ApiFnEntry {
+    /// Name of the API trait of the API module, which is unique in the final binary.
+    pub api: &'static str,
+    /// Name of the API function, empty for the entries only keeping the pointed function linked.
+    pub function: &'static str,
+    /// Pointer to the implementation, of the function pointer type matching the signature of the API function.
+    pub ptr: *const (),
+    /// Path of the implementing module.
+    pub module: &'static str,
+    /// Order of the implementation among the ones of a `multi` API module, see [`for_each`].
+    pub order: i32,
+}
+
+// SAFETY: the entries are immutable, and the pointers are function pointers.
+unsafe impl Sync for ApiFnEntry {}
+
+/// Placeholder entry keeping the section non-empty, so that its boundary symbols are always defined.
+#[used]
+#[unsafe(link_section = "axvisor_api_fns")]
+static PLACEHOLDER: ApiFnEntry = ApiFnEntry {
+    api: "",
+    function: "",
+    ptr: core::ptr::null(),
+    module: "",
+    order: 0,
+};
+
+unsafe extern "Rust" {
+    static __start_axvisor_api_fns: ApiFnEntry;
+    static __stop_axvisor_api_fns: ApiFnEntry;
+}
+
+/// Get all entries in the registry, including the placeholder.
+fn entries() -> &'static [ApiFnEntry] {
+    // Make sure the placeholder is linked.
+    core::hint::black_box(&PLACEHOLDER);
+
+    // SAFETY: the section only contains `ApiFnEntry`s, which are laid out contiguously as their size is a multiple of
+    // their alignment.
+    unsafe {
+        let start = &raw const __start_axvisor_api_fns;
+        let stop = &raw const __stop_axvisor_api_fns;
+        core::slice::from_raw_parts(start, stop.offset_from(start) as usize)
+    }
+}
+
+/// Look up the implementation of an API function, returning `None` if it is not implemented in the final binary.
+///
+/// `api` is the name of the API trait of the API module, e.g. `AxvisorMemoryApiTrait`. The returned pointer should be
+/// cast to the function pointer type matching the signature of the API function.
+pub fn lookup(api: &str, function: &str) -> Option<*const ()> {
+    #[cfg(feature = "std")]
+    if let Some(ptr) = crate::testing::overridden(api, function) {
+        return Some(ptr);
+    }
+
+    lookup_uncached(api, function)
+}
+
+/// Look up the implementation of an API function in the runtime and link-time entries, scanning them.
+fn lookup_uncached(api: &str, function: &str) -> Option<*const ()> {
+    #[cfg(feature = "dynamic")]
+    if let Some(entry) = dynamic::registered(api, function) {
+        return Some(entry.ptr);
+    }
+
+    entries()
+        .iter()
+        .find(|entry| !entry.ptr.is_null() && entry.api == api && entry.function == function)
+        .map(|entry| entry.ptr)
+}
+
+/// Get the generation of the registry, which changes every time an entry is registered or unregistered at runtime.
+fn generation() -> usize {
+    #[cfg(feature = "dynamic")]
+    return dynamic::GENERATION.load(Ordering::Acquire);
+    #[cfg(not(feature = "dynamic"))]
+    0
+}
+
+/// Cache of the implementation of an API function, kept by the `_checked` variant of each API function so that calls
+/// do not scan the registry.
+///
+/// The cache is tagged with the generation of the registry it is filled in, so it is invalidated when an entry is
+/// registered or unregistered at runtime. Only one thread fills it at a time, the others look the implementation up
+/// without caching it.
+#[derive(Debug)]
+pub struct ApiFnCache {
+    /// Generation of the cached pointer, or `EMPTY` or `UPDATING`.
+    generation: AtomicUsize,
+    /// The cached pointer, null if the API function is not implemented.
+    ptr: AtomicPtr<()>,
+}
+
+impl ApiFnCache {
+    /// Generation of an empty cache.
+    const EMPTY: usize = usize::MAX - 1;
+    /// Generation of a cache being filled.
+    const UPDATING: usize = usize::MAX;
+
+    /// Create an empty cache.
+    pub const fn new() -> Self {
+        Self {
+            generation: AtomicUsize::new(Self::EMPTY),
+            ptr: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Look up the implementation of an API function like [`lookup`], through the cache.
+    pub fn lookup(&self, api: &str, function: &str) -> Option<*const ()> {
+        // Thread-local overrides are never cached.
+        #[cfg(feature = "std")]
+        if let Some(ptr) = crate::testing::overridden(api, function) {
+            return Some(ptr);
+        }
+
+        let generation = generation();
+        if self.generation.load(Ordering::Acquire) == generation {
+            let ptr = self.ptr.load(Ordering::Acquire);
+            // The pointer is of this generation if the cache has not been refilled meanwhile.
+            if self.generation.load(Ordering::Acquire) == generation {
+                return (!ptr.is_null()).then_some(ptr as *const ());
+            }
+        }
+
+        let ptr = lookup_uncached(api, function);
+        let cached = self.generation.load(Ordering::Relaxed);
+        if cached != Self::UPDATING
+            && self
+                .generation
+                .compare_exchange(cached, Self::UPDATING, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        {
+            let ptr = ptr.unwrap_or(core::ptr::null()) as *mut ();
+            self.ptr.store(ptr, Ordering::Release);
+            self.generation.store(generation, Ordering::Release);
+        }
+        ptr
+    }
+}
+
+impl Default for ApiFnCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Call `f` with all implementations of an API function of a `multi` API module, returning the number of them.
+///
+/// The implementations are visited in ascending order of their `order`, and then of the paths of the implementing
+/// modules, so the order does not depend on the link order. Implementations registered at runtime are not visited.
+pub fn for_each(api: &str, function: &str, mut f: impl FnMut(*const ())) -> usize {
+    let key = |entry: &ApiFnEntry| (entry.order, entry.module);
+    let matches = |entry: &&ApiFnEntry| {
+        !entry.ptr.is_null() && entry.api == api && entry.function == function
+    };
+
+    // There are only a few implementations of each API function, so a selection is cheap enough, and does not need
+    // any allocation.
+    let mut count = 0;
+    let mut last = None;
+    while let Some(next) = entries()
+        .iter()
+        .filter(matches)
+        .filter(|entry| last.is_none_or(|last| key(entry) > last))
+        .min_by_key(|entry| key(entry))
+    {
+        f(next.ptr);
+        count += 1;
+        last = Some(key(next));
+    }
+    count
+}
+
+#[cfg(feature = "dynamic")]
+pub use dynamic::{register, registered, unregister};
+
+#[cfg(feature = "dynamic")]
+mod dynamic {
+    use super::ApiFnEntry;
+    use crate::error::{AxvisorError, AxvisorResult};
+    use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+    /// Maximum number of entries registered at runtime.
+    const CAPACITY: usize = 1024;
+
+    /// Entries registered at runtime, null for free slots.
+    static TABLE: [AtomicPtr<ApiFnEntry>; CAPACITY] =
+        [const { AtomicPtr::new(core::ptr::null_mut()) }; CAPACITY];
+
+    /// Generation of the registry, bumped after every change of the table.
+    pub(super) static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+    /// Lock serializing the changes of the table, so that an API function is never registered in two slots. Lookups
+    /// do not take the lock.
+    static LOCK: AtomicBool = AtomicBool::new(false);
+
+    /// Change the table with the lock held, bumping the generation afterwards.
+    fn change<R>(f: impl FnOnce() -> R) -> R {
+        while LOCK
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let result = f();
+        GENERATION.fetch_add(1, Ordering::Release);
+        LOCK.store(false, Ordering::Release);
+        result
+    }
+
+    /// Find the slot holding the entry of an API function.
+    fn find_slot(
+        api: &str,
+        function: &str,
+    ) -> Option<(&'static AtomicPtr<ApiFnEntry>, &'static ApiFnEntry)> {
+        TABLE.iter().find_map(|slot| {
+            // SAFETY: only `&'static ApiFnEntry`s are stored in the table.
+            let entry = unsafe { slot.load(Ordering::Acquire).as_ref()? };
+            (entry.api == api && entry.function == function).then_some((slot, entry))
+        })
+    }
+
+    /// Get the entry of an API function registered at runtime, if any.
+    pub fn registered(api: &str, function: &str) -> Option<&'static ApiFnEntry> {
+        find_slot(api, function).map(|(_, entry)| entry)
+    }
+
+    /// Register the implementation of an API function at runtime, replacing the one registered before, if any.
+    ///
+    /// Fails with [`NoMemory`](AxvisorError::NoMemory) if the registry is full, or with
+    /// [`InvalidArg`](AxvisorError::InvalidArg) if the pointer in the entry is null.
+    pub fn register(entry: &'static ApiFnEntry) -> AxvisorResult {
+        if entry.ptr.is_null() {
+            return Err(AxvisorError::InvalidArg);
+        }
+
+        let new = entry as *const ApiFnEntry as *mut ApiFnEntry;
+        change(|| {
+            let slot = match find_slot(entry.api, entry.function) {
+                Some((slot, _)) => slot,
+                None => TABLE
+                    .iter()
+                    .find(|slot| slot.load(Ordering::Relaxed).is_null())
+                    .ok_or(AxvisorError::NoMemory)?,
+            };
+            slot.store(new, Ordering::Release);
+            Ok(())
+        })
+    }
+
+    /// Unregister the implementation of an API function registered at runtime, falling back to the link-time one.
+    ///
+    /// Fails with [`NotFound`](AxvisorError::NotFound) if no implementation of the API function is registered at
+    /// runtime.
+    pub fn unregister(api: &str, function: &str) -> AxvisorResult {
+        change(|| {
+            let (slot, _) = find_slot(api, function).ok_or(AxvisorError::NotFound)?;
+            slot.store(core::ptr::null_mut(), Ordering::Release);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,155 @@
+//! Lock-free ring buffers, suitable for handing data off from interrupt handlers to tasks.
+
+extern crate alloc;
+use alloc::{boxed::Box, sync::Arc};
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A lock-free, bounded, single-producer/single-consumer ring buffer.
+///
+/// The storage is allocated from the heap once at creation, pushing and popping never allocate, block, or take a
+/// lock, so the producer side can be used in interrupt context. Use [`SpscRing::split`] to get the [`Producer`] and
+/// the [`Consumer`] handles, which can be moved to different contexts independently.
+pub struct SpscRing<T> {
+    /// Storage of the elements.
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// Index of the next element to be popped. Only written by the consumer.
+    head: AtomicUsize,
+    /// Index of the next slot to be pushed to. Only written by the producer.
+    tail: AtomicUsize,
+}
+
+// SAFETY: the producer and the consumer never access the same slot at the same time, and elements are moved between
+// them, so the ring is safe to share as long as `T` can be sent.
+unsafe impl<T: Send> Sync for SpscRing<T> {}
+unsafe impl<T: Send> Send for SpscRing<T> {}
+
+impl<T> SpscRing<T> {
+    /// Create a ring buffer which can hold up to `capacity` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity of a ring buffer must be non-zero");
+        Self {
+            slots: (0..capacity)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Split the ring buffer into the producer and the consumer handles.
+    pub fn split(self) -> (Producer<T>, Consumer<T>) {
+        let ring = Arc::new(self);
+        (Producer { ring: ring.clone() }, Consumer { ring })
+    }
+
+    /// Get the maximum number of elements the ring buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Get the number of elements in the ring buffer.
+    ///
+    /// The value may be outdated as soon as it is returned if the other side is active.
+    pub fn len(&self) -> usize {
+        // `head` never passes `tail`, so load `head` first to avoid underflow.
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(head).min(self.capacity())
+    }
+
+    /// Check whether the ring buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check whether the ring buffer is full.
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.capacity()
+    }
+
+    /// Get the slot for an index.
+    fn slot(&self, index: usize) -> &UnsafeCell<MaybeUninit<T>> {
+        &self.slots[index % self.slots.len()]
+    }
+
+    /// Push an element. Must only be called by the single producer.
+    fn push(&self, value: T) -> Result<(), T> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= self.capacity() {
+            return Err(value);
+        }
+
+        // SAFETY: the slot is not visible to the consumer until `tail` is published below.
+        unsafe { (*self.slot(tail).get()).write(value) };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Pop an element. Must only be called by the single consumer.
+    fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+
+        // SAFETY: the slot has been initialized by the producer, and it will not be reused by the producer until
+        // `head` is published below.
+        let value = unsafe { (*self.slot(head).get()).assume_init_read() };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+}
+
+impl<T> Drop for SpscRing<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+/// The producer handle of a [`SpscRing`].
+pub struct Producer<T> {
+    ring: Arc<SpscRing<T>>,
+}
+
+impl<T> Producer<T> {
+    /// Push an element into the ring buffer.
+    ///
+    /// Returns the element back if the ring buffer is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        self.ring.push(value)
+    }
+
+    /// Get the underlying ring buffer.
+    pub fn ring(&self) -> &SpscRing<T> {
+        &self.ring
+    }
+}
+
+/// The consumer handle of a [`SpscRing`].
+pub struct Consumer<T> {
+    ring: Arc<SpscRing<T>>,
+}
+
+impl<T> Consumer<T> {
+    /// Pop an element from the ring buffer.
+    ///
+    /// Returns `None` if the ring buffer is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        self.ring.pop()
+    }
+
+    /// Get the underlying ring buffer.
+    pub fn ring(&self) -> &SpscRing<T> {
+        &self.ring
+    }
+}
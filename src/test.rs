@@ -3,12 +3,24 @@ use memory_addr::{pa, va};
 /// A demonstration of the `memory` API implementation.
 #[crate::api_mod_impl(crate::memory)]
 mod memory_impl {
-    use core::sync::atomic::AtomicUsize;
+    use crate::error::{AxvisorError, AxvisorResult};
+    extern crate std;
+    use crate::guest_memory::GuestPhysAddrRange;
+    use crate::memory::{AllocPolicy, FrameStats, LinearMap, MappingFlags, PageSize};
+    use crate::vmm::VMId;
+    use core::{panic::Location, sync::atomic::AtomicUsize};
     use memory_addr::{PhysAddr, VirtAddr, pa, va};
+    use std::collections::{BTreeMap, btree_map::Entry};
+    use std::sync::Mutex;
 
     static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
     static RETURNED_SUM: AtomicUsize = AtomicUsize::new(0);
     pub const VA_PA_OFFSET: usize = 0x1000;
+    pub const LINEAR_MAP: LinearMap = LinearMap {
+        phys_start: pa!(0),
+        virt_start: va!(VA_PA_OFFSET),
+        size: 0x10_0000,
+    };
 
     extern fn alloc_frame() -> Option<PhysAddr> {
         let value = ALLOCATED.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
@@ -16,10 +28,13 @@ mod memory_impl {
         Some(pa!(value * 0x1000))
     }
 
-    extern fn alloc_contiguous_frames(
-        _num_frames: usize,
+    extern fn try_alloc_contiguous_frames(
+        num_frames: usize,
         _frame_align_pow2: usize,
-    ) -> Option<PhysAddr> {
+    ) -> AxvisorResult<PhysAddr> {
+        if num_frames == 0 {
+            return Err(AxvisorError::InvalidArg);
+        }
         unimplemented!();
     }
 
@@ -31,6 +46,36 @@ mod memory_impl {
         unimplemented!();
     }
 
+    extern fn set_debug_fill(_fill: Option<u8>) {
+        unimplemented!();
+    }
+
+    /// Base address of the huge frames, each of which is given a 1G-aligned slot regardless of its size.
+    const HUGE_BASE: usize = 0x40_0000_0000;
+    static HUGE_NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    extern fn alloc_huge_frame(size: PageSize) -> AxvisorResult<PhysAddr> {
+        if !size.is_huge() {
+            return Err(AxvisorError::InvalidArg);
+        }
+        let index = HUGE_NEXT.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
+        Ok(pa!(HUGE_BASE + index * PageSize::Size1G.size()))
+    }
+
+    extern fn dealloc_huge_frame(_addr: PhysAddr, _size: PageSize) {}
+
+    extern fn alloc_frame_with_policy(_policy: AllocPolicy) -> AxvisorResult<PhysAddr> {
+        unimplemented!();
+    }
+
+    extern fn alloc_contiguous_frames_with_policy(
+        _num_frames: usize,
+        _frame_align_pow2: usize,
+        _policy: AllocPolicy,
+    ) -> AxvisorResult<PhysAddr> {
+        unimplemented!();
+    }
+
     /// Get the sum of all returned physical addresses.
     ///
     /// Note that this function demonstrates that non-API functions work well in a module with the `api_mod_impl` attribute.
@@ -43,12 +88,144 @@ mod memory_impl {
         RETURNED_SUM.store(0, core::sync::atomic::Ordering::SeqCst);
     }
 
-    extern fn phys_to_virt(addr: PhysAddr) -> VirtAddr {
-        va!(addr.as_usize() + VA_PA_OFFSET) // Example implementation
+    extern fn phys_to_virt(addr: PhysAddr) -> Option<VirtAddr> {
+        LINEAR_MAP.phys_to_virt(addr) // Example implementation
+    }
+
+    extern fn virt_to_phys(addr: VirtAddr) -> Option<PhysAddr> {
+        LINEAR_MAP.virt_to_phys(addr) // Example implementation
+    }
+
+    extern fn linear_map_region(index: usize) -> Option<LinearMap> {
+        (index == 0).then_some(LINEAR_MAP)
+    }
+
+    /// Base address of the frames allocated in batches, apart from the ones of `alloc_frame`.
+    const BATCH_BASE: usize = 0x1000_0000;
+    /// Number of frames available for batch allocation.
+    pub const BATCH_FRAMES: usize = 0x100;
+    static BATCH_NEXT: AtomicUsize = AtomicUsize::new(0);
+    static BATCH_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+    extern fn alloc_frames(frames: &mut [PhysAddr]) -> AxvisorResult {
+        let allocated = BATCH_ALLOCATED.load(core::sync::atomic::Ordering::SeqCst);
+        if allocated + frames.len() > BATCH_FRAMES {
+            return Err(AxvisorError::NoMemory);
+        }
+        BATCH_ALLOCATED.fetch_add(frames.len(), core::sync::atomic::Ordering::SeqCst);
+        for frame in frames {
+            let index = BATCH_NEXT.fetch_add(1, core::sync::atomic::Ordering::SeqCst);
+            *frame = pa!(BATCH_BASE + index * 0x1000);
+        }
+        Ok(())
+    }
+
+    extern fn dealloc_frames(frames: &[PhysAddr]) {
+        BATCH_ALLOCATED.fetch_sub(frames.len(), core::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Statistics of the frames allocated in batches.
+    extern fn frame_stats() -> FrameStats {
+        let allocated = BATCH_ALLOCATED.load(core::sync::atomic::Ordering::SeqCst);
+        FrameStats {
+            total: BATCH_FRAMES,
+            free: BATCH_FRAMES - allocated,
+            allocated,
+        }
+    }
+
+    extern fn alloc_dma(_size: usize, _align: usize) -> AxvisorResult<(PhysAddr, VirtAddr)> {
+        unimplemented!();
+    }
+
+    extern fn dealloc_dma(_paddr: PhysAddr, _vaddr: VirtAddr, _size: usize) {
+        unimplemented!();
+    }
+
+    extern fn flush_dcache_range(_vaddr: VirtAddr, _size: usize) {}
+
+    extern fn invalidate_dcache_range(_vaddr: VirtAddr, _size: usize) {}
+
+    extern fn clean_invalidate_dcache(_vaddr: VirtAddr, _size: usize) {}
+
+    extern fn invalidate_icache(_vaddr: VirtAddr, _size: usize) {}
+
+    extern fn flush_tlb_all() {}
+
+    extern fn flush_tlb_va(_vaddr: VirtAddr) {}
+
+    extern fn flush_guest_tlb(
+        _vm_id: crate::vmm::VMId,
+        _gpa_range: crate::guest_memory::GuestPhysAddrRange,
+    ) {
+    }
+
+    extern fn map_device(_mmio_pa: PhysAddr, _size: usize) -> AxvisorResult<VirtAddr> {
+        unimplemented!();
+    }
+
+    extern fn unmap_device(_va: VirtAddr, _size: usize) {
+        unimplemented!();
+    }
+
+    extern fn apply_hypervisor_range_protection(
+        _va: VirtAddr,
+        _len: usize,
+        _perms: MappingFlags,
+        _caller: &'static Location<'static>,
+    ) -> AxvisorResult {
+        Ok(())
     }
 
-    extern fn virt_to_phys(addr: VirtAddr) -> PhysAddr {
-        pa!(addr.as_usize() - VA_PA_OFFSET) // Example implementation
+    /// Dirty logs of the virtual machines with the logging enabled, covering the first 64 pages of guest memory.
+    static DIRTY_LOGS: Mutex<BTreeMap<VMId, u64>> = Mutex::new(BTreeMap::new());
+
+    /// Log a write to a page of the guest memory of a virtual machine, as if the guest wrote it.
+    pub fn mark_dirty(vm_id: VMId, page: usize) {
+        if let Some(log) = DIRTY_LOGS.lock().unwrap().get_mut(&vm_id) {
+            *log |= 1 << page;
+        }
+    }
+
+    extern fn enable_dirty_log(vm_id: VMId) -> AxvisorResult {
+        match DIRTY_LOGS.lock().unwrap().entry(vm_id) {
+            Entry::Occupied(_) => Err(AxvisorError::BadState),
+            Entry::Vacant(entry) => {
+                entry.insert(0);
+                Ok(())
+            }
+        }
+    }
+
+    extern fn disable_dirty_log(vm_id: VMId) -> AxvisorResult {
+        let log = DIRTY_LOGS.lock().unwrap().remove(&vm_id);
+        log.map(|_| ()).ok_or(AxvisorError::BadState)
+    }
+
+    extern fn fetch_and_clear_dirty_bitmap(
+        vm_id: VMId,
+        gpa_range: GuestPhysAddrRange,
+        bitmap: &mut [u64],
+    ) -> AxvisorResult<usize> {
+        let (start, end) = (gpa_range.start.as_usize(), gpa_range.end.as_usize());
+        let pages = (end - start) / 0x1000;
+        if start % 0x1000 != 0 || end % 0x1000 != 0 || bitmap.len() * 64 < pages {
+            return Err(AxvisorError::InvalidArg);
+        }
+        let mut logs = DIRTY_LOGS.lock().unwrap();
+        let log = logs.get_mut(&vm_id).ok_or(AxvisorError::BadState)?;
+
+        bitmap.fill(0);
+        let mut count = 0;
+        for i in 0..pages {
+            let page = start / 0x1000 + i;
+            if page < 64 && *log & (1 << page) != 0 {
+                *log &= !(1 << page);
+                bitmap[i / 64] |= 1 << (i % 64);
+                count += 1;
+            }
+        }
+        Ok(count)
     }
 }
 
@@ -73,8 +250,50 @@ pub fn test_memory() {
     memory::dealloc_frame(frame1.unwrap());
     assert_eq!(memory_impl::get_returned_sum(), 0x3000);
 
-    assert_eq!(memory::phys_to_virt(pa!(0)), va!(memory_impl::VA_PA_OFFSET));
-    assert_eq!(memory::virt_to_phys(va!(memory_impl::VA_PA_OFFSET)), pa!(0));
+    assert_eq!(
+        memory::phys_to_virt(pa!(0)),
+        Some(va!(memory_impl::VA_PA_OFFSET))
+    );
+    assert_eq!(
+        memory::virt_to_phys(va!(memory_impl::VA_PA_OFFSET)),
+        Some(pa!(0))
+    );
+    assert_eq!(memory::phys_to_virt(pa!(0x10_0000)), None);
+    assert_eq!(memory::virt_to_phys(va!(0)), None);
+    assert_eq!(memory::linear_map_regions().count(), 1);
+
+    assert_eq!(
+        memory::try_alloc_contiguous_frames(0, 0),
+        Err(crate::error::AxvisorError::InvalidArg)
+    );
+    assert_eq!(memory::alloc_contiguous_frames(0, 0), None);
+}
+
+#[test]
+pub fn test_memory_protect_hypervisor_range() {
+    use crate::{
+        error::AxvisorError,
+        memory::{self, MappingFlags},
+    };
+
+    let rw = MappingFlags::READ | MappingFlags::WRITE;
+    let rx = MappingFlags::READ | MappingFlags::EXECUTE;
+    assert_eq!(
+        memory::protect_hypervisor_range(va!(0x1000), 0x2000, rw),
+        Ok(())
+    );
+    assert_eq!(
+        memory::protect_hypervisor_range(va!(0x1000), 0x2000, rx),
+        Ok(())
+    );
+    assert_eq!(
+        memory::protect_hypervisor_range(va!(0x1000), 0x2000, rw | rx),
+        Err(AxvisorError::PermissionDenied)
+    );
+    assert_eq!(
+        memory::protect_hypervisor_range(va!(0x1800), 0x1000, rx),
+        Err(AxvisorError::InvalidArg)
+    );
 }
 
 #[test]
@@ -100,3 +319,1713 @@ pub fn test_memory_phys_frame() {
     drop(frame1);
     assert_eq!(memory_impl::get_returned_sum(), 0x6000);
 }
+
+#[test]
+pub fn test_memory_frame_pool() {
+    use crate::{
+        error::AxvisorError,
+        memory::{self, FramePool},
+    };
+
+    let mut pool = FramePool::new(4).unwrap();
+    assert_eq!(pool.len(), 4);
+    assert_eq!(memory::frame_stats().allocated, 4);
+
+    let kept = pool.take().unwrap();
+    let returned = pool.take().unwrap();
+    assert_ne!(kept, returned);
+    pool.put(returned);
+    assert_eq!(pool.len(), 3);
+
+    // Only the frames remaining in the pool are deallocated when it is dropped.
+    drop(pool);
+    assert_eq!(memory::frame_stats().allocated, 1);
+    memory::dealloc_frames(&[kept]);
+    assert_eq!(memory::frame_stats().allocated, 0);
+
+    assert_eq!(
+        FramePool::new(memory_impl::BATCH_FRAMES + 1).err(),
+        Some(AxvisorError::NoMemory)
+    );
+    assert_eq!(memory::frame_stats().free, memory_impl::BATCH_FRAMES);
+}
+
+#[test]
+pub fn test_memory_huge_frame() {
+    use crate::{
+        error::AxvisorError,
+        guest_memory::{self, GuestPhysAddr},
+        memory::{self, MappingFlags, PageSize},
+        vmm::VMId,
+    };
+
+    let vm = VMId::from(0usize);
+    let frame = memory::alloc_huge_frame(PageSize::Size2M).unwrap();
+    assert_eq!(frame.as_usize() % PageSize::Size2M.size(), 0);
+    assert_eq!(
+        memory::alloc_huge_frame(PageSize::Size4K),
+        Err(AxvisorError::InvalidArg)
+    );
+
+    let gpa = GuestPhysAddr::from_usize(0x20_0000);
+    let rw = MappingFlags::READ | MappingFlags::WRITE;
+    let size = PageSize::Size2M.size();
+    assert_eq!(
+        guest_memory::map_guest_range(vm, gpa, frame, size, rw, PageSize::Size2M),
+        Ok(())
+    );
+    assert_eq!(
+        guest_memory::map_guest_range(vm, gpa + 0x1000, frame, size, rw, PageSize::Size2M),
+        Err(AxvisorError::InvalidArg)
+    );
+    assert_eq!(guest_memory::unmap_guest_range(vm, gpa, size), Ok(()));
+    memory::dealloc_huge_frame(frame, PageSize::Size2M);
+}
+
+#[test]
+pub fn test_memory_dirty_log() {
+    use crate::{
+        error::AxvisorError,
+        guest_memory::{GuestPhysAddr, GuestPhysAddrRange},
+        memory,
+        vmm::VMId,
+    };
+
+    let vm = VMId::from(3usize);
+    let range = |start, end| {
+        GuestPhysAddrRange::new(
+            GuestPhysAddr::from_usize(start),
+            GuestPhysAddr::from_usize(end),
+        )
+    };
+    let mut bitmap = [0u64; 1];
+
+    assert_eq!(
+        memory::fetch_and_clear_dirty_bitmap(vm, range(0, 0x4000), &mut bitmap),
+        Err(AxvisorError::BadState)
+    );
+    assert_eq!(memory::enable_dirty_log(vm), Ok(()));
+    assert_eq!(memory::enable_dirty_log(vm), Err(AxvisorError::BadState));
+
+    memory_impl::mark_dirty(vm, 1);
+    memory_impl::mark_dirty(vm, 3);
+    memory_impl::mark_dirty(vm, 5);
+    assert_eq!(
+        memory::fetch_and_clear_dirty_bitmap(vm, range(0x1000, 0x5000), &mut bitmap),
+        Ok(2)
+    );
+    assert_eq!(bitmap, [0b101]);
+    // Written pages are cleared once fetched, and the ones out of the range are kept.
+    assert_eq!(
+        memory::fetch_and_clear_dirty_bitmap(vm, range(0, 0x8000), &mut bitmap),
+        Ok(1)
+    );
+    assert_eq!(bitmap, [1 << 5]);
+    assert_eq!(
+        memory::fetch_and_clear_dirty_bitmap(vm, range(0x800, 0x1000), &mut bitmap),
+        Err(AxvisorError::InvalidArg)
+    );
+    assert_eq!(
+        memory::fetch_and_clear_dirty_bitmap(vm, range(0, 0x41000), &mut bitmap),
+        Err(AxvisorError::InvalidArg)
+    );
+
+    assert_eq!(memory::disable_dirty_log(vm), Ok(()));
+    assert_eq!(memory::disable_dirty_log(vm), Err(AxvisorError::BadState));
+}
+
+/// A demonstration of the `guest_memory` API implementation, backed by a small byte array.
+#[crate::api_mod_impl(crate::guest_memory)]
+mod guest_memory_impl {
+    use crate::{guest_memory::GuestPhysAddr, vmm::VMId};
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    pub const GUEST_MEMORY_SIZE: usize = 0x40;
+    static GUEST_MEMORY: [AtomicU8; GUEST_MEMORY_SIZE] =
+        [const { AtomicU8::new(0) }; GUEST_MEMORY_SIZE];
+
+    extern fn read_guest_memory(_vm_id: VMId, gpa: GuestPhysAddr, buf: &mut [u8]) -> usize {
+        let mut count = 0;
+        for (byte, cell) in buf.iter_mut().zip(GUEST_MEMORY.iter().skip(gpa.as_usize())) {
+            *byte = cell.load(Ordering::SeqCst);
+            count += 1;
+        }
+        count
+    }
+
+    extern fn write_guest_memory(_vm_id: VMId, gpa: GuestPhysAddr, buf: &[u8]) -> usize {
+        let mut count = 0;
+        for (byte, cell) in buf.iter().zip(GUEST_MEMORY.iter().skip(gpa.as_usize())) {
+            cell.store(*byte, Ordering::SeqCst);
+            count += 1;
+        }
+        count
+    }
+
+    extern fn gpa_to_hpa(_vm_id: VMId, _gpa: GuestPhysAddr) -> Option<crate::memory::HostPhysAddr> {
+        None
+    }
+
+    extern fn reclaim_guest_pages(
+        _vm_id: VMId,
+        _gpa_list: &[GuestPhysAddr],
+    ) -> crate::error::AxvisorResult {
+        unimplemented!();
+    }
+
+    extern fn restore_guest_pages(
+        _vm_id: VMId,
+        _gpa_list: &[GuestPhysAddr],
+    ) -> crate::error::AxvisorResult {
+        unimplemented!();
+    }
+
+    extern fn vm_memory_usage(_vm_id: VMId) -> Option<crate::guest_memory::MemUsage> {
+        unimplemented!();
+    }
+
+    extern fn share_page(
+        _vm_a: VMId,
+        _gpa_a: GuestPhysAddr,
+        _vm_b: VMId,
+        _gpa_b: GuestPhysAddr,
+    ) -> crate::error::AxvisorResult {
+        unimplemented!();
+    }
+
+    extern fn mark_cow(
+        _vm_id: VMId,
+        _gpa_range: crate::guest_memory::GuestPhysAddrRange,
+    ) -> crate::error::AxvisorResult {
+        unimplemented!();
+    }
+
+    extern fn set_cow_break_handler(_handler: Option<crate::guest_memory::CowBreakHandler>) {
+        unimplemented!();
+    }
+
+    extern fn populate_guest_range(
+        _vm_id: VMId,
+        gpa: GuestPhysAddr,
+        len: usize,
+    ) -> crate::error::AxvisorResult {
+        match gpa.as_usize().checked_add(len) {
+            Some(end) if end <= GUEST_MEMORY_SIZE => Ok(()),
+            _ => Err(crate::error::AxvisorError::BadAddress),
+        }
+    }
+
+    /// Guest memory is fixed, so mapping only checks the alignment.
+    extern fn map_guest_range(
+        _vm_id: VMId,
+        gpa: GuestPhysAddr,
+        hpa: crate::memory::HostPhysAddr,
+        size: usize,
+        _flags: crate::memory::MappingFlags,
+        page_size: crate::memory::PageSize,
+    ) -> crate::error::AxvisorResult {
+        let align = page_size.size();
+        if gpa.as_usize() % align != 0 || hpa.as_usize() % align != 0 || size % align != 0 {
+            return Err(crate::error::AxvisorError::InvalidArg);
+        }
+        Ok(())
+    }
+
+    extern fn unmap_guest_range(
+        _vm_id: VMId,
+        gpa: GuestPhysAddr,
+        size: usize,
+    ) -> crate::error::AxvisorResult {
+        if gpa.as_usize() % 0x1000 != 0 || size % 0x1000 != 0 {
+            return Err(crate::error::AxvisorError::InvalidArg);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_guest_memory_endianness() {
+    use crate::{
+        guest_memory::{self, GuestPhysAddr},
+        vmm::VMId,
+    };
+
+    let vm = VMId::from(0usize);
+    let gpa = GuestPhysAddr::from_usize(0x10);
+
+    assert!(guest_memory::guest_write_le(vm, gpa, 0x1234_5678u32));
+    assert_eq!(
+        guest_memory::guest_read_le::<u32>(vm, gpa),
+        Some(0x1234_5678)
+    );
+    assert_eq!(
+        guest_memory::guest_read_be::<u32>(vm, gpa),
+        Some(0x7856_3412)
+    );
+    assert_eq!(guest_memory::guest_read_le::<u8>(vm, gpa), Some(0x78));
+
+    assert!(guest_memory::guest_write_be(vm, gpa, 0xabcdu16));
+    assert_eq!(guest_memory::guest_read_le::<u16>(vm, gpa), Some(0xcdab));
+
+    // out of range
+    let last = GuestPhysAddr::from_usize(guest_memory_impl::GUEST_MEMORY_SIZE - 2);
+    assert_eq!(guest_memory::guest_read_le::<u32>(vm, last), None);
+    assert!(!guest_memory::guest_write_le(vm, last, 0u64));
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(C, packed)]
+    struct Header {
+        magic: u16,
+        len: u32,
+    }
+
+    let header = Header {
+        magic: 0x5a5a,
+        len: 0x100,
+    };
+    assert!(unsafe { guest_memory::guest_write_struct(vm, gpa, &header) });
+    assert_eq!(
+        unsafe { guest_memory::guest_read_struct::<Header>(vm, gpa) },
+        Some(header)
+    );
+}
+
+#[test]
+pub fn test_spsc_ring() {
+    use crate::ring::SpscRing;
+
+    let (mut producer, mut consumer) = SpscRing::new(3).split();
+    assert!(producer.ring().is_empty());
+    assert_eq!(consumer.pop(), None);
+
+    for round in 0..4 {
+        assert_eq!(producer.push(round), Ok(()));
+        assert_eq!(producer.push(round + 1), Ok(()));
+        assert_eq!(producer.push(round + 2), Ok(()));
+        assert_eq!(producer.push(round + 3), Err(round + 3));
+        assert!(consumer.ring().is_full());
+
+        assert_eq!(consumer.pop(), Some(round));
+        assert_eq!(consumer.pop(), Some(round + 1));
+        assert_eq!(consumer.pop(), Some(round + 2));
+        assert_eq!(consumer.pop(), None);
+    }
+}
+
+#[test]
+pub fn test_spsc_ring_drop() {
+    extern crate alloc;
+    use crate::ring::SpscRing;
+    use alloc::sync::Arc;
+
+    let value = Arc::new(());
+    let (mut producer, consumer) = SpscRing::new(4).split();
+    producer.push(value.clone()).unwrap();
+    producer.push(value.clone()).unwrap();
+    assert_eq!(Arc::strong_count(&value), 3);
+
+    drop(producer);
+    drop(consumer);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+#[test]
+pub fn test_spsc_ring_threads() {
+    extern crate std;
+    use crate::ring::SpscRing;
+
+    const COUNT: usize = 10000;
+    let (mut producer, mut consumer) = SpscRing::new(16).split();
+
+    let handle = std::thread::spawn(move || {
+        for i in 0..COUNT {
+            let mut value = i;
+            while let Err(v) = producer.push(value) {
+                value = v;
+                std::thread::yield_now();
+            }
+        }
+    });
+
+    let mut expected = 0;
+    while expected < COUNT {
+        match consumer.pop() {
+            Some(value) => {
+                assert_eq!(value, expected);
+                expected += 1;
+            }
+            None => std::thread::yield_now(),
+        }
+    }
+    handle.join().unwrap();
+}
+
+mod mmio_demo {
+    crate::mmio_regs! {
+        /// Registers of a demo device.
+        pub struct DemoRegs {
+            0x00 => CTRL: u32 rw,
+            0x04 => STATUS: u8 ro = 0x80,
+            0x08 => DATA: u64 wo,
+            0x10 => ID: u16 ro = 0xbeef,
+        }
+    }
+}
+
+#[test]
+pub fn test_mmio_regs() {
+    use crate::mmio::{MmioRegion, Register, RegisterBlock};
+    use memory_addr::VirtAddr;
+    use mmio_demo::*;
+
+    // emulation side
+    let mut regs = DemoRegs::new();
+    assert_eq!(regs.read(DemoRegs::CTRL), Some(0));
+    assert_eq!(regs.read(DemoRegs::STATUS), Some(0x80));
+    assert_eq!(regs.read(DemoRegs::DATA), None);
+    assert_eq!(regs.read(0x0c), None);
+
+    assert!(regs.write(DemoRegs::CTRL, 0x1_0000_0001));
+    assert_eq!(regs.get::<CTRL>(), 1);
+    assert!(!regs.write(DemoRegs::ID, 0));
+    assert!(regs.write(DemoRegs::DATA, u64::MAX));
+    assert_eq!(regs.get::<DATA>(), u64::MAX);
+
+    regs.set::<STATUS>(0x1);
+    regs.reset();
+    assert_eq!(regs.get::<STATUS>(), 0x80);
+    assert_eq!(regs.get::<DATA>(), 0);
+    assert_eq!(<ID as Register>::OFFSET, 0x10);
+
+    // host side
+    let mut backing = [0u64; 4];
+    let region = unsafe { MmioRegion::new(VirtAddr::from_mut_ptr_of(backing.as_mut_ptr())) };
+    region.write::<DATA>(0x1234_5678_9abc_def0);
+    region.write::<CTRL>(0x5);
+    region.modify::<CTRL>(|v| v | 0x10);
+    assert_eq!(region.read::<CTRL>(), 0x15);
+    assert_eq!(backing[1], 0x1234_5678_9abc_def0);
+}
+
+#[crate::api_mod(version = "1.2")]
+/// A demonstration of a versioned API module.
+mod versioned_demo {
+    /// Get the answer.
+    extern fn answer() -> u32;
+}
+
+#[crate::api_mod_impl(versioned_demo)]
+mod versioned_demo_impl {
+    extern fn answer() -> u32 {
+        42
+    }
+}
+
+#[test]
+pub fn test_api_client() {
+    use versioned_demo::VersionedDemoApi;
+
+    fn double_answer<A: VersionedDemoApi>() -> u32 {
+        A::answer() * 2
+    }
+
+    struct MockApi;
+    impl VersionedDemoApi for MockApi {
+        fn answer() -> u32 {
+            1
+        }
+    }
+
+    assert_eq!(double_answer::<versioned_demo::Api>(), 84);
+    assert_eq!(double_answer::<MockApi>(), 2);
+}
+
+#[test]
+pub fn test_api_version() {
+    use crate::version::ApiVersion;
+
+    assert_eq!(versioned_demo::MODULE_API_VERSION, ApiVersion::new(1, 2));
+    assert_eq!(versioned_demo::implemented_version(), ApiVersion::new(1, 2));
+    assert!(versioned_demo::check_version());
+    assert_eq!(versioned_demo::answer(), 42);
+
+    let required = ApiVersion::new(1, 2);
+    assert!(required.is_satisfied_by(ApiVersion::new(1, 2)));
+    assert!(required.is_satisfied_by(ApiVersion::new(1, 3)));
+    assert!(!required.is_satisfied_by(ApiVersion::new(1, 1)));
+    assert!(!required.is_satisfied_by(ApiVersion::new(2, 2)));
+}
+
+#[crate::api_mod]
+/// A demonstration of an API module without implementation.
+mod unimplemented_demo {
+    /// Never implemented.
+    extern fn missing(x: u32) -> u32;
+}
+
+#[test]
+pub fn test_api_checked() {
+    use crate::error::ApiNotImplemented;
+
+    assert_eq!(versioned_demo::answer_checked(), Ok(42));
+    assert_eq!(cfg_demo::always_checked(), Ok(1));
+    assert_eq!(
+        unimplemented_demo::missing_checked(1),
+        Err(ApiNotImplemented)
+    );
+}
+
+#[crate::api_mod]
+/// A demonstration of swapping implementations at runtime.
+mod dynamic_demo {
+    /// Get the name of the implementation.
+    extern fn name() -> &'static str;
+}
+
+#[crate::api_mod_impl(dynamic_demo)]
+mod dynamic_demo_impl {
+    extern fn name() -> &'static str {
+        "static"
+    }
+}
+
+#[cfg(feature = "dynamic")]
+#[test]
+pub fn test_api_dynamic() {
+    extern crate std;
+    use crate::registry::{self, ApiFnEntry};
+
+    fn mock_name() -> &'static str {
+        "mock"
+    }
+    static MOCK_ENTRY: ApiFnEntry = ApiFnEntry {
+        api: "AxvisorDynamic_demoApiTrait",
+        function: "name",
+        ptr: mock_name as *const (),
+        module: module_path!(),
+        order: 0,
+    };
+
+    assert_eq!(dynamic_demo::name(), "static");
+    registry::register(&MOCK_ENTRY).unwrap();
+    assert_eq!(dynamic_demo::name(), "mock");
+    dynamic_demo_impl::register_impl().unwrap();
+    assert_eq!(dynamic_demo::name(), "static");
+    registry::unregister("AxvisorDynamic_demoApiTrait", "name").unwrap();
+    assert_eq!(dynamic_demo::name(), "static");
+
+    // Concurrent registrations of the same function take a single slot, so one unregistration removes it.
+    let threads: std::vec::Vec<_> = (0..8)
+        .map(|_| std::thread::spawn(|| registry::register(&MOCK_ENTRY).unwrap()))
+        .collect();
+    threads
+        .into_iter()
+        .for_each(|thread| thread.join().unwrap());
+    assert_eq!(dynamic_demo::name(), "mock");
+    registry::unregister("AxvisorDynamic_demoApiTrait", "name").unwrap();
+    assert!(registry::registered("AxvisorDynamic_demoApiTrait", "name").is_none());
+    assert_eq!(dynamic_demo::name(), "static");
+}
+
+#[crate::api_mod(multi)]
+/// A demonstration of API modules with multiple implementations.
+mod multi_demo {
+    /// Notify all subscribers.
+    extern fn notify(value: u32);
+}
+
+#[crate::api_mod_impl(multi_demo, multi, order = -1)]
+mod multi_demo_impl_first {
+    extern crate std;
+    use std::{sync::Mutex, vec::Vec};
+
+    /// Notifications received by all implementations of `multi_demo`.
+    pub static NOTIFIED: Mutex<Vec<(&str, u32)>> = Mutex::new(Vec::new());
+
+    extern fn notify(value: u32) {
+        NOTIFIED.lock().unwrap().push(("first", value));
+    }
+}
+
+#[crate::api_mod_impl(multi_demo, multi)]
+mod multi_demo_impl_b {
+    extern fn notify(value: u32) {
+        super::multi_demo_impl_first::NOTIFIED
+            .lock()
+            .unwrap()
+            .push(("b", value));
+    }
+}
+
+#[crate::api_mod_impl(multi_demo, multi)]
+mod multi_demo_impl_a {
+    extern fn notify(value: u32) {
+        super::multi_demo_impl_first::NOTIFIED
+            .lock()
+            .unwrap()
+            .push(("a", value));
+    }
+}
+
+#[test]
+pub fn test_api_multi() {
+    multi_demo::notify(7);
+    assert_eq!(
+        *multi_demo_impl_first::NOTIFIED.lock().unwrap(),
+        [("first", 7), ("a", 7), ("b", 7)]
+    );
+}
+
+#[crate::api_mod]
+/// A demonstration of API modules implemented partially by more than one implementation.
+mod partial_demo {
+    /// Get the first value.
+    extern fn first() -> u32;
+    /// Get the second value.
+    extern fn second() -> u32;
+}
+
+#[crate::api_mod_impl(partial_demo, partial)]
+mod partial_demo_impl_first {
+    extern fn first() -> u32 {
+        1
+    }
+}
+
+#[crate::api_mod_impl(partial_demo, partial)]
+mod partial_demo_impl_second {
+    extern fn second() -> u32 {
+        2
+    }
+}
+
+#[test]
+pub fn test_api_partial() {
+    assert_eq!(partial_demo::first(), 1);
+    assert_eq!(partial_demo::second(), 2);
+    assert_eq!(partial_demo::second_checked(), Ok(2));
+}
+
+#[crate::api_mod]
+/// A demonstration of API constants.
+mod const_demo {
+    /// Size of a page.
+    const PAGE_SIZE: usize;
+    /// Name of the platform.
+    const PLATFORM_NAME: &'static str;
+}
+
+#[crate::api_mod_impl(const_demo)]
+mod const_demo_impl {
+    #[api]
+    const PAGE_SIZE: usize = 1 << 12;
+    #[api]
+    const PLATFORM_NAME: &'static str = "test";
+}
+
+#[test]
+pub fn test_api_const() {
+    assert_eq!(const_demo::page_size(), 4096);
+    assert_eq!(const_demo::platform_name(), "test");
+}
+
+#[crate::api_mod]
+/// A demonstration of overriding implementations in tests.
+mod override_demo {
+    /// Get the name of the implementation.
+    extern fn name() -> &'static str;
+}
+
+#[crate::api_mod_impl(override_demo)]
+mod override_demo_impl {
+    extern fn name() -> &'static str {
+        "real"
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+pub fn test_override_impl() {
+    extern crate std;
+
+    struct Mock;
+    impl override_demo::AxvisorOverride_demoApiTrait for Mock {
+        fn name() -> &'static str {
+            "mock"
+        }
+    }
+    struct NestedMock;
+    impl override_demo::AxvisorOverride_demoApiTrait for NestedMock {
+        fn name() -> &'static str {
+            "nested"
+        }
+    }
+
+    assert_eq!(override_demo::name(), "real");
+    {
+        let _guard = crate::override_impl!(override_demo, Mock);
+        assert_eq!(override_demo::name(), "mock");
+        // Overrides only apply to the thread creating them.
+        std::thread::spawn(|| assert_eq!(override_demo::name(), "real"))
+            .join()
+            .unwrap();
+        {
+            let _guard = crate::override_impl!(override_demo, NestedMock);
+            assert_eq!(override_demo::name(), "nested");
+        }
+        assert_eq!(override_demo::name(), "mock");
+    }
+    assert_eq!(override_demo::name(), "real");
+}
+
+/// A demonstration of the `time` API implementation, with a fake clock and timers expired explicitly by the tests.
+#[crate::api_mod_impl(crate::time)]
+mod time_impl {
+    extern crate alloc;
+    extern crate std;
+    use crate::id::{Id, IdAllocator};
+    use crate::time::{
+        CancelToken, Nanos, Ticks, TimeValue, TimerHandle, TimerSlotCallback, TimerSlotId,
+        UnixNanos,
+    };
+    use crate::vmm::VMId;
+    use alloc::{boxed::Box, collections::BTreeMap, sync::Arc};
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{LazyLock, Mutex};
+
+    static TICKS: AtomicU64 = AtomicU64::new(0);
+
+    /// Advance the clock, one tick per nanosecond.
+    pub fn advance(nanos: Nanos) {
+        TICKS.fetch_add(nanos, Ordering::SeqCst);
+    }
+
+    extern fn current_ticks() -> Ticks {
+        TICKS.load(Ordering::SeqCst)
+    }
+
+    extern fn ticks_to_nanos(ticks: Ticks) -> Nanos {
+        ticks
+    }
+
+    extern fn nanos_to_ticks(nanos: Nanos) -> Ticks {
+        nanos
+    }
+
+    extern fn ticks_per_sec() -> u64 {
+        1_000_000_000
+    }
+
+    extern fn is_monotonic_across_cpus() -> bool {
+        true
+    }
+
+    extern fn resync_clock() -> crate::error::AxvisorResult {
+        Ok(())
+    }
+
+    extern fn cpu_ticks_offset(_cpu_id: usize) -> i64 {
+        0
+    }
+
+    extern fn set_cpu_ticks_offset(_cpu_id: usize, _offset: i64) -> crate::error::AxvisorResult {
+        Err(crate::error::AxvisorError::Unsupported)
+    }
+
+    /// Wall-clock time when the clock was zero, if it has been set.
+    static BOOT_WALL_CLOCK: Mutex<Option<UnixNanos>> = Mutex::new(None);
+
+    extern fn wall_clock_time() -> Option<UnixNanos> {
+        let boot = (*BOOT_WALL_CLOCK.lock().unwrap())?;
+        Some(boot + crate::time::current_time_nanos())
+    }
+
+    extern fn set_wall_clock_time(time: UnixNanos) {
+        let boot = time.saturating_sub(crate::time::current_time_nanos());
+        *BOOT_WALL_CLOCK.lock().unwrap() = Some(boot);
+    }
+
+    static VM_TIME_OFFSETS: Mutex<BTreeMap<VMId, TimeValue>> = Mutex::new(BTreeMap::new());
+
+    extern fn vm_time_offset(vm_id: VMId) -> TimeValue {
+        let offsets = VM_TIME_OFFSETS.lock().unwrap();
+        offsets.get(&vm_id).copied().unwrap_or_default()
+    }
+
+    extern fn set_vm_time_offset(vm_id: VMId, offset: TimeValue) -> crate::error::AxvisorResult {
+        VM_TIME_OFFSETS.lock().unwrap().insert(vm_id, offset);
+        Ok(())
+    }
+
+    extern fn sleep(duration: TimeValue) {
+        advance(duration.as_nanos() as Nanos);
+    }
+
+    extern fn timer_resolution() -> TimeValue {
+        TimeValue::from_micros(1)
+    }
+
+    enum Callback {
+        Once(Box<dyn FnOnce(TimeValue) + Send + 'static>),
+        Periodic(TimeValue, Box<dyn FnMut(TimeValue) + Send + 'static>),
+    }
+
+    /// A timer slot, with the deadline it is armed with.
+    type Slot = (Arc<dyn Fn(TimeValue) + Send + Sync>, Option<TimeValue>);
+
+    struct Timers {
+        tokens: IdAllocator,
+        pending: BTreeMap<CancelToken, (TimeValue, Callback)>,
+        slot_ids: IdAllocator,
+        slots: BTreeMap<TimerSlotId, Slot>,
+    }
+
+    static TIMERS: LazyLock<Mutex<Timers>> = LazyLock::new(|| {
+        Mutex::new(Timers {
+            tokens: IdAllocator::new(64),
+            pending: BTreeMap::new(),
+            slot_ids: IdAllocator::new(8),
+            slots: BTreeMap::new(),
+        })
+    });
+
+    fn add_timer(deadline: TimeValue, callback: Callback) -> TimerHandle {
+        let mut timers = TIMERS.lock().unwrap();
+        let token = timers.tokens.alloc().expect("too many timers").to_raw();
+        timers.pending.insert(token, (deadline, callback));
+        TimerHandle::from_raw(token)
+    }
+
+    /// Serialize the tests using timers, as [`expire_timers`] expires the timers of all tests.
+    pub fn lock_timer_tests() -> std::sync::MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        LOCK.lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Run the callbacks of the timers and timer slots expiring at or before `now`, as if the clock reached it,
+    /// returning the number of callbacks run.
+    pub fn expire_timers(now: TimeValue) -> usize {
+        let mut count = 0;
+        loop {
+            let mut timers = TIMERS.lock().unwrap();
+            let Some((&token, _)) = timers
+                .pending
+                .iter()
+                .filter(|(_, (deadline, _))| *deadline <= now)
+                .min_by_key(|(_, (deadline, _))| *deadline)
+            else {
+                break;
+            };
+            let (deadline, callback) = timers.pending.remove(&token).unwrap();
+            match callback {
+                Callback::Once(callback) => {
+                    timers.tokens.free(Id::from_raw(token));
+                    drop(timers);
+                    callback(deadline);
+                }
+                Callback::Periodic(period, mut callback) => {
+                    drop(timers);
+                    callback(deadline);
+                    // Missed expirations are skipped.
+                    let mut next = deadline + period;
+                    while next <= now {
+                        next += period;
+                    }
+                    let callback = Callback::Periodic(period, callback);
+                    TIMERS
+                        .lock()
+                        .unwrap()
+                        .pending
+                        .insert(token, (next, callback));
+                }
+            }
+            count += 1;
+        }
+
+        let expired: alloc::vec::Vec<_> = TIMERS
+            .lock()
+            .unwrap()
+            .slots
+            .values_mut()
+            .filter_map(|(callback, deadline)| {
+                let deadline = deadline.take_if(|deadline| *deadline <= now)?;
+                Some((callback.clone(), deadline))
+            })
+            .collect();
+        for (callback, deadline) in expired {
+            callback(deadline);
+            count += 1;
+        }
+        count
+    }
+
+    extern fn register_timer(
+        deadline: TimeValue,
+        callback: Box<dyn FnOnce(TimeValue) + Send + 'static>,
+    ) -> TimerHandle {
+        add_timer(deadline, Callback::Once(callback))
+    }
+
+    extern fn register_timer_on(
+        _cpu_id: usize,
+        deadline: TimeValue,
+        callback: Box<dyn FnOnce(TimeValue) + Send + 'static>,
+    ) -> TimerHandle {
+        add_timer(deadline, Callback::Once(callback))
+    }
+
+    extern fn register_periodic_timer(
+        period: TimeValue,
+        callback: Box<dyn FnMut(TimeValue) + Send + 'static>,
+    ) -> TimerHandle {
+        let deadline = crate::time::current_time() + period;
+        add_timer(deadline, Callback::Periodic(period, callback))
+    }
+
+    extern fn reschedule_timer(
+        token: CancelToken,
+        new_deadline: TimeValue,
+    ) -> crate::error::AxvisorResult {
+        let mut timers = TIMERS.lock().unwrap();
+        let (deadline, _) = timers
+            .pending
+            .get_mut(&token)
+            .ok_or(crate::error::AxvisorError::NotFound)?;
+        *deadline = new_deadline;
+        Ok(())
+    }
+
+    extern fn remaining_time(token: CancelToken) -> Option<TimeValue> {
+        let timers = TIMERS.lock().unwrap();
+        let (deadline, _) = timers.pending.get(&token)?;
+        Some(deadline.saturating_sub(crate::time::current_time()))
+    }
+
+    extern fn cancel_timer(token: CancelToken) -> bool {
+        let mut timers = TIMERS.lock().unwrap();
+        let cancelled = timers.pending.remove(&token).is_some();
+        if cancelled {
+            timers.tokens.free(Id::from_raw(token));
+        }
+        cancelled
+    }
+
+    extern fn allocate_timer_slot(
+        callback: TimerSlotCallback,
+    ) -> crate::error::AxvisorResult<TimerSlotId> {
+        let mut timers = TIMERS.lock().unwrap();
+        let id = timers
+            .slot_ids
+            .alloc()
+            .ok_or(crate::error::AxvisorError::NoMemory)?;
+        let slot = id.to_raw() as TimerSlotId;
+        timers.slots.insert(slot, (Arc::from(callback), None));
+        Ok(slot)
+    }
+
+    extern fn free_timer_slot(slot: TimerSlotId) {
+        let mut timers = TIMERS.lock().unwrap();
+        if timers.slots.remove(&slot).is_some() {
+            timers.slot_ids.free(Id::from_raw(slot as u64));
+        }
+    }
+
+    extern fn arm_slot(slot: TimerSlotId, deadline: TimeValue) -> crate::error::AxvisorResult {
+        let mut timers = TIMERS.lock().unwrap();
+        let (_, armed) = timers
+            .slots
+            .get_mut(&slot)
+            .ok_or(crate::error::AxvisorError::NotFound)?;
+        *armed = Some(deadline);
+        Ok(())
+    }
+
+    extern fn disarm_slot(slot: TimerSlotId) -> bool {
+        let mut timers = TIMERS.lock().unwrap();
+        timers
+            .slots
+            .get_mut(&slot)
+            .is_some_and(|(_, armed)| armed.take().is_some())
+    }
+}
+
+#[test]
+pub fn test_timer_handle() {
+    extern crate std;
+    use crate::time::{self, TimeValue};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let _lock = time_impl::lock_timer_tests();
+    let fired = Arc::new(AtomicUsize::new(0));
+    let on_fire = || {
+        let fired = fired.clone();
+        std::boxed::Box::new(move |_| {
+            fired.fetch_add(1, Ordering::SeqCst);
+        })
+    };
+    let now = time::current_time();
+    let secs = |secs| now + TimeValue::from_secs(secs);
+
+    let handle = time::register_timer(secs(10), on_fire());
+    assert!(
+        handle
+            .remaining_time()
+            .is_some_and(|t| t <= TimeValue::from_secs(10))
+    );
+    assert_eq!(handle.reschedule(secs(20)), Ok(()));
+    time_impl::expire_timers(secs(15));
+    assert_eq!(fired.load(Ordering::SeqCst), 0);
+    time_impl::expire_timers(secs(20));
+    assert_eq!(fired.load(Ordering::SeqCst), 1);
+    assert_eq!(handle.remaining_time(), None);
+    assert_eq!(
+        handle.reschedule(secs(30)),
+        Err(crate::error::AxvisorError::NotFound)
+    );
+    // The handle of the expired timer does not cancel a timer registered later.
+    let later = time::register_timer(secs(30), on_fire());
+    assert_ne!(later.token(), handle.token());
+    assert!(!handle.cancel());
+    assert!(later.remaining_time().is_some());
+    drop(later);
+
+    // Dropping a handle cancels the timer, while detaching it keeps the timer pending.
+    let dropped = time::register_timer(secs(10), on_fire());
+    let token = dropped.token();
+    drop(dropped);
+    assert_eq!(time::remaining_time(token), None);
+    let detached = time::register_timer_on(0, secs(10), on_fire()).detach();
+    time_impl::expire_timers(secs(10));
+    assert_eq!(fired.load(Ordering::SeqCst), 2);
+    assert!(!time::cancel_timer(detached));
+}
+
+#[test]
+pub fn test_periodic_timer() {
+    extern crate std;
+    use crate::time::{self, TimeValue};
+    use std::{sync::Arc, sync::Mutex, vec::Vec};
+
+    let _lock = time_impl::lock_timer_tests();
+    let deadlines = Arc::new(Mutex::new(Vec::new()));
+    let now = time::current_time();
+    let secs = |millis| now + TimeValue::from_millis(millis);
+
+    let recorded = deadlines.clone();
+    let handle = time::register_periodic_timer(
+        TimeValue::from_secs(1),
+        std::boxed::Box::new(move |deadline| recorded.lock().unwrap().push(deadline)),
+    );
+    time_impl::expire_timers(secs(1500));
+    assert_eq!(deadlines.lock().unwrap().len(), 1);
+    // The expiration at 3 seconds is missed, and not compensated.
+    time_impl::expire_timers(secs(3500));
+    {
+        let deadlines = deadlines.lock().unwrap();
+        assert_eq!(deadlines.len(), 2);
+        assert_eq!(deadlines[1] - deadlines[0], TimeValue::from_secs(1));
+    }
+    assert!(handle.remaining_time().is_some());
+    assert!(handle.cancel());
+    time_impl::expire_timers(secs(10_000));
+    assert_eq!(deadlines.lock().unwrap().len(), 2);
+}
+
+#[test]
+pub fn test_timer_slot() {
+    extern crate std;
+    use crate::time::{self, TimeValue, TimerSlot};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let _lock = time_impl::lock_timer_tests();
+    let fired = Arc::new(AtomicUsize::new(0));
+    let counter = fired.clone();
+    let slot = TimerSlot::new(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+    let deadline = time::current_time() + TimeValue::from_secs(100);
+
+    slot.arm(deadline);
+    assert!(slot.disarm());
+    assert!(!slot.disarm());
+    slot.arm(deadline);
+    time_impl::expire_timers(deadline);
+    assert_eq!(fired.load(Ordering::SeqCst), 1);
+    // The slot is disarmed when it expires.
+    assert!(!slot.disarm());
+
+    let id = slot.id();
+    drop(slot);
+    assert_eq!(
+        time::arm_slot(id, deadline),
+        Err(crate::error::AxvisorError::NotFound)
+    );
+}
+
+#[test]
+pub fn test_wall_clock_and_vm_time() {
+    use crate::{time, vmm::VMId};
+    use core::time::Duration;
+
+    const NOW: u64 = 1_700_000_000_000_000_000;
+    time::set_wall_clock_time(NOW);
+    assert!(time::wall_clock_time().is_some_and(|now| now >= NOW));
+    assert!(time::boot_time().is_some_and(|boot| boot <= NOW));
+
+    let vm = VMId::from(7usize);
+    assert_eq!(time::vm_time_offset(vm), Duration::ZERO);
+    assert_eq!(
+        time::set_vm_time_offset(vm, Duration::from_nanos(100)),
+        Ok(())
+    );
+    let vm_time = time::vm_current_time(vm);
+    assert!(vm_time + Duration::from_nanos(100) <= time::current_time());
+}
+
+/// A demonstration of the `diag` API implementation, recording the last slow API call.
+#[crate::api_mod_impl(crate::diag)]
+mod diag_impl {
+    extern crate std;
+
+    use crate::diag::{ApiCallArgs, ApiTraceSink, SlowApiCall, TracedApiCall};
+    use crate::time::TimeValue;
+    use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+    use std::{format, string::String, sync::Mutex, vec::Vec};
+
+    pub static SLOW_CALL_ELAPSED: AtomicU64 = AtomicU64::new(0);
+    pub static SLOW_CALL_LINE: AtomicU32 = AtomicU32::new(0);
+
+    extern fn report_slow_api_call(call: &SlowApiCall) {
+        assert_eq!(call.function, "work");
+        SLOW_CALL_ELAPSED.store(call.elapsed.as_nanos() as u64, Ordering::SeqCst);
+        SLOW_CALL_LINE.store(call.caller.line(), Ordering::SeqCst);
+    }
+
+    /// A trace sink recording the events as strings.
+    pub struct RecordingSink(pub Mutex<Vec<String>>);
+
+    impl ApiTraceSink for RecordingSink {
+        fn enter(&self, call: &TracedApiCall, args: &ApiCallArgs) {
+            let event = format!("enter {}{:?}", call.function, args);
+            self.0.lock().unwrap().push(event);
+        }
+
+        fn exit(&self, call: &TracedApiCall, _elapsed: TimeValue) {
+            let event = format!("exit {}", call.function);
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    pub static TRACE_SINK: RecordingSink = RecordingSink(Mutex::new(Vec::new()));
+
+    extern fn api_trace_sink() -> Option<&'static dyn ApiTraceSink> {
+        Some(&TRACE_SINK)
+    }
+}
+
+#[crate::api_mod(trace)]
+/// A demonstration of traced API modules.
+mod traced_demo {
+    /// Add two numbers.
+    extern fn add(a: u32, b: u32) -> u32;
+}
+
+#[crate::api_mod_impl(traced_demo)]
+mod traced_demo_impl {
+    extern fn add(a: u32, b: u32) -> u32 {
+        a + b
+    }
+}
+
+#[test]
+pub fn test_api_trace() {
+    assert_eq!(traced_demo::add(1, 2), 3);
+    assert_eq!(
+        *diag_impl::TRACE_SINK.0.lock().unwrap(),
+        ["enter add{a: 1, b: 2}", "exit add"]
+    );
+}
+
+#[crate::api_mod]
+/// A demonstration of `cfg`-gated API functions.
+mod cfg_demo {
+    /// Always available.
+    extern fn always() -> u32;
+    /// Available only with the `watchdog` feature.
+    #[cfg(feature = "watchdog")]
+    extern fn watched() -> u32;
+    /// Never available.
+    #[cfg(any())]
+    extern fn never() -> u32;
+}
+
+#[crate::api_mod_impl(cfg_demo)]
+mod cfg_demo_impl {
+    extern fn always() -> u32 {
+        1
+    }
+
+    #[cfg(feature = "watchdog")]
+    extern fn watched() -> u32 {
+        2
+    }
+}
+
+#[test]
+pub fn test_api_cfg() {
+    assert_eq!(cfg_demo::always(), 1);
+    #[cfg(feature = "watchdog")]
+    assert_eq!(cfg_demo::watched(), 2);
+}
+
+#[crate::api_mod]
+/// A demonstration of time-bounded API functions.
+mod watched_demo {
+    /// Do some work, taking `nanos` nanoseconds.
+    #[api(max_duration = "1us")]
+    extern fn work(nanos: u64) -> u64;
+}
+
+#[crate::api_mod_impl(watched_demo)]
+mod watched_demo_impl {
+    extern fn work(nanos: u64) -> u64 {
+        super::time_impl::advance(nanos);
+        nanos
+    }
+}
+
+#[test]
+pub fn test_api_max_duration() {
+    assert_eq!(watched_demo::work(500), 500);
+    assert_eq!(watched_demo::work(2000), 2000);
+
+    #[cfg(feature = "watchdog")]
+    {
+        use core::sync::atomic::Ordering;
+
+        let line = line!() - 6;
+        assert_eq!(diag_impl::SLOW_CALL_ELAPSED.load(Ordering::SeqCst), 2000);
+        assert_eq!(diag_impl::SLOW_CALL_LINE.load(Ordering::SeqCst), line);
+    }
+}
+
+#[test]
+pub fn test_id_allocator() {
+    use crate::id::{Id, IdAllocator};
+
+    let mut allocator = IdAllocator::new(3);
+    let id0 = allocator.alloc().unwrap();
+    let id1 = allocator.alloc().unwrap();
+    let id2 = allocator.alloc().unwrap();
+    assert_eq!((id0.index(), id1.index(), id2.index()), (0, 1, 2));
+    assert_eq!(allocator.alloc(), None);
+    assert_eq!(allocator.allocated_count(), 3);
+
+    assert!(allocator.free(id1));
+    assert!(!allocator.free(id1));
+    assert!(!allocator.is_allocated(id1));
+
+    let id1_new = allocator.alloc().unwrap();
+    assert_eq!(id1_new.index(), 1);
+    assert_eq!(id1_new.generation(), 1);
+    assert!(!allocator.is_allocated(id1));
+    assert!(!allocator.free(id1));
+    assert!(allocator.is_allocated(id1_new));
+
+    assert_eq!(Id::from_raw(id1_new.to_raw()), id1_new);
+    assert!(!allocator.is_allocated(Id::from_raw(100)));
+}
+
+#[cfg(not(feature = "legacy-ids"))]
+#[test]
+pub fn test_vmm_ids() {
+    extern crate std;
+    use crate::vmm::{VCpuId, VMId};
+    use std::format;
+
+    let vcpu = VCpuId::new(2);
+    assert_eq!(usize::from(vcpu), 2);
+    assert_eq!(format!("{vcpu}"), "2");
+    assert!(vcpu.is_valid_for(3));
+    assert!(!vcpu.is_valid_for(2));
+    assert!(vcpu.is_in_mask(0b100));
+    assert!(!vcpu.is_in_mask(0b011));
+    assert!(!VCpuId::new(usize::BITS as usize).is_in_mask(usize::MAX));
+
+    assert!(VMId::from(0usize).is_valid());
+    assert!(!VMId::INVALID.is_valid());
+}
+
+#[cfg(not(feature = "legacy-ids"))]
+#[test]
+pub fn test_vcpu_mask() {
+    extern crate std;
+    use crate::vmm::{VCpuId, VCpuMask};
+    use std::vec::Vec;
+
+    let vcpu = VCpuId::new;
+    let mut mask = VCpuMask::from_bits(0b101);
+    assert!(mask.contains(vcpu(2)));
+    assert!(!mask.contains(vcpu(1)));
+    assert!(!mask.contains(vcpu(200)));
+
+    mask.insert(vcpu(130));
+    assert!(mask.contains(vcpu(130)));
+    assert_eq!(mask.count(), 3);
+    assert_eq!(
+        mask.iter().map(usize::from).collect::<Vec<_>>(),
+        [0, 2, 130]
+    );
+
+    mask.remove(vcpu(130));
+    mask.remove(vcpu(500));
+    assert_eq!(mask, VCpuMask::from_bits(0b101));
+    assert_eq!(mask, [0, 2].into_iter().map(vcpu).collect());
+    mask.remove(vcpu(0));
+    mask.remove(vcpu(2));
+    assert!(mask.is_empty());
+}
+
+#[test]
+pub fn test_handler_desc_display() {
+    extern crate std;
+    use crate::vmm::{HandlerDesc, HandlerKind};
+    use std::string::ToString;
+
+    let desc = HandlerDesc {
+        kind: HandlerKind::Mmio,
+        start: 0x0800_0000,
+        size: 0x1_0000,
+        owner: "vgicd",
+    };
+    assert_eq!(desc.to_string(), "Mmio [0x8000000, 0x8010000) by vgicd");
+}
+
+#[test]
+pub fn test_time_conversion_overflow() {
+    use crate::time::{self, TimeValue};
+
+    assert_eq!(
+        time::mul_div_saturating(3_000_000_000, 1_000_000_000, 24_000_000),
+        125_000_000_000
+    );
+    assert_eq!(
+        time::mul_div_saturating(u64::MAX, 1_000_000_000, 24_000_000),
+        u64::MAX
+    );
+
+    let hours = TimeValue::from_secs(3600 * 10);
+    assert_eq!(time::time_to_ticks(hours), 36_000_000_000_000);
+    assert_eq!(time::time_to_ticks_checked(hours), Some(36_000_000_000_000));
+    assert_eq!(time::time_to_ticks(TimeValue::MAX), u64::MAX);
+    let max = TimeValue::from_nanos(u64::MAX);
+    assert_eq!(time::time_to_ticks_checked(max), Some(u64::MAX));
+    assert_eq!(
+        time::time_to_ticks_checked(max + TimeValue::from_nanos(1)),
+        None
+    );
+    assert_eq!(time::time_to_ticks_checked(TimeValue::MAX), None);
+}
+
+#[test]
+pub fn test_msi_x86_decode() {
+    use crate::vmm::MsiMessage;
+
+    let msg = MsiMessage {
+        addr: 0xfee0_3000,
+        data: 0x4031,
+    };
+    assert_eq!(msg.x86_decode(), Some((3, 0x31)));
+    assert_eq!(
+        MsiMessage {
+            addr: 0x0804_0040,
+            data: 0x31
+        }
+        .x86_decode(),
+        None
+    );
+}
+
+#[test]
+pub fn test_timer_list() {
+    extern crate std;
+    use crate::time::{TimeValue, TimerList};
+    use std::vec::Vec;
+
+    let at = TimeValue::from_millis;
+    let mut list = TimerList::new();
+    assert_eq!(list.next_deadline(), None);
+
+    list.add(at(30), "c");
+    let b = list.add(at(20), "b");
+    list.add(at(10), "a1");
+    list.add(at(10), "a2");
+    assert_eq!(list.len(), 4);
+    assert_eq!(list.next_deadline(), Some(at(10)));
+    assert_eq!(list.cancel(b), Some("b"));
+    assert_eq!(list.cancel(b), None);
+
+    assert_eq!(list.pop_expired(at(5)), None);
+    let mut expired = Vec::new();
+    assert_eq!(list.expire(at(25), |_, value| expired.push(value)), 2);
+    assert_eq!(expired, ["a1", "a2"]);
+    assert_eq!(list.next_deadline(), Some(at(30)));
+    assert_eq!(list.pop_expired(at(30)), Some((at(30), "c")));
+    assert!(list.is_empty());
+}
+
+#[test]
+pub fn test_interrupt_bitmap() {
+    extern crate std;
+    use crate::vmm::InterruptBitmap;
+    use std::vec::Vec;
+
+    let mut pending = InterruptBitmap::new();
+    assert!(pending.is_empty());
+    assert!(pending.insert(27));
+    assert!(pending.insert(64));
+    assert!(pending.insert(1019));
+    assert!(!pending.insert(1024));
+    assert!(pending.contains(64));
+    assert!(!pending.contains(1024));
+    assert_eq!(pending.len(), 3);
+
+    pending.remove(64);
+    pending.remove(2000);
+    assert_eq!(pending.iter().collect::<Vec<_>>(), [27, 1019]);
+}
+
+#[test]
+pub fn test_host_cpu_mask() {
+    extern crate std;
+    use crate::host::HostCpuMask;
+    use std::vec::Vec;
+
+    let mut mask = HostCpuMask::first(3);
+    assert_eq!(mask.iter().collect::<Vec<_>>(), [0, 1, 2]);
+    mask.remove(1);
+    assert!(mask.insert(200));
+    assert!(!mask.insert(HostCpuMask::CAPACITY));
+    assert!(mask.contains(200));
+    assert!(!mask.contains(1));
+    assert_eq!(mask.count(), 3);
+    assert!(HostCpuMask::first(0).is_empty());
+}
+
+#[test]
+pub fn test_irte() {
+    use crate::arch::Irte;
+
+    let irte = Irte {
+        low: 0x0000_0002_0031_0001,
+        high: 0x0004_0010,
+    };
+    assert!(irte.is_present());
+    assert!(irte.is_well_formed());
+    assert_eq!(irte.vector(), 0x31);
+    assert_eq!(irte.destination(), 2);
+    assert_eq!(irte.source_id(), 0x10);
+
+    let posted = Irte {
+        low: irte.low | 1 << 15,
+        ..irte
+    };
+    assert!(!posted.is_well_formed());
+}
+
+#[test]
+pub fn test_cpu_features() {
+    use crate::arch::CpuFeatures;
+
+    let host = CpuFeatures::SVE | CpuFeatures::VHE | CpuFeatures::GICV3;
+    let mask = CpuFeatures::VHE | CpuFeatures::GICV3 | CpuFeatures::GICV4;
+    assert_eq!(host & mask, CpuFeatures::VHE | CpuFeatures::GICV3);
+    assert_eq!(host.difference(mask), CpuFeatures::SVE);
+    assert!(host.contains(CpuFeatures::VHE | CpuFeatures::SVE));
+    assert!(!host.contains(CpuFeatures::GICV4));
+    assert!(host.contains(CpuFeatures::EMPTY));
+    assert!(host.intersection(CpuFeatures::VMX_EPT).is_empty());
+}
+
+#[test]
+pub fn test_device_args() {
+    use crate::{config::ArgMap, error::AxvisorError};
+
+    let args = ArgMap::parse("virtio-net, mac=52:54:00:12:34:56,queues=4,,vhost,csum=off,queues=8");
+    assert_eq!(args.kind(), "virtio-net");
+    assert_eq!(args.get("mac"), Some("52:54:00:12:34:56"));
+    assert_eq!(args.get_as::<u16>("queues"), Ok(Some(8)));
+    assert_eq!(args.get_as::<u16>("mac"), Err(AxvisorError::InvalidArg));
+    assert_eq!(args.get_as::<u16>("mtu"), Ok(None));
+    assert!(args.flag("vhost"));
+    assert!(!args.flag("csum"));
+    assert!(!args.flag("mrg_rxbuf"));
+    assert_eq!(args.get("vhost"), None);
+    assert_eq!(args.iter().count(), 5);
+
+    assert_eq!(ArgMap::parse("").kind(), "");
+}
+
+/// A demonstration of the `console` API implementation, recording the output.
+#[crate::api_mod_impl(crate::console)]
+mod console_impl {
+    extern crate std;
+    use crate::console::InputHandler;
+    use std::{sync::Mutex, vec::Vec};
+
+    pub static OUTPUT: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+    extern fn put_bytes(bytes: &[u8]) {
+        OUTPUT.lock().unwrap().extend_from_slice(bytes);
+    }
+
+    extern fn get_byte() -> Option<u8> {
+        None
+    }
+
+    extern fn register_input_handler(_handler: InputHandler) -> crate::error::AxvisorResult {
+        Err(crate::error::AxvisorError::Unsupported)
+    }
+
+    extern fn unregister_input_handler() -> crate::error::AxvisorResult {
+        Err(crate::error::AxvisorError::NotFound)
+    }
+}
+
+#[test]
+pub fn test_console_write() {
+    use crate::console::Console;
+    use core::fmt::Write;
+
+    write!(Console, "vm{} up, {:#x}", 1, 0x80000).unwrap();
+    assert_eq!(
+        console_impl::OUTPUT.lock().unwrap().as_slice(),
+        b"vm1 up, 0x80000"
+    );
+}
+
+#[test]
+pub fn test_acpi_fragment() {
+    use crate::acpi::{AcpiFragment, McfgSegment};
+
+    // An I/O APIC structure.
+    let ioapic = [1, 12, 0, 0, 0x00, 0x00, 0xc0, 0xfe, 0, 0, 0, 0];
+    assert!(AcpiFragment::MadtEntry(&ioapic).is_well_formed());
+    assert!(!AcpiFragment::MadtEntry(&ioapic[..8]).is_well_formed());
+    assert!(!AcpiFragment::MadtEntry(&[]).is_well_formed());
+    assert!(!AcpiFragment::DsdtAml(&[]).is_well_formed());
+
+    let segment = McfgSegment {
+        base: crate::guest_memory::GuestPhysAddr::from_usize(0x3000_0000),
+        segment: 0,
+        start_bus: 0,
+        end_bus: 0xff,
+    };
+    assert!(AcpiFragment::McfgSegment(segment).is_well_formed());
+    assert!(
+        !AcpiFragment::McfgSegment(McfgSegment {
+            start_bus: 1,
+            end_bus: 0,
+            ..segment
+        })
+        .is_well_formed()
+    );
+}
+
+mod virtio_memory {
+    extern crate std;
+    use crate::guest_memory::GuestPhysAddr;
+    use crate::virtio::GuestMemoryAccess;
+    use std::{sync::Mutex, vec, vec::Vec};
+
+    /// Guest memory of the virtio tests, a local buffer starting at guest physical address 0.
+    pub struct VirtioMemory(Mutex<Vec<u8>>);
+
+    impl VirtioMemory {
+        pub fn new(size: usize) -> Self {
+            Self(Mutex::new(vec![0; size]))
+        }
+
+        pub fn put<const N: usize>(&self, gpa: usize, bytes: [u8; N]) {
+            self.0.lock().unwrap()[gpa..gpa + N].copy_from_slice(&bytes);
+        }
+
+        pub fn get<const N: usize>(&self, gpa: usize) -> [u8; N] {
+            self.0.lock().unwrap()[gpa..gpa + N].try_into().unwrap()
+        }
+    }
+
+    impl GuestMemoryAccess for VirtioMemory {
+        fn read(&self, gpa: GuestPhysAddr, buf: &mut [u8]) -> bool {
+            let memory = self.0.lock().unwrap();
+            match memory.get(gpa.as_usize()..gpa.as_usize() + buf.len()) {
+                Some(bytes) => {
+                    buf.copy_from_slice(bytes);
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn write(&self, gpa: GuestPhysAddr, buf: &[u8]) -> bool {
+            let mut memory = self.0.lock().unwrap();
+            match memory.get_mut(gpa.as_usize()..gpa.as_usize() + buf.len()) {
+                Some(bytes) => {
+                    bytes.copy_from_slice(buf);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+}
+
+#[test]
+pub fn test_virtio_split_queue() {
+    use crate::error::AxvisorError;
+    use crate::guest_memory::GuestPhysAddr;
+    use crate::virtio::{SplitQueue, VIRTQ_DESC_F_INDIRECT, VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE};
+
+    // A queue of 4 entries, with the descriptor table at 0x0, the available ring at 0x40 and the used ring at 0x80.
+    let mem = virtio_memory::VirtioMemory::new(0x100);
+    let desc = |index: usize, addr: u64, len: u32, flags: u16, next: u16| {
+        mem.put(index * 16, addr.to_le_bytes());
+        mem.put(index * 16 + 8, len.to_le_bytes());
+        mem.put(index * 16 + 12, flags.to_le_bytes());
+        mem.put(index * 16 + 14, next.to_le_bytes());
+    };
+    let mut queue = SplitQueue::new(
+        4,
+        GuestPhysAddr::from_usize(0x0),
+        GuestPhysAddr::from_usize(0x40),
+        GuestPhysAddr::from_usize(0x80),
+        false,
+    )
+    .unwrap();
+    assert_eq!(queue.pop_avail(&mem), Ok(None));
+
+    // A request header followed by a response buffer, made available at slot 0.
+    desc(2, 0xc0, 8, VIRTQ_DESC_F_NEXT, 1);
+    desc(1, 0xd0, 16, VIRTQ_DESC_F_WRITE, 0);
+    mem.put(0x44, 2u16.to_le_bytes());
+    mem.put(0x42, 1u16.to_le_bytes());
+
+    let chain = queue.pop_avail(&mem).unwrap().unwrap();
+    assert_eq!(chain.id, 2);
+    assert_eq!(chain.descriptors.len(), 2);
+    assert_eq!((chain.readable_len(), chain.writable_len()), (8, 16));
+    assert_eq!(queue.pop_avail(&mem), Ok(None));
+
+    chain.writable().next().unwrap().write(&mem, b"ok").unwrap();
+    queue.push_used(&mem, &chain, 2).unwrap();
+    assert_eq!(mem.get(0x82), 1u16.to_le_bytes());
+    assert_eq!(mem.get(0x84), 2u32.to_le_bytes());
+    assert_eq!(mem.get(0x88), 2u32.to_le_bytes());
+    assert_eq!(mem.get(0xd0), *b"ok");
+    assert_eq!(queue.needs_notification(&mem), Ok(true));
+    assert_eq!(queue.needs_notification(&mem), Ok(false));
+
+    // A looping chain is rejected.
+    desc(0, 0xc0, 8, VIRTQ_DESC_F_NEXT, 3);
+    desc(3, 0xc0, 8, VIRTQ_DESC_F_NEXT, 0);
+    mem.put(0x46, 0u16.to_le_bytes());
+    mem.put(0x42, 2u16.to_le_bytes());
+    assert_eq!(queue.pop_avail(&mem), Err(AxvisorError::InvalidArg));
+
+    // An indirect table with more entries than the queue is rejected before it is read.
+    desc(0, 0xe0, 0xffff_fff0, VIRTQ_DESC_F_INDIRECT, 0);
+    mem.put(0x48, 0u16.to_le_bytes());
+    mem.put(0x42, 3u16.to_le_bytes());
+    assert_eq!(queue.pop_avail(&mem), Err(AxvisorError::InvalidArg));
+
+    // So is a chain exceeding the queue with its indirect table, while a table within the queue is resolved.
+    desc(3, 0xe0, 4 * 16, VIRTQ_DESC_F_INDIRECT, 0);
+    desc(0, 0xc0, 8, VIRTQ_DESC_F_NEXT, 3);
+    mem.put(0x4a, 0u16.to_le_bytes());
+    mem.put(0x42, 4u16.to_le_bytes());
+    assert_eq!(queue.pop_avail(&mem), Err(AxvisorError::InvalidArg));
+    desc(3, 0xe0, 2 * 16, VIRTQ_DESC_F_INDIRECT, 0);
+    mem.put(0xe0, 0xc0u64.to_le_bytes());
+    mem.put(0xe8, 8u32.to_le_bytes());
+    mem.put(0xec, VIRTQ_DESC_F_NEXT.to_le_bytes());
+    mem.put(0xee, 1u16.to_le_bytes());
+    mem.put(0xf0, 0xd0u64.to_le_bytes());
+    mem.put(0xf8, 16u32.to_le_bytes());
+    mem.put(0xfc, VIRTQ_DESC_F_WRITE.to_le_bytes());
+    mem.put(0x44, 0u16.to_le_bytes());
+    mem.put(0x42, 5u16.to_le_bytes());
+    let chain = queue.pop_avail(&mem).unwrap().unwrap();
+    assert_eq!(chain.descriptors.len(), 3);
+    assert_eq!((chain.readable_len(), chain.writable_len()), (16, 16));
+}
+
+#[test]
+pub fn test_virtio_packed_queue() {
+    use crate::error::AxvisorError;
+    use crate::guest_memory::GuestPhysAddr;
+    use crate::virtio::{
+        PackedQueue, VIRTQ_DESC_F_AVAIL, VIRTQ_DESC_F_INDIRECT, VIRTQ_DESC_F_NEXT,
+        VIRTQ_DESC_F_USED, VIRTQ_DESC_F_WRITE,
+    };
+
+    // A queue of 2 entries, with the descriptor ring at 0x0 and the event suppression structures at 0x20 and 0x24.
+    let mem = virtio_memory::VirtioMemory::new(0x100);
+    let desc = |index: usize, addr: u64, len: u32, id: u16, flags: u16| {
+        mem.put(index * 16, addr.to_le_bytes());
+        mem.put(index * 16 + 8, len.to_le_bytes());
+        mem.put(index * 16 + 12, id.to_le_bytes());
+        mem.put(index * 16 + 14, flags.to_le_bytes());
+    };
+    let mut queue = PackedQueue::new(
+        2,
+        GuestPhysAddr::from_usize(0x0),
+        GuestPhysAddr::from_usize(0x20),
+        GuestPhysAddr::from_usize(0x24),
+    )
+    .unwrap();
+    assert_eq!(queue.pop_avail(&mem), Ok(None));
+
+    // A chain of two descriptors with buffer ID 7, filling the ring.
+    desc(1, 0xd0, 16, 7, VIRTQ_DESC_F_AVAIL | VIRTQ_DESC_F_WRITE);
+    desc(0, 0xc0, 8, 7, VIRTQ_DESC_F_AVAIL | VIRTQ_DESC_F_NEXT);
+    let chain = queue.pop_avail(&mem).unwrap().unwrap();
+    assert_eq!(chain.id, 7);
+    assert_eq!((chain.readable_len(), chain.writable_len()), (8, 16));
+    assert_eq!(queue.pop_avail(&mem), Ok(None));
+
+    queue.push_used(&mem, &chain, 4).unwrap();
+    assert_eq!(mem.get(0x8), 4u32.to_le_bytes());
+    assert_eq!(mem.get(0xc), 7u16.to_le_bytes());
+    assert_eq!(
+        mem.get(0xe),
+        (VIRTQ_DESC_F_AVAIL | VIRTQ_DESC_F_USED | VIRTQ_DESC_F_WRITE).to_le_bytes()
+    );
+    assert_eq!(queue.needs_notification(&mem), Ok(true));
+
+    // After wrapping around, descriptors are available with the avail flag clear and the used flag set.
+    desc(0, 0xc0, 8, 3, VIRTQ_DESC_F_USED);
+    let chain = queue.pop_avail(&mem).unwrap().unwrap();
+    assert_eq!(chain.id, 3);
+    queue.set_notification(&mem, false).unwrap();
+    assert_eq!(mem.get(0x26), 1u16.to_le_bytes());
+
+    // Notifications disabled by the driver.
+    mem.put(0x22, 1u16.to_le_bytes());
+    queue.push_used(&mem, &chain, 0).unwrap();
+    assert_eq!(mem.get(0xe), 0u16.to_le_bytes());
+    assert_eq!(queue.needs_notification(&mem), Ok(false));
+
+    // An indirect table with more entries than the queue is rejected.
+    desc(
+        1,
+        0xe0,
+        3 * 16,
+        5,
+        VIRTQ_DESC_F_USED | VIRTQ_DESC_F_INDIRECT,
+    );
+    assert_eq!(queue.pop_avail(&mem), Err(AxvisorError::InvalidArg));
+}
@@ -0,0 +1,97 @@
+//! Utilities for testing components against alternative implementations of API modules.
+//!
+//! Available with the `std` feature, which enables the `dynamic` feature, so that all API functions are dispatched
+//! through the [`registry`](crate::registry) where the overrides are looked up first.
+
+extern crate alloc;
+extern crate std;
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::registry::ApiFnEntry;
+
+/// Override the implementation of an API module with a type implementing its API trait on the current thread, until
+/// the returned [`ImplOverride`] is dropped.
+///
+/// Overrides are thread-local, so tests running concurrently do not see each other's overrides, and calls made on
+/// other threads, e.g. ones spawned by the code under test, still go to the regular implementation. Overrides can be
+/// nested, in which case the most recent one is in effect.
+///
+/// ```rust, ignore
+/// struct MockMemory;
+///
+/// impl axvisor_api::memory::AxvisorMemoryApiTrait for MockMemory {
+///     // ...
+/// }
+///
+/// let _guard = axvisor_api::testing::override_impl!(axvisor_api::memory, MockMemory);
+/// // Calls to `axvisor_api::memory` APIs on this thread go to `MockMemory` here.
+/// ```
+#[macro_export]
+macro_rules! override_impl {
+    ($($module:ident)::+, $impl:ty) => {
+        $crate::testing::ImplOverride::new(|f| $($module)::+::__axvisor_api_for_each_entry_of::<$impl>(f))
+    };
+}
+
+pub use override_impl;
+
+std::thread_local! {
+    /// Overrides in effect on the current thread, most recent last, with the id of the guard owning each.
+    static OVERRIDES: RefCell<Vec<(u64, ApiFnEntry)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Id of the next [`ImplOverride`].
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Look up the implementation of an API function overridden on the current thread, if any.
+pub(crate) fn overridden(api: &str, function: &str) -> Option<*const ()> {
+    OVERRIDES
+        .try_with(|overrides| {
+            overrides
+                .borrow()
+                .iter()
+                .rev()
+                .find(|(_, entry)| entry.api == api && entry.function == function)
+                .map(|(_, entry)| entry.ptr)
+        })
+        .ok()
+        .flatten()
+}
+
+/// An active override of the implementation of an API module on the current thread, created by [`override_impl!`].
+///
+/// The override is removed when dropped, which can happen in any order relative to the other overrides.
+#[must_use = "the override is reverted immediately if the guard is not held"]
+pub struct ImplOverride {
+    /// Id of the override, with which its entries are tagged.
+    id: u64,
+    /// Make the guard `!Send`, as it must be dropped on the thread it overrides the implementation on.
+    _not_send: core::marker::PhantomData<*const ()>,
+}
+
+impl ImplOverride {
+    #[doc(hidden)]
+    pub fn new(for_each_entry: impl FnOnce(&mut dyn FnMut(ApiFnEntry))) -> Self {
+        let guard = Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            _not_send: core::marker::PhantomData,
+        };
+
+        let mut entries = Vec::new();
+        for_each_entry(&mut |entry| entries.push((guard.id, entry)));
+        OVERRIDES.with_borrow_mut(|overrides| overrides.extend(entries));
+        guard
+    }
+}
+
+impl Drop for ImplOverride {
+    fn drop(&mut self) {
+        // The thread-local may already be destroyed if the guard is dropped in another thread-local's destructor.
+        let _ = OVERRIDES.try_with(|overrides| {
+            overrides.borrow_mut().retain(|(id, _)| *id != self.id);
+        });
+    }
+}
@@ -0,0 +1,40 @@
+//! Versions of API modules.
+
+use core::fmt;
+
+/// Version of an API module, declared with `#[api_mod(version = "major.minor")]`.
+///
+/// Minor versions are backward compatible, i.e., an implementation of version `1.3` can be used by components built
+/// against version `1.2`, while different major versions are incompatible.
+///
+/// The layout of this type is fixed, as it is shared between components possibly built against different versions of
+/// this crate.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApiVersion {
+    /// Major version.
+    pub major: u16,
+    /// Minor version.
+    pub minor: u16,
+}
+
+impl ApiVersion {
+    /// Version of API modules which do not declare a version.
+    pub const UNVERSIONED: Self = Self::new(0, 0);
+
+    /// Create a version.
+    pub const fn new(major: u16, minor: u16) -> Self {
+        Self { major, minor }
+    }
+
+    /// Check whether an implementation of version `provided` can be used by components requiring this version.
+    pub const fn is_satisfied_by(self, provided: ApiVersion) -> bool {
+        self.major == provided.major && self.minor <= provided.minor
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
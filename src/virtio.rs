@@ -0,0 +1,653 @@
+//! Virtqueue helpers shared by the components emulating virtio devices.
+//!
+//! Both the [split](SplitQueue) and the [packed](PackedQueue) virtqueue layouts of virtio 1.x are supported, from the
+//! device side. The queues live in guest memory, which is accessed through [`GuestMemoryAccess`], implemented with the
+//! [`guest_memory`](crate::guest_memory) API by [`VmMemory`].
+//!
+//! Virtqueues are little-endian, legacy (pre-1.0) big-endian guests are not supported.
+
+extern crate alloc;
+use alloc::vec::Vec;
+use core::sync::atomic::{Ordering, fence};
+
+use crate::error::{AxvisorError, AxvisorResult};
+use crate::guest_memory::{Endianness, GuestPhysAddr, GuestPrimitive};
+use crate::vmm::VMId;
+
+/// Access to the guest memory holding the virtqueues and the buffers.
+pub trait GuestMemoryAccess {
+    /// Read bytes from guest memory into `buf`, returning `false` if the range is not fully accessible.
+    fn read(&self, gpa: GuestPhysAddr, buf: &mut [u8]) -> bool;
+    /// Write bytes from `buf` into guest memory, returning `false` if the range is not fully accessible.
+    fn write(&self, gpa: GuestPhysAddr, buf: &[u8]) -> bool;
+}
+
+/// The guest memory of a virtual machine, accessed with the [`guest_memory`](crate::guest_memory) API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VmMemory(pub VMId);
+
+impl GuestMemoryAccess for VmMemory {
+    fn read(&self, gpa: GuestPhysAddr, buf: &mut [u8]) -> bool {
+        crate::guest_memory::read_guest_memory(self.0, gpa, buf) == buf.len()
+    }
+
+    fn write(&self, gpa: GuestPhysAddr, buf: &[u8]) -> bool {
+        crate::guest_memory::write_guest_memory(self.0, gpa, buf) == buf.len()
+    }
+}
+
+/// Read a little-endian value from guest memory.
+fn read_le<T: GuestPrimitive>(
+    mem: &impl GuestMemoryAccess,
+    gpa: GuestPhysAddr,
+) -> AxvisorResult<T> {
+    let mut bytes = T::Bytes::default();
+    if mem.read(gpa, bytes.as_mut()) {
+        Ok(T::from_bytes(bytes, Endianness::Little))
+    } else {
+        Err(AxvisorError::BadAddress)
+    }
+}
+
+/// Write a little-endian value into guest memory.
+fn write_le<T: GuestPrimitive>(
+    mem: &impl GuestMemoryAccess,
+    gpa: GuestPhysAddr,
+    value: T,
+) -> AxvisorResult {
+    if mem.write(gpa, value.to_bytes(Endianness::Little).as_ref()) {
+        Ok(())
+    } else {
+        Err(AxvisorError::BadAddress)
+    }
+}
+
+/// The descriptor continues via the next field.
+pub const VIRTQ_DESC_F_NEXT: u16 = 1;
+/// The buffer is device write-only, otherwise device read-only.
+pub const VIRTQ_DESC_F_WRITE: u16 = 2;
+/// The buffer contains a table of descriptors.
+pub const VIRTQ_DESC_F_INDIRECT: u16 = 4;
+/// The descriptor is available, in packed virtqueues.
+pub const VIRTQ_DESC_F_AVAIL: u16 = 1 << 7;
+/// The descriptor is used, in packed virtqueues.
+pub const VIRTQ_DESC_F_USED: u16 = 1 << 15;
+
+/// Size of a descriptor in both layouts.
+const DESC_SIZE: usize = 16;
+
+/// A buffer described by a descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Descriptor {
+    /// Guest physical address of the buffer.
+    pub addr: GuestPhysAddr,
+    /// Length of the buffer in bytes.
+    pub len: u32,
+    /// Flags of the descriptor, `VIRTQ_DESC_F_*`.
+    pub flags: u16,
+}
+
+impl Descriptor {
+    /// Check whether the buffer is written by the device, otherwise it is read by the device.
+    pub fn is_write_only(&self) -> bool {
+        self.flags & VIRTQ_DESC_F_WRITE != 0
+    }
+
+    /// Read the buffer from guest memory, into `buf` of at most `len` bytes, returning the number of bytes read.
+    pub fn read(&self, mem: &impl GuestMemoryAccess, buf: &mut [u8]) -> AxvisorResult<usize> {
+        let len = buf.len().min(self.len as usize);
+        if mem.read(self.addr, &mut buf[..len]) {
+            Ok(len)
+        } else {
+            Err(AxvisorError::BadAddress)
+        }
+    }
+
+    /// Write `buf` of at most `len` bytes into the buffer in guest memory, returning the number of bytes written.
+    pub fn write(&self, mem: &impl GuestMemoryAccess, buf: &[u8]) -> AxvisorResult<usize> {
+        let len = buf.len().min(self.len as usize);
+        if mem.write(self.addr, &buf[..len]) {
+            Ok(len)
+        } else {
+            Err(AxvisorError::BadAddress)
+        }
+    }
+}
+
+/// A chain of descriptors made available by the driver, describing the device-readable buffers followed by the
+/// device-writable buffers of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorChain {
+    /// ID of the chain, i.e. the index of the head descriptor in split virtqueues, or the buffer ID in packed
+    /// virtqueues.
+    pub id: u16,
+    /// The descriptors, with indirect tables resolved.
+    pub descriptors: Vec<Descriptor>,
+    /// Number of descriptors the chain occupies in the descriptor ring of a packed virtqueue.
+    ring_slots: u16,
+}
+
+impl DescriptorChain {
+    /// Get the device-readable descriptors.
+    pub fn readable(&self) -> impl Iterator<Item = &Descriptor> {
+        self.descriptors.iter().filter(|desc| !desc.is_write_only())
+    }
+
+    /// Get the device-writable descriptors.
+    pub fn writable(&self) -> impl Iterator<Item = &Descriptor> {
+        self.descriptors.iter().filter(|desc| desc.is_write_only())
+    }
+
+    /// Get the total length of the device-readable buffers.
+    pub fn readable_len(&self) -> u64 {
+        self.readable().map(|desc| desc.len as u64).sum()
+    }
+
+    /// Get the total length of the device-writable buffers.
+    pub fn writable_len(&self) -> u64 {
+        self.writable().map(|desc| desc.len as u64).sum()
+    }
+
+    /// Append a descriptor, checking that device-readable ones never follow device-writable ones.
+    fn push(&mut self, desc: Descriptor) -> AxvisorResult {
+        if !desc.is_write_only()
+            && self
+                .descriptors
+                .last()
+                .is_some_and(Descriptor::is_write_only)
+        {
+            return Err(AxvisorError::InvalidArg);
+        }
+        self.descriptors.push(desc);
+        Ok(())
+    }
+
+    /// Append the descriptors in an indirect table, whose entries are in the layout of split virtqueues, and only the
+    /// ones of split virtqueues are linked by their `next` fields.
+    ///
+    /// The length of the table is controlled by the guest, so the chain is bounded by `queue_size` descriptors, as
+    /// required by the specification, before the table is read.
+    fn push_indirect(
+        &mut self,
+        mem: &impl GuestMemoryAccess,
+        table: Descriptor,
+        split: bool,
+        queue_size: u16,
+    ) -> AxvisorResult {
+        if table.len == 0 || table.len as usize % DESC_SIZE != 0 {
+            return Err(AxvisorError::InvalidArg);
+        }
+
+        let count = table.len as usize / DESC_SIZE;
+        if self.descriptors.len() + count > queue_size as usize {
+            return Err(AxvisorError::InvalidArg);
+        }
+        let mut index = 0;
+        for _ in 0..count {
+            let (desc, next) = read_split_desc(mem, table.addr, index)?;
+            if desc.flags & VIRTQ_DESC_F_INDIRECT != 0 {
+                return Err(AxvisorError::InvalidArg);
+            }
+            self.push(desc)?;
+
+            if split && desc.flags & VIRTQ_DESC_F_NEXT == 0 {
+                return Ok(());
+            }
+            index = if split { next as usize } else { index + 1 };
+            if index >= count {
+                return if split {
+                    Err(AxvisorError::InvalidArg)
+                } else {
+                    Ok(())
+                };
+            }
+        }
+
+        // A loop in the table of a split virtqueue.
+        if split {
+            Err(AxvisorError::InvalidArg)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Read a descriptor in the layout of split virtqueues from a table, returning it with its `next` field.
+fn read_split_desc(
+    mem: &impl GuestMemoryAccess,
+    table: GuestPhysAddr,
+    index: usize,
+) -> AxvisorResult<(Descriptor, u16)> {
+    let base = table + index * DESC_SIZE;
+    let desc = Descriptor {
+        addr: GuestPhysAddr::from_usize(read_le::<u64>(mem, base)? as usize),
+        len: read_le(mem, base + 8)?,
+        flags: read_le(mem, base + 12)?,
+    };
+    Ok((desc, read_le(mem, base + 14)?))
+}
+
+/// Check whether an event index is crossed when an index moves from `old` to `new`, as `vring_need_event` in the
+/// virtio specification.
+fn need_event(event: u16, new: u16, old: u16) -> bool {
+    new.wrapping_sub(event).wrapping_sub(1) < new.wrapping_sub(old)
+}
+
+/// Check the size of a virtqueue, which is at most 32768.
+fn check_queue_size(size: u16, power_of_two: bool) -> AxvisorResult {
+    if size == 0 || size > 0x8000 || (power_of_two && !size.is_power_of_two()) {
+        Err(AxvisorError::InvalidArg)
+    } else {
+        Ok(())
+    }
+}
+
+/// The driver does not want interrupts, in the flags of the available ring of split virtqueues.
+const VIRTQ_AVAIL_F_NO_INTERRUPT: u16 = 1;
+/// The device does not want notifications, in the flags of the used ring of split virtqueues.
+const VIRTQ_USED_F_NO_NOTIFY: u16 = 1;
+
+/// The device side of a split virtqueue.
+#[derive(Debug)]
+pub struct SplitQueue {
+    /// Number of entries, a power of two.
+    size: u16,
+    /// Address of the descriptor table.
+    desc_table: GuestPhysAddr,
+    /// Address of the available ring.
+    avail_ring: GuestPhysAddr,
+    /// Address of the used ring.
+    used_ring: GuestPhysAddr,
+    /// Whether `VIRTIO_F_EVENT_IDX` is negotiated.
+    event_idx: bool,
+    /// Index of the next entry to be popped from the available ring.
+    next_avail: u16,
+    /// Index of the next entry to be pushed to the used ring.
+    next_used: u16,
+    /// Value of `next_used` when the driver was last considered for notification.
+    signalled_used: u16,
+}
+
+impl SplitQueue {
+    /// Create the device side of a split virtqueue of `size` entries, with the addresses of the descriptor table, and
+    /// the available and used rings configured by the driver.
+    ///
+    /// Fails with [`InvalidArg`](AxvisorError::InvalidArg) if `size` is not a power of two up to 32768.
+    pub fn new(
+        size: u16,
+        desc_table: GuestPhysAddr,
+        avail_ring: GuestPhysAddr,
+        used_ring: GuestPhysAddr,
+        event_idx: bool,
+    ) -> AxvisorResult<Self> {
+        check_queue_size(size, true)?;
+        Ok(Self {
+            size,
+            desc_table,
+            avail_ring,
+            used_ring,
+            event_idx,
+            next_avail: 0,
+            next_used: 0,
+            signalled_used: 0,
+        })
+    }
+
+    /// Get the number of entries.
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// Pop the next descriptor chain made available by the driver, or `None` if there is none.
+    ///
+    /// Fails with [`BadAddress`](AxvisorError::BadAddress) if the virtqueue or a descriptor table is not accessible,
+    /// or with [`InvalidArg`](AxvisorError::InvalidArg) if the chain is malformed, e.g. it loops or is longer than the
+    /// queue.
+    pub fn pop_avail(
+        &mut self,
+        mem: &impl GuestMemoryAccess,
+    ) -> AxvisorResult<Option<DescriptorChain>> {
+        let avail_idx: u16 = read_le(mem, self.avail_ring + 2)?;
+        if avail_idx == self.next_avail {
+            return Ok(None);
+        }
+        if avail_idx.wrapping_sub(self.next_avail) > self.size {
+            return Err(AxvisorError::InvalidArg);
+        }
+        // Read the ring entry and the descriptors after the index.
+        fence(Ordering::Acquire);
+
+        let slot = (self.next_avail % self.size) as usize;
+        let head: u16 = read_le(mem, self.avail_ring + 4 + slot * 2)?;
+        self.next_avail = self.next_avail.wrapping_add(1);
+        if self.event_idx {
+            write_le(
+                mem,
+                self.used_ring + 4 + self.size as usize * 8,
+                self.next_avail,
+            )?;
+        }
+
+        self.read_chain(mem, head).map(Some)
+    }
+
+    /// Read the descriptor chain starting at `head`.
+    fn read_chain(
+        &self,
+        mem: &impl GuestMemoryAccess,
+        head: u16,
+    ) -> AxvisorResult<DescriptorChain> {
+        let mut chain = DescriptorChain {
+            id: head,
+            descriptors: Vec::new(),
+            ring_slots: 0,
+        };
+
+        let mut index = head;
+        for _ in 0..self.size {
+            if index >= self.size {
+                return Err(AxvisorError::InvalidArg);
+            }
+            let (desc, next) = read_split_desc(mem, self.desc_table, index as usize)?;
+            chain.ring_slots += 1;
+
+            if desc.flags & VIRTQ_DESC_F_INDIRECT != 0 {
+                if desc.flags & VIRTQ_DESC_F_NEXT != 0 {
+                    return Err(AxvisorError::InvalidArg);
+                }
+                chain.push_indirect(mem, desc, true, self.size)?;
+                return Ok(chain);
+            }
+
+            chain.push(desc)?;
+            if desc.flags & VIRTQ_DESC_F_NEXT == 0 {
+                return Ok(chain);
+            }
+            index = next;
+        }
+
+        // The chain loops.
+        Err(AxvisorError::InvalidArg)
+    }
+
+    /// Return a descriptor chain to the driver, with the number of bytes written into its device-writable buffers.
+    ///
+    /// Fails with [`BadAddress`](AxvisorError::BadAddress) if the used ring is not accessible.
+    pub fn push_used(
+        &mut self,
+        mem: &impl GuestMemoryAccess,
+        chain: &DescriptorChain,
+        len: u32,
+    ) -> AxvisorResult {
+        let slot = (self.next_used % self.size) as usize;
+        let elem = self.used_ring + 4 + slot * 8;
+        write_le(mem, elem, chain.id as u32)?;
+        write_le(mem, elem + 4, len)?;
+
+        // Publish the element before the index.
+        fence(Ordering::Release);
+        self.next_used = self.next_used.wrapping_add(1);
+        write_le(mem, self.used_ring + 2, self.next_used)
+    }
+
+    /// Check whether the driver should be notified of the chains pushed since the last check.
+    ///
+    /// Fails with [`BadAddress`](AxvisorError::BadAddress) if the available ring is not accessible.
+    pub fn needs_notification(&mut self, mem: &impl GuestMemoryAccess) -> AxvisorResult<bool> {
+        // Read the suppression fields after publishing the used entries.
+        fence(Ordering::SeqCst);
+        let old = core::mem::replace(&mut self.signalled_used, self.next_used);
+        if self.event_idx {
+            let used_event: u16 = read_le(mem, self.avail_ring + 4 + self.size as usize * 2)?;
+            Ok(need_event(used_event, self.next_used, old))
+        } else {
+            let flags: u16 = read_le(mem, self.avail_ring)?;
+            Ok(old != self.next_used && flags & VIRTQ_AVAIL_F_NO_INTERRUPT == 0)
+        }
+    }
+
+    /// Enable or disable the notifications from the driver when it makes descriptor chains available.
+    ///
+    /// Fails with [`BadAddress`](AxvisorError::BadAddress) if the used ring is not accessible.
+    pub fn set_notification(
+        &mut self,
+        mem: &impl GuestMemoryAccess,
+        enable: bool,
+    ) -> AxvisorResult {
+        if self.event_idx {
+            // The driver notifies when it makes the entry at `avail_event` available, so "disabling" leaves it behind.
+            let avail_event = if enable {
+                self.next_avail
+            } else {
+                self.next_avail.wrapping_sub(1)
+            };
+            write_le(
+                mem,
+                self.used_ring + 4 + self.size as usize * 8,
+                avail_event,
+            )
+        } else {
+            let flags = if enable { 0 } else { VIRTQ_USED_F_NO_NOTIFY };
+            write_le(mem, self.used_ring, flags)
+        }
+    }
+}
+
+/// Events are enabled, in the event suppression structures of packed virtqueues.
+const RING_EVENT_FLAGS_ENABLE: u16 = 0;
+/// Events are disabled.
+const RING_EVENT_FLAGS_DISABLE: u16 = 1;
+/// An event is wanted for a specific descriptor.
+const RING_EVENT_FLAGS_DESC: u16 = 2;
+
+/// The device side of a packed virtqueue.
+#[derive(Debug)]
+pub struct PackedQueue {
+    /// Number of entries.
+    size: u16,
+    /// Address of the descriptor ring.
+    desc_ring: GuestPhysAddr,
+    /// Address of the driver event suppression structure.
+    driver_event: GuestPhysAddr,
+    /// Address of the device event suppression structure.
+    device_event: GuestPhysAddr,
+    /// Index of the next descriptor to be made available by the driver.
+    next_avail: u16,
+    /// Wrap counter of `next_avail`.
+    avail_wrap: bool,
+    /// Index of the next descriptor to be written back as used.
+    next_used: u16,
+    /// Wrap counter of `next_used`.
+    used_wrap: bool,
+    /// Number of descriptors written back as used since the last check for notification.
+    unsignalled: u16,
+}
+
+impl PackedQueue {
+    /// Create the device side of a packed virtqueue of `size` entries, with the addresses of the descriptor ring, and
+    /// the driver and device event suppression structures configured by the driver.
+    ///
+    /// Fails with [`InvalidArg`](AxvisorError::InvalidArg) if `size` is zero or greater than 32768.
+    pub fn new(
+        size: u16,
+        desc_ring: GuestPhysAddr,
+        driver_event: GuestPhysAddr,
+        device_event: GuestPhysAddr,
+    ) -> AxvisorResult<Self> {
+        check_queue_size(size, false)?;
+        Ok(Self {
+            size,
+            desc_ring,
+            driver_event,
+            device_event,
+            next_avail: 0,
+            avail_wrap: true,
+            next_used: 0,
+            used_wrap: true,
+            unsignalled: 0,
+        })
+    }
+
+    /// Get the number of entries.
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// Read the descriptor at `index` of the ring, returning it with its buffer ID.
+    fn read_desc(
+        &self,
+        mem: &impl GuestMemoryAccess,
+        index: u16,
+    ) -> AxvisorResult<(Descriptor, u16)> {
+        let base = self.desc_ring + index as usize * DESC_SIZE;
+        let desc = Descriptor {
+            addr: GuestPhysAddr::from_usize(read_le::<u64>(mem, base)? as usize),
+            len: read_le(mem, base + 8)?,
+            flags: read_le(mem, base + 14)?,
+        };
+        Ok((desc, read_le(mem, base + 12)?))
+    }
+
+    /// Pop the next descriptor chain made available by the driver, or `None` if there is none.
+    ///
+    /// Fails with [`BadAddress`](AxvisorError::BadAddress) if the virtqueue or a descriptor table is not accessible,
+    /// or with [`InvalidArg`](AxvisorError::InvalidArg) if the chain is malformed, e.g. it is longer than the queue.
+    pub fn pop_avail(
+        &mut self,
+        mem: &impl GuestMemoryAccess,
+    ) -> AxvisorResult<Option<DescriptorChain>> {
+        let flags: u16 = read_le(
+            mem,
+            self.desc_ring + self.next_avail as usize * DESC_SIZE + 14,
+        )?;
+        let avail = flags & VIRTQ_DESC_F_AVAIL != 0;
+        let used = flags & VIRTQ_DESC_F_USED != 0;
+        if avail != self.avail_wrap || used == self.avail_wrap {
+            return Ok(None);
+        }
+        // Read the descriptors after the flags of the head.
+        fence(Ordering::Acquire);
+
+        let mut chain = DescriptorChain {
+            id: 0,
+            descriptors: Vec::new(),
+            ring_slots: 0,
+        };
+        let mut index = self.next_avail;
+        loop {
+            if chain.ring_slots >= self.size {
+                return Err(AxvisorError::InvalidArg);
+            }
+            let (desc, id) = self.read_desc(mem, index)?;
+            chain.ring_slots += 1;
+            chain.id = id;
+            index = if index + 1 == self.size { 0 } else { index + 1 };
+
+            if desc.flags & VIRTQ_DESC_F_INDIRECT != 0 {
+                if desc.flags & VIRTQ_DESC_F_NEXT != 0 || !chain.descriptors.is_empty() {
+                    return Err(AxvisorError::InvalidArg);
+                }
+                chain.push_indirect(mem, desc, false, self.size)?;
+                break;
+            }
+
+            chain.push(desc)?;
+            if desc.flags & VIRTQ_DESC_F_NEXT == 0 {
+                break;
+            }
+        }
+
+        let (next_avail, wrapped) = self.advance(self.next_avail, chain.ring_slots);
+        self.next_avail = next_avail;
+        self.avail_wrap ^= wrapped;
+        Ok(Some(chain))
+    }
+
+    /// Advance an index in the ring, returning the new index, and whether it wraps around.
+    fn advance(&self, index: u16, count: u16) -> (u16, bool) {
+        let next = index as u32 + count as u32;
+        if next >= self.size as u32 {
+            ((next - self.size as u32) as u16, true)
+        } else {
+            (next as u16, false)
+        }
+    }
+
+    /// Return a descriptor chain to the driver, with the number of bytes written into its device-writable buffers.
+    ///
+    /// Chains should be returned in the order they are popped. Fails with [`BadAddress`](AxvisorError::BadAddress) if
+    /// the descriptor ring is not accessible.
+    pub fn push_used(
+        &mut self,
+        mem: &impl GuestMemoryAccess,
+        chain: &DescriptorChain,
+        len: u32,
+    ) -> AxvisorResult {
+        let base = self.desc_ring + self.next_used as usize * DESC_SIZE;
+        write_le(mem, base + 8, len)?;
+        write_le(mem, base + 12, chain.id)?;
+
+        // Publish the descriptor with its flags, after the other fields.
+        fence(Ordering::Release);
+        let mut flags = if self.used_wrap {
+            VIRTQ_DESC_F_AVAIL | VIRTQ_DESC_F_USED
+        } else {
+            0
+        };
+        if len > 0 {
+            flags |= VIRTQ_DESC_F_WRITE;
+        }
+        write_le(mem, base + 14, flags)?;
+
+        let (next_used, wrapped) = self.advance(self.next_used, chain.ring_slots);
+        self.next_used = next_used;
+        self.used_wrap ^= wrapped;
+        self.unsignalled = self.unsignalled.saturating_add(chain.ring_slots);
+        Ok(())
+    }
+
+    /// Check whether the driver should be notified of the chains pushed since the last check.
+    ///
+    /// Fails with [`BadAddress`](AxvisorError::BadAddress) if the driver event suppression structure is not
+    /// accessible.
+    pub fn needs_notification(&mut self, mem: &impl GuestMemoryAccess) -> AxvisorResult<bool> {
+        // Read the suppression structure after publishing the used descriptors.
+        fence(Ordering::SeqCst);
+        let count = core::mem::take(&mut self.unsignalled);
+        let flags: u16 = read_le(mem, self.driver_event + 2)?;
+        match flags {
+            RING_EVENT_FLAGS_ENABLE => Ok(count > 0),
+            RING_EVENT_FLAGS_DISABLE => Ok(false),
+            RING_EVENT_FLAGS_DESC => {
+                let off_wrap: u16 = read_le(mem, self.driver_event)?;
+                let mut event = off_wrap & 0x7fff;
+                if (off_wrap >> 15 != 0) != self.used_wrap {
+                    event = event.wrapping_sub(self.size);
+                }
+                Ok(need_event(
+                    event,
+                    self.next_used,
+                    self.next_used.wrapping_sub(count),
+                ))
+            }
+            _ => Err(AxvisorError::InvalidArg),
+        }
+    }
+
+    /// Enable or disable the notifications from the driver when it makes descriptor chains available.
+    ///
+    /// Fails with [`BadAddress`](AxvisorError::BadAddress) if the device event suppression structure is not
+    /// accessible.
+    pub fn set_notification(
+        &mut self,
+        mem: &impl GuestMemoryAccess,
+        enable: bool,
+    ) -> AxvisorResult {
+        let flags = if enable {
+            RING_EVENT_FLAGS_ENABLE
+        } else {
+            RING_EVENT_FLAGS_DISABLE
+        };
+        write_le(mem, self.device_event + 2, flags)
+    }
+}